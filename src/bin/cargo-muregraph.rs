@@ -0,0 +1,18 @@
+//! `cargo muregraph`: runs the `muregraph` binary installed alongside, which analyzes the current
+//! workspace along with the configured repositories when invoked this way
+
+fn main() {
+    let exe = std::env::current_exe()
+        .map(|exe| exe.with_file_name(format!("muregraph{}", std::env::consts::EXE_SUFFIX)))
+        .unwrap_or_else(|_| "muregraph".into());
+    let status = std::process::Command::new(&exe)
+        .args(std::env::args_os().skip(1))
+        .status();
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Failed to run {:?}: {}", exe, e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Output of the commands printing their results as text, the library returning them as data
+
+use std::collections::BTreeMap;
+
+use muregraph::{
+    diff_sections, CrateInfo, CrateMetrics, LintResult, Metrics, Severity, Snapshot, SourceSize,
+    TopDependents,
+};
+
+/// Prints a table summarizing the results of all the lints that ran
+pub(crate) fn print_lint_summary(results: &[LintResult]) {
+    eprintln!();
+    eprintln!(
+        "{:<20} {:>8}  {:<8} {}",
+        console::style("lint").for_stderr().underlined(),
+        console::style("findings").for_stderr().underlined(),
+        console::style("severity").for_stderr().underlined(),
+        console::style("result").for_stderr().underlined(),
+    );
+    for r in results {
+        let result = if r.findings == 0 {
+            console::style("pass").green()
+        } else if r.severity == Severity::Error {
+            console::style("fail").red()
+        } else {
+            console::style("warn").yellow()
+        };
+        eprintln!(
+            "{:<20} {:>8}  {:<8} {}",
+            r.id,
+            r.findings,
+            r.severity.to_string(),
+            result.for_stderr().bold()
+        );
+    }
+}
+
+/// Prints the changes between two sets of repositories
+pub(crate) fn print_diff(
+    old: &BTreeMap<String, Vec<CrateInfo>>,
+    new: &BTreeMap<String, Vec<CrateInfo>>,
+) {
+    let sections = diff_sections(old, new);
+    if sections.is_empty() {
+        println!("No changes");
+    }
+    for (title, lines) in sections {
+        println!("{}:", title);
+        for l in lines {
+            println!("  {}", l);
+        }
+    }
+}
+
+/// Renders values as a line of bars of heights proportional to the values
+fn sparkline(values: &[usize]) -> String {
+    const BARS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|v| BARS[v * (BARS.len() - 1) / max])
+        .collect()
+}
+
+/// Print the snapshots as a table, followed by a sparkline for each statistic
+pub(crate) fn print_trends(snapshots: &[Snapshot]) {
+    println!(
+        "{:<20} {:>6} {:>6} {:>6} {:>9}",
+        "date", "crates", "edges", "cycles", "max depth"
+    );
+    for s in snapshots {
+        println!(
+            "{:<20} {:>6} {:>6} {:>6} {:>9}",
+            s.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            s.crates,
+            s.edges,
+            s.cycles,
+            s.max_depth
+        );
+    }
+    println!();
+    let trend = |f: fn(&Snapshot) -> usize| sparkline(&snapshots.iter().map(f).collect::<Vec<_>>());
+    println!("crates    {}", trend(|s| s.crates));
+    println!("edges     {}", trend(|s| s.edges));
+    println!("cycles    {}", trend(|s| s.cycles));
+    println!("max depth {}", trend(|s| s.max_depth));
+}
+
+/// Print the metrics as two tables, one for the crates and one for the repositories
+///
+/// The tables have a column with the number of lines of Rust if some crates were measured.
+pub(crate) fn print_metrics(metrics: &Metrics) {
+    let measured = metrics.crates.iter().any(|c| c.size.is_some());
+    let lines = |size: Option<SourceSize>| match (measured, size) {
+        (false, _) => String::new(),
+        (true, Some(size)) => format!(" {:>8}", size.lines),
+        (true, None) => format!(" {:>8}", "-"),
+    };
+    let header = match measured {
+        true => format!(" {:>8}", "lines"),
+        false => String::new(),
+    };
+    println!(
+        "{:<30} {:<20} {:>6} {:>7} {:>10} {:>5} {:>11}{}",
+        "crate", "repository", "fan-in", "fan-out", "dependents", "depth", "instability", header
+    );
+    for c in metrics.crates.iter() {
+        println!(
+            "{:<30} {:<20} {:>6} {:>7} {:>10} {:>5} {:>11.2}{}",
+            c.name,
+            c.repo,
+            c.fan_in,
+            c.fan_out,
+            c.dependents,
+            c.depth,
+            c.instability,
+            lines(c.size)
+        );
+    }
+    println!();
+    println!(
+        "{:<20} {:>6} {:>8} {:>8} {:>8}{}",
+        "repository", "crates", "internal", "outgoing", "incoming", header
+    );
+    for r in metrics.repos.iter() {
+        println!(
+            "{:<20} {:>6} {:>8} {:>8} {:>8}{}",
+            r.name,
+            r.crates,
+            r.internal_edges,
+            r.outgoing_edges,
+            r.incoming_edges,
+            lines(r.size)
+        );
+    }
+}
+
+/// Print the crates with the most dependents as a table, then a table for each repository
+pub(crate) fn print_top_dependents(top: &TopDependents) {
+    let table = |crates: &[&CrateMetrics]| {
+        println!(
+            "{:<30} {:<20} {:>10} {:>6}",
+            "crate", "repository", "dependents", "fan-in"
+        );
+        for c in crates {
+            println!(
+                "{:<30} {:<20} {:>10} {:>6}",
+                c.name, c.repo, c.dependents, c.fan_in
+            );
+        }
+    };
+    println!("Most depended-on crates:");
+    table(&top.overall);
+    for (repo, crates) in top.repos.iter() {
+        println!();
+        println!("Most depended-on crates of {}:", repo);
+        table(crates);
+    }
+}
@@ -0,0 +1,239 @@
+//! Owners of the crates, from the `CODEOWNERS` file of their repository
+//!
+//! The owners of a crate are those of its `Cargo.toml`, following GitHub's rules: the last
+//! matching pattern of the file wins, with gitignore-like patterns.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Where a `CODEOWNERS` file is looked for, relative to the root of the repository, by
+/// decreasing priority
+pub const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Rules of a `CODEOWNERS` file
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    /// Pattern and owners of each rule, in the order of the file
+    rules: Vec<(Pattern, Vec<String>)>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    /// Glob, without its leading and trailing slashes
+    glob: String,
+    /// Whether the glob is matched from the root only, rather than from any directory
+    anchored: bool,
+    /// Whether the glob only matches directories, ie. the files they contain
+    directory: bool,
+    /// Whether the glob only matches files, as GitHub's `docs/*` matches `docs/index.md` but not
+    /// `docs/guide/index.md`
+    files: bool,
+}
+
+impl CodeOwners {
+    /// Parse the contents of a `CODEOWNERS` file, skipping comments and blank lines
+    ///
+    /// Comments start with a `#` that is not escaped as `\#`, which patterns use for paths
+    /// containing a `#`.
+    pub fn parse(contents: &str) -> CodeOwners {
+        let rules = contents
+            .lines()
+            .filter_map(|line| {
+                let comment = line
+                    .match_indices('#')
+                    .map(|(i, _)| i)
+                    .find(|i| !line[..*i].ends_with('\\'))
+                    .unwrap_or(line.len());
+                let mut words = line[..comment].split_whitespace();
+                let pattern = words.next()?.replace("\\#", "#");
+                let directory = pattern.ends_with('/');
+                let pattern = pattern.trim_end_matches('/');
+                let anchored = pattern.starts_with('/') || pattern.contains('/');
+                let files = !directory && pattern.ends_with("/*");
+                let glob = pattern.trim_start_matches('/').to_string();
+                Some((
+                    Pattern {
+                        glob,
+                        anchored,
+                        directory,
+                        files,
+                    },
+                    words.map(String::from).collect(),
+                ))
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Owners of the file at `path`, relative to the root of the repository, empty if it has none
+    pub fn owners(&self, path: &Path) -> Vec<String> {
+        let path = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(&path))
+            .map(|(_, owners)| owners.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Pattern {
+    /// Whether the pattern matches the file at `path`, or one of the directories containing it
+    fn matches(&self, path: &str) -> bool {
+        let starts = std::iter::once(0).chain(path.match_indices('/').map(|(i, _)| i + 1));
+        let ends = path
+            .match_indices('/')
+            .map(|(i, _)| i)
+            .filter(|_| !self.files)
+            .chain((!self.directory).then_some(path.len()));
+        let ends = ends.collect::<Vec<_>>();
+        starts
+            .take(if self.anchored { 1 } else { usize::MAX })
+            .any(|start| {
+                ends.iter()
+                    .filter(|end| **end > start)
+                    .any(|end| glob_match(self.glob.as_bytes(), &path.as_bytes()[start..*end]))
+            })
+    }
+}
+
+/// Whether `text` matches `glob`, where `**` matches any string, `*` any string without a slash
+/// and `?` any character but a slash
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == b'/' && glob_match(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => matches!(text, [c, text @ ..] if *c != b'/' && glob_match(rest, text)),
+        [c, rest @ ..] => matches!(text, [t, text @ ..] if t == c && glob_match(rest, text)),
+    }
+}
+
+/// Root of the repository and priority of a `CODEOWNERS` file found at `path` in a tarball, whose
+/// files may all be in a top-level directory, the lower the priority the better
+pub fn tarball_location(path: &Path) -> Option<(usize, PathBuf)> {
+    LOCATIONS
+        .iter()
+        .enumerate()
+        .find_map(|(priority, location)| {
+            let location = Path::new(location);
+            let depth = path
+                .components()
+                .count()
+                .checked_sub(location.components().count())?;
+            let root = path.components().take(depth).collect::<PathBuf>();
+            (depth <= 1 && root.join(location) == path).then_some((priority, root))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owners(codeowners: &CodeOwners, path: &str) -> Vec<String> {
+        codeowners.owners(Path::new(path))
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let codeowners = CodeOwners::parse(
+            "# Default owners\n\
+             * @org/everyone\n\
+             \n\
+             *.rs @rustaceans # Rust code\n\
+             /crates/net/ @org/network @alice\n",
+        );
+        assert_eq!(owners(&codeowners, "README.md"), vec!["@org/everyone"]);
+        assert_eq!(owners(&codeowners, "src/lib.rs"), vec!["@rustaceans"]);
+        assert_eq!(
+            owners(&codeowners, "crates/net/Cargo.toml"),
+            vec!["@org/network", "@alice"]
+        );
+        assert_eq!(
+            owners(&codeowners, "./crates/net/src/lib.rs"),
+            vec!["@org/network", "@alice"]
+        );
+    }
+
+    #[test]
+    fn anchored_and_unanchored_patterns() {
+        let codeowners = CodeOwners::parse("/build/ @root\nlogs/ @logs\n**/gen/** @gen\n");
+        assert_eq!(owners(&codeowners, "build/Cargo.toml"), vec!["@root"]);
+        assert!(owners(&codeowners, "sub/build/Cargo.toml").is_empty());
+        assert_eq!(owners(&codeowners, "logs/Cargo.toml"), vec!["@logs"]);
+        assert_eq!(owners(&codeowners, "a/b/gen/c/Cargo.toml"), vec!["@gen"]);
+    }
+
+    #[test]
+    fn github_documented_examples() {
+        let codeowners = CodeOwners::parse(
+            "*       @global-owner1 @global-owner2\n\
+             *.js    @js-owner #This is an inline comment.\n\
+             **/logs @logs-owner\n\
+             /build/logs/ @doctocat\n\
+             docs/* docs@example.com\n\
+             apps/ @octocat\n\
+             /scripts/ @doctocat @octocat\n\
+             /apps/github\n",
+        );
+        let global = vec!["@global-owner1", "@global-owner2"];
+        assert_eq!(owners(&codeowners, "README.md"), global);
+        assert_eq!(owners(&codeowners, "src/index.js"), vec!["@js-owner"]);
+        assert_eq!(owners(&codeowners, "build/logs/a/b.txt"), vec!["@doctocat"]);
+        assert_eq!(
+            owners(&codeowners, "docs/getting-started.md"),
+            vec!["docs@example.com"]
+        );
+        assert_eq!(
+            owners(&codeowners, "docs/build-app/troubleshooting.md"),
+            global
+        );
+        assert_eq!(owners(&codeowners, "apps/a.rs"), vec!["@octocat"]);
+        assert_eq!(owners(&codeowners, "src/apps/a.rs"), vec!["@octocat"]);
+        assert_eq!(
+            owners(&codeowners, "deeply/nested/logs/a.txt"),
+            vec!["@logs-owner"]
+        );
+        assert_eq!(
+            owners(&codeowners, "scripts/build.sh"),
+            vec!["@doctocat", "@octocat"]
+        );
+        assert!(owners(&codeowners, "apps/github/Cargo.toml").is_empty());
+    }
+
+    #[test]
+    fn escaped_hashes_are_not_comments() {
+        let codeowners = CodeOwners::parse("\\#crates/ @hash # comment\n# crates/ @commented\n");
+        assert_eq!(owners(&codeowners, "#crates/Cargo.toml"), vec!["@hash"]);
+        assert!(owners(&codeowners, "crates/Cargo.toml").is_empty());
+    }
+
+    #[test]
+    fn codeowners_locations_in_tarballs() {
+        assert_eq!(
+            tarball_location(Path::new("repo-main/.github/CODEOWNERS")),
+            Some((0, PathBuf::from("repo-main")))
+        );
+        assert_eq!(
+            tarball_location(Path::new("CODEOWNERS")),
+            Some((1, PathBuf::new()))
+        );
+        assert_eq!(tarball_location(Path::new("a/b/CODEOWNERS")), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! Errors of the library, classified by what failed so that callers can react to each class
+
+use std::fmt;
+
+/// Result of the fallible functions of the library
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failure of the library, wrapping the underlying error and its context by class of failure
+///
+/// Both `Display` and `Debug` are those of the underlying [`anyhow::Error`], so that the class
+/// does not get in the way of reporting the error.
+#[derive(thiserror::Error)]
+pub enum Error {
+    /// The configuration could not be read, or is invalid
+    #[error(transparent)]
+    Config(anyhow::Error),
+    /// A repository could not be downloaded, cloned or listed
+    #[error(transparent)]
+    Fetch(anyhow::Error),
+    /// A downloaded archive could not be decompressed or unpacked
+    #[error(transparent)]
+    Decode(anyhow::Error),
+    /// A manifest, or the output of `cargo metadata`, could not be parsed
+    #[error(transparent)]
+    Parse(anyhow::Error),
+    /// The lints could not run
+    #[error(transparent)]
+    Lint(anyhow::Error),
+    /// The graph could not be rendered
+    #[error(transparent)]
+    Render(anyhow::Error),
+    /// The crates do not support the analysis, eg. because of a cycle or of a selector matching
+    /// no crate
+    #[error(transparent)]
+    Graph(anyhow::Error),
+    /// A snapshot could not be saved or loaded
+    #[error(transparent)]
+    Snapshot(anyhow::Error),
+}
+
+impl Error {
+    /// The underlying error, with its context
+    pub fn inner(&self) -> &anyhow::Error {
+        match self {
+            Error::Config(e)
+            | Error::Fetch(e)
+            | Error::Decode(e)
+            | Error::Parse(e)
+            | Error::Lint(e)
+            | Error::Render(e)
+            | Error::Graph(e)
+            | Error::Snapshot(e) => e,
+        }
+    }
+
+    /// Turn into the underlying error, with its context
+    pub fn into_inner(self) -> anyhow::Error {
+        match self {
+            Error::Config(e)
+            | Error::Fetch(e)
+            | Error::Decode(e)
+            | Error::Parse(e)
+            | Error::Lint(e)
+            | Error::Render(e)
+            | Error::Graph(e)
+            | Error::Snapshot(e) => e,
+        }
+    }
+
+    /// Wrap the underlying error with `context`, keeping the class of the error
+    pub fn context<C>(self, context: C) -> Error
+    where
+        C: fmt::Display + Send + Sync + 'static,
+    {
+        self.map(|e| e.context(context))
+    }
+
+    fn map(self, f: impl FnOnce(anyhow::Error) -> anyhow::Error) -> Error {
+        match self {
+            Error::Config(e) => Error::Config(f(e)),
+            Error::Fetch(e) => Error::Fetch(f(e)),
+            Error::Decode(e) => Error::Decode(f(e)),
+            Error::Parse(e) => Error::Parse(f(e)),
+            Error::Lint(e) => Error::Lint(f(e)),
+            Error::Render(e) => Error::Render(f(e)),
+            Error::Graph(e) => Error::Graph(f(e)),
+            Error::Snapshot(e) => Error::Snapshot(f(e)),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.inner(), f)
+    }
+}
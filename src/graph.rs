@@ -0,0 +1,86 @@
+//! Dependency graph between the crates of all the repositories, backed by petgraph
+//!
+//! The analyses of this crate and its consumers can run petgraph's algorithms (eg.
+//! `petgraph::algo::tarjan_scc`, `toposort` or `dominators`) on [`DependencyGraph::graph`]
+//! instead of each rebuilding adjacency structures from the dependencies of the crates.
+
+use std::collections::BTreeMap;
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::{all_crates, CrateInfo, DepKind, Dependency};
+
+/// Crate, as a node of the [`DependencyGraph`]
+#[derive(Clone, Copy, Debug)]
+pub struct CrateNode<'a> {
+    /// Repository the crate belongs to
+    pub repo: &'a str,
+    /// The crate itself
+    pub info: &'a CrateInfo,
+}
+
+/// Dependencies between the crates of all the repositories
+///
+/// There is a node for each crate, and an edge for each dependency on another crate of the
+/// repositories, weighted by the [`Dependency`] and thus by its [`DepKind`]. Dependencies on
+/// external crates, and of crates on themselves, are left out.
+pub struct DependencyGraph<'a> {
+    /// The graph itself, whose nodes are in the order of [`all_crates`] and edges in the order
+    /// of the dependencies of each crate
+    pub graph: DiGraph<CrateNode<'a>, &'a Dependency>,
+    indices: BTreeMap<&'a str, NodeIndex>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    /// Graph of the dependencies between the crates of `infos`
+    pub fn new(infos: &'a BTreeMap<String, Vec<CrateInfo>>) -> DependencyGraph<'a> {
+        let mut graph = DiGraph::new();
+        let mut indices = BTreeMap::new();
+        for (repo, info) in all_crates(infos) {
+            let idx = graph.add_node(CrateNode { repo, info });
+            indices.entry(&info.name as &str).or_insert(idx);
+        }
+        for (_, c) in all_crates(infos) {
+            let from = indices[&c.name as &str];
+            for d in c.deps.iter().filter(|d| d.name != c.name) {
+                if let Some(to) = indices.get(&d.name as &str) {
+                    graph.add_edge(from, *to, d);
+                }
+            }
+        }
+        DependencyGraph { graph, indices }
+    }
+
+    /// Index of the node of crate `name`, if it is one of the crates of the repositories
+    pub fn index(&self, name: &str) -> Option<NodeIndex> {
+        self.indices.get(name).copied()
+    }
+
+    /// Crate of node `idx`
+    pub fn node(&self, idx: NodeIndex) -> CrateNode<'a> {
+        self.graph[idx]
+    }
+
+    /// Returns, for each crate, the crates it directly depends on, only considering the
+    /// dependencies of the given kinds
+    pub fn adjacency(&self, kinds: &[DepKind]) -> BTreeMap<&'a str, Vec<&'a str>> {
+        let mut res = self
+            .graph
+            .node_weights()
+            .map(|n| (&n.info.name as &str, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+        // Edges are iterated in the order they were added, ie. that of the dependencies
+        for e in self.graph.raw_edges() {
+            if kinds.contains(&e.weight.kind) {
+                let deps = res
+                    .get_mut(&self.graph[e.source()].info.name as &str)
+                    .unwrap();
+                let to = &self.graph[e.target()].info.name as &str;
+                if !deps.contains(&to) {
+                    deps.push(to);
+                }
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,4856 @@
+//! Analysis of the crates of multiple repositories, and of the dependencies between them
+//!
+//! Repositories are fetched and their manifests parsed into [`CrateInfo`]s with a [`Fetcher`],
+//! keyed by repository name. The resulting map is the input of the analyses, of the lints run by
+//! [`sanity_check`] and of the renderers like [`render`].
+
+#![warn(missing_docs)]
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    io::{BufRead, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{ensure, Context};
+use sha2::Digest;
+
+pub mod codeowners;
+pub mod error;
+pub mod graph;
+pub mod lint;
+pub mod render;
+pub mod site;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod source;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use error::{Error, Result};
+pub use petgraph;
+
+/// Version of the JSON schema of [`export`] and of the [`Snapshot`]s, in their `schema_version`
+/// field
+///
+/// Fields may be added without changing the version, so readers must ignore the fields they do
+/// not know. The version is bumped when a field is removed, renamed, or changes meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Package metadata fields that can be required by the `required-metadata` lint
+pub const METADATA_FIELDS: &[&str] = &[
+    "authors",
+    "categories",
+    "description",
+    "documentation",
+    "homepage",
+    "keywords",
+    "license",
+    "repository",
+];
+
+/// Graphviz layout engines that can render the SVG output
+pub const GRAPHVIZ_ENGINES: &[&str] = &["dot", "neato", "fdp", "sfdp", "twopi"];
+
+/// Configuration file listing the repositories to analyze
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Config {
+    /// Repositories downloaded as tarballs, by name
+    #[serde(default)]
+    pub tarballs: BTreeMap<String, String>,
+
+    /// Repositories checked out locally, scanned for crates instead of being downloaded
+    #[serde(default)]
+    pub directories: BTreeMap<String, PathBuf>,
+
+    /// Git repositories, shallowly cloned from these URLs
+    #[serde(default)]
+    pub git: BTreeMap<String, String>,
+
+    /// Workspaces checked out locally, whose crates are listed by `cargo metadata`
+    #[serde(default, rename = "cargo-metadata")]
+    pub cargo_metadata: BTreeMap<String, PathBuf>,
+
+    /// Tag, branch or commit of the git repositories and of the GitHub and GitLab archives to
+    /// fetch, by name, the URLs of the archives being rewritten for their ref when loaded
+    #[serde(default)]
+    pub pins: BTreeMap<String, String>,
+
+    /// Groups and owners of the repositories, and the crates they deprecated, in the `[groups]`,
+    /// `[owners]` and `[deprecated]` sections
+    #[serde(flatten)]
+    pub ownership: Ownership,
+
+    /// Settings of the lints
+    #[serde(default)]
+    pub lints: LintConfig,
+
+    /// Architectural rules enforced by the `policy` lint
+    #[serde(default)]
+    pub policy: PolicyConfig,
+
+    /// Settings of the downloads
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Settings of the notifications of new lint findings and cycles
+    #[serde(default)]
+    pub notify: NotifyConfig,
+
+    /// How graphviz renders the SVG output
+    #[serde(default)]
+    pub graphviz: GraphvizConfig,
+}
+
+/// Who owns the repositories, shown on their clusters, in the lint findings and in the export,
+/// and which of their crates are deprecated
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Ownership {
+    /// Group of each repository, eg. the team or domain owning it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, String>,
+
+    /// Owner of each repository, and how to contact them, eg. "payments <payments@example.com>"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub owners: BTreeMap<String, String>,
+
+    /// Deprecated crates, by name, with what to use instead, eg. "use core-net instead", if
+    /// anything
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deprecated: BTreeMap<String, String>,
+}
+
+/// Settings of the lints, in the `[lints]` section of the configuration
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// Edition all crates are expected to be using, eg. "2021"
+    pub edition: Option<String>,
+
+    /// Licenses crates are allowed to use, eg. ["MIT", "Apache-2.0"]
+    pub licenses: Option<Vec<String>>,
+
+    /// Package metadata fields that published crates must set, among `METADATA_FIELDS`
+    pub required_metadata: Vec<String>,
+
+    /// Registries crates may be published to, "crates-io" allowing the default registry
+    pub allowed_registries: Option<Vec<String>>,
+
+    /// Report external crates that repositories depend on with incompatible versions
+    pub divergent_versions: bool,
+
+    /// Fail when requirements on crates of the repositories do not match their current version,
+    /// or would match their next incompatible version
+    pub semver_requirements: bool,
+
+    /// Fail when manifests were skipped with `--lenient`
+    pub invalid_manifests: bool,
+}
+
+impl LintConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        for field in self.required_metadata.iter() {
+            ensure!(
+                METADATA_FIELDS.contains(&field.as_str()),
+                "Unknown package metadata field {:?} in lints.required-metadata, known fields are {:?}",
+                field,
+                METADATA_FIELDS
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Architectural rules, in the `[policy]` section of the configuration
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Dependencies no crate may have, eg.
+    /// `[{ from = "repo:frontend-*", to = "crate:billing-core" }]`
+    pub deny: Vec<DenyRule>,
+
+    /// Layers of the architecture, from the top one to the bottom one, crates only being allowed
+    /// to depend on crates of their layer or of the layers below it
+    pub layers: Vec<Layer>,
+
+    /// Known cycles and dependencies accepted until they are fixed
+    pub exceptions: Vec<Exception>,
+}
+
+/// Dependencies forbidden from the crates selected by `from` to the crates selected by `to`
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DenyRule {
+    /// Crates the rule applies to, as `crate:pattern`, `repo:pattern` or `group:pattern`
+    pub from: String,
+
+    /// Dependencies these crates may not have, with the same syntax, external crates only being
+    /// selected by `crate:pattern`
+    pub to: String,
+
+    /// Why the dependencies are forbidden, shown along with the findings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Layer of the architecture, eg. `{ name = "services", crates = ["repo:*-service"] }`
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Layer {
+    /// Name of the layer, shown in the findings
+    pub name: String,
+
+    /// Crates in the layer, with the selectors of [`DenyRule`], a crate selected by several
+    /// layers being in the first one
+    pub crates: Vec<String>,
+}
+
+/// Known issue accepted by the lints until it expires, either a `cycle` or a dependency `from` a
+/// crate `to` another one, eg. `{ cycle = ["app", "core"], expires = "2026-12-31" }`
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Exception {
+    /// Crates of a cycle across repositories, or repositories of a cycle between repositories,
+    /// in any order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cycle: Vec<String>,
+
+    /// Crate with an accepted dependency denied by the policy, on a higher layer or on a
+    /// deprecated crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+
+    /// Crate it depends on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+
+    /// Who is to fix the issue, eg. "payments <payments@example.com>"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// Last day the issue is accepted, eg. "2026-12-31", after which the lints report it again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+}
+
+impl Exception {
+    /// What the exception accepts, eg. "cycle app, core"
+    pub fn accepted(&self) -> String {
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => format!("dependency {} -> {}", from, to),
+            _ => format!("cycle {}", self.cycle.join(", ")),
+        }
+    }
+
+    /// Last day the issue is accepted, if the exception expires
+    pub fn expires(&self) -> Result<Option<chrono::NaiveDate>> {
+        self.expires
+            .as_deref()
+            .map(|date| {
+                chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .with_context(|| {
+                        format!(
+                            "Invalid date {:?} in policy.exceptions, expected eg. \"2026-12-31\"",
+                            date
+                        )
+                    })
+                    .map_err(Error::Config)
+            })
+            .transpose()
+    }
+
+    /// Whether the exception expired before `today`
+    pub fn expired(&self, today: chrono::NaiveDate) -> bool {
+        self.expires()
+            .ok()
+            .flatten()
+            .is_some_and(|expires| expires < today)
+    }
+
+    /// Whether the exception accepts the cycle of crates or repositories `names`
+    pub fn accepts_cycle(&self, names: &[&str]) -> bool {
+        let mut cycle = self.cycle.iter().map(|c| c as &str).collect::<Vec<_>>();
+        let mut names = names.to_vec();
+        cycle.sort_unstable();
+        names.sort_unstable();
+        !cycle.is_empty() && cycle == names
+    }
+
+    /// Whether the exception accepts the dependency of crate `from` on crate `to`
+    pub fn accepts_dependency(&self, from: &str, to: &str) -> bool {
+        self.from.as_deref() == Some(from) && self.to.as_deref() == Some(to)
+    }
+}
+
+/// Kinds of crate selectors that the rules of the `[policy]` section can use
+const POLICY_SELECTORS: &[&str] = &["crate", "repo", "group"];
+
+impl PolicyConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        let deny = self.deny.iter().flat_map(|r| [&r.from, &r.to]);
+        let layers = self.layers.iter().flat_map(|l| l.crates.iter());
+        for (section, selector) in deny
+            .map(|s| ("deny", s))
+            .chain(layers.map(|s| ("layers", s)))
+        {
+            let kind = selector.split_once(':').map_or("crate", |(kind, _)| kind);
+            ensure!(
+                POLICY_SELECTORS.contains(&kind),
+                "Unknown selector {:?} in policy.{}, expected `crate:pattern`, `repo:pattern` or `group:pattern`",
+                selector,
+                section
+            );
+        }
+        let mut names = BTreeSet::new();
+        for layer in self.layers.iter() {
+            ensure!(
+                names.insert(&layer.name),
+                "Layer {:?} is listed several times in policy.layers",
+                layer.name
+            );
+        }
+        for exception in self.exceptions.iter() {
+            ensure!(
+                matches!(
+                    (exception.cycle.is_empty(), &exception.from, &exception.to),
+                    (false, None, None) | (true, Some(_), Some(_))
+                ),
+                "Exceptions in policy.exceptions need either a cycle, or both from and to, got {:?}",
+                exception
+            );
+            exception.expires().map_err(Error::into_inner)?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the notifications of new lint findings and cycles, in the `[notify]` section of
+/// the configuration
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotifyConfig {
+    /// URL the notifications are posted to, eg. that of a Slack incoming webhook
+    pub webhook: Option<String>,
+
+    /// JSON payload posted to the webhook, where `{{text}}` is replaced by the text of the
+    /// notification, inside a JSON string, and `{{summary}}` by the summary of the run as a JSON
+    /// object, `{"text": "{{text}}"}` if not set
+    pub template: Option<String>,
+}
+
+impl NotifyConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        self.payload("", "{}")
+            .map(|_| ())
+            .map_err(Error::into_inner)
+    }
+
+    /// Payload of the notification with `text`, for the run summarized by the JSON object
+    /// `summary`
+    pub fn payload(&self, text: &str, summary: &str) -> Result<String> {
+        let template = self
+            .template
+            .as_deref()
+            .unwrap_or(r#"{"text": "{{text}}"}"#);
+        // Escape the text as a JSON string, without its quotes
+        let text = serde_json::Value::from(text).to_string();
+        let payload = template
+            .replace("{{text}}", &text[1..text.len() - 1])
+            .replace("{{summary}}", summary);
+        serde_json::from_str::<serde_json::Value>(&payload)
+            .context("Invalid JSON in notify.template")
+            .map_err(Error::Config)?;
+        Ok(payload)
+    }
+}
+
+/// Settings of graphviz, in the `[graphviz]` section of the configuration
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GraphvizConfig {
+    /// Layout engine, among `GRAPHVIZ_ENGINES`, `dot` if not set
+    pub engine: Option<String>,
+
+    /// Extra arguments passed to the engine, eg. ["-Goverlap=prism"]
+    pub args: Vec<String>,
+}
+
+impl GraphvizConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(engine) = &self.engine {
+            ensure!(
+                GRAPHVIZ_ENGINES.contains(&engine.as_str()),
+                "Unknown graphviz engine {:?} in graphviz.engine, known engines are {:?}",
+                engine,
+                GRAPHVIZ_ENGINES
+            );
+        }
+        Ok(())
+    }
+
+    /// Command of the layout engine
+    pub fn engine(&self) -> &str {
+        self.engine.as_deref().unwrap_or("dot")
+    }
+
+    /// Whether the layout engine can be run, ie. graphviz is installed
+    pub fn installed(&self) -> bool {
+        std::process::Command::new(self.engine())
+            .arg("-V")
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok()
+    }
+}
+
+/// Settings of the downloads, in the `[network]` section of the configuration
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NetworkConfig {
+    /// Limits of the downloads of all the repositories
+    #[serde(flatten)]
+    pub limits: NetworkLimits,
+
+    /// Limits of some repositories, by name, overriding those of all the repositories, in the
+    /// `[network.repos.<name>]` sections
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub repos: BTreeMap<String, NetworkLimits>,
+}
+
+impl NetworkConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        self.limits.validate()?;
+        for (repo, limits) in self.repos.iter() {
+            limits
+                .validate()
+                .with_context(|| format!("Invalid limits of repository {}", repo))?;
+        }
+        Ok(())
+    }
+
+    /// Limits of the downloads of `repo`, those of `[network.repos.<repo>]` overriding those of
+    /// `[network]`
+    pub fn limits(&self, repo: &str) -> NetworkLimits {
+        let limits = self.limits.clone();
+        match self.repos.get(repo) {
+            Some(overrides) => NetworkLimits {
+                connect_timeout: overrides.connect_timeout.clone().or(limits.connect_timeout),
+                timeout: overrides.timeout.clone().or(limits.timeout),
+                max_download_size: overrides
+                    .max_download_size
+                    .clone()
+                    .or(limits.max_download_size),
+            },
+            None => limits,
+        }
+    }
+
+    /// HTTP client with the timeouts of the `[network]` section
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn client(&self) -> Result<reqwest::blocking::Client> {
+        self.limits.client()
+    }
+}
+
+/// Limits of the downloads, either of all the repositories or of one of them
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NetworkLimits {
+    /// How long to wait for the connection to a server, eg. "10s"
+    pub connect_timeout: Option<String>,
+
+    /// How long each download may take in total, from the connection to the end of the body,
+    /// eg. "5m", 30 seconds if not set
+    pub timeout: Option<String>,
+
+    /// Maximum size of the archive of each repository, eg. "500MB" or "2GiB"
+    pub max_download_size: Option<String>,
+}
+
+impl NetworkLimits {
+    fn validate(&self) -> anyhow::Result<()> {
+        self.timeouts().map_err(Error::into_inner)?;
+        self.max_download_size().map_err(Error::into_inner)?;
+        Ok(())
+    }
+
+    /// Connection timeout and total timeout of the downloads, if set
+    pub fn timeouts(&self) -> Result<(Option<Duration>, Option<Duration>)> {
+        let parse = |timeout: &Option<String>, field| {
+            timeout
+                .as_deref()
+                .map(|t| {
+                    humantime::parse_duration(t)
+                        .with_context(|| format!("Invalid duration {:?} in network.{}", t, field))
+                        .map_err(Error::Config)
+                })
+                .transpose()
+        };
+        Ok((
+            parse(&self.connect_timeout, "connect-timeout")?,
+            parse(&self.timeout, "timeout")?,
+        ))
+    }
+
+    /// Maximum size of the archive of each repository in bytes, if limited
+    pub fn max_download_size(&self) -> Result<Option<u64>> {
+        self.max_download_size
+            .as_deref()
+            .map(|size| {
+                parse_size(size)
+                    .with_context(|| {
+                        format!(
+                            "Invalid size {:?} in network.max-download-size, expected eg. \"500MB\"",
+                            size
+                        )
+                    })
+                    .map_err(Error::Config)
+            })
+            .transpose()
+    }
+
+    /// HTTP client with the timeouts of the configuration
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn client(&self) -> Result<reqwest::blocking::Client> {
+        let (connect_timeout, timeout) = self.timeouts()?;
+        let mut builder = reqwest::blocking::Client::builder();
+        if let Some(timeout) = connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder
+            .build()
+            .context("Failed to initialize reqwest")
+            .map_err(Error::Fetch)
+    }
+}
+
+/// Parses a number of bytes, possibly with a decimal or binary unit, eg. "500MB" or "2GiB"
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, unit) = size.split_at(
+        size.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(size.len()),
+    );
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+impl Config {
+    /// Names of all the repositories, downloaded or local
+    pub fn repos(&self) -> impl Iterator<Item = &str> {
+        self.tarballs
+            .keys()
+            .chain(self.directories.keys())
+            .chain(self.git.keys())
+            .chain(self.cargo_metadata.keys())
+            .map(|r| r as &str)
+    }
+
+    /// Only keep the repositories whose name matches `keep`
+    pub fn retain_repos(&mut self, keep: impl Fn(&str) -> bool) {
+        self.tarballs.retain(|repo, _| keep(repo));
+        self.directories.retain(|repo, _| keep(repo));
+        self.git.retain(|repo, _| keep(repo));
+        self.cargo_metadata.retain(|repo, _| keep(repo));
+    }
+
+    /// Source of each repository, depending on the section it is listed in
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn sources(&self) -> BTreeMap<&str, Box<dyn source::Source>> {
+        let mut sources = BTreeMap::<&str, Box<dyn source::Source>>::new();
+        for (repo, url) in self.tarballs.iter() {
+            sources.insert(
+                repo,
+                Box::new(source::Tarball {
+                    url: url.clone(),
+                    rev: self.pins.get(repo).cloned(),
+                }),
+            );
+        }
+        for (repo, path) in self.directories.iter() {
+            sources.insert(repo, Box::new(source::Directory { path: path.clone() }));
+        }
+        for (repo, url) in self.git.iter() {
+            sources.insert(
+                repo,
+                Box::new(source::Git {
+                    url: url.clone(),
+                    rev: self.pins.get(repo).cloned(),
+                }),
+            );
+        }
+        for (repo, path) in self.cargo_metadata.iter() {
+            sources.insert(repo, Box::new(source::CargoMetadata { path: path.clone() }));
+        }
+        sources
+    }
+}
+
+/// Where a crate can be published, according to its `publish` field
+///
+/// It is serialized like the `publish` field: `false`, `true` or a list of registries.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(from = "PublishField", into = "PublishField")]
+pub enum Publish {
+    /// `publish = false`
+    Nowhere,
+    /// The default registry, crates.io, when `publish` is not set
+    Default,
+    /// The listed registries
+    At(Vec<String>),
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum PublishField {
+    Flag(bool),
+    Registries(Vec<String>),
+}
+
+impl From<PublishField> for Publish {
+    fn from(field: PublishField) -> Publish {
+        match field {
+            PublishField::Flag(false) => Publish::Nowhere,
+            PublishField::Flag(true) => Publish::Default,
+            PublishField::Registries(registries) => Publish::At(registries),
+        }
+    }
+}
+
+impl From<Publish> for PublishField {
+    fn from(publish: Publish) -> PublishField {
+        match publish {
+            Publish::Nowhere => PublishField::Flag(false),
+            Publish::Default => PublishField::Flag(true),
+            Publish::At(registries) => PublishField::Registries(registries),
+        }
+    }
+}
+
+impl std::fmt::Display for Publish {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Publish::Nowhere => write!(f, "unpublished"),
+            Publish::Default => write!(f, "published to the default registry"),
+            Publish::At(registries) => write!(f, "published to {}", registries.join(", ")),
+        }
+    }
+}
+
+/// Section of the manifest a dependency is declared in
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepKind {
+    /// `[dependencies]`
+    Normal,
+    /// `[dev-dependencies]`
+    Dev,
+    /// `[build-dependencies]`
+    Build,
+}
+
+impl DepKind {
+    /// Section of the manifest the dependencies of this kind are declared in
+    pub fn section(self) -> &'static str {
+        match self {
+            DepKind::Normal => "dependencies",
+            DepKind::Dev => "dev-dependencies",
+            DepKind::Build => "build-dependencies",
+        }
+    }
+}
+
+impl std::fmt::Display for DepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DepKind::Normal => write!(f, "normal"),
+            DepKind::Dev => write!(f, "dev"),
+            DepKind::Build => write!(f, "build"),
+        }
+    }
+}
+
+/// Size of Rust sources
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SourceSize {
+    /// Number of `.rs` files
+    pub files: u64,
+    /// Total size of the files, in bytes
+    pub bytes: u64,
+    /// Total number of lines of the files
+    pub lines: u64,
+}
+
+impl SourceSize {
+    /// Size of a single file with `contents`
+    pub fn of(contents: &[u8]) -> SourceSize {
+        let newlines = contents.iter().filter(|b| **b == b'\n').count() as u64;
+        let unterminated = contents.last().is_some_and(|b| *b != b'\n');
+        SourceSize {
+            files: 1,
+            bytes: contents.len() as u64,
+            lines: newlines + unterminated as u64,
+        }
+    }
+}
+
+impl std::ops::AddAssign for SourceSize {
+    fn add_assign(&mut self, other: SourceSize) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+    }
+}
+
+/// Kind of target a package builds
+#[derive(
+    Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetKind {
+    /// `[lib]` or `src/lib.rs`
+    Lib,
+    /// `[[bin]]`, `src/main.rs` or the files of `src/bin`
+    Bin,
+    /// `[lib]` with `proc-macro = true`
+    ProcMacro,
+}
+
+/// Dependency of a crate, as declared in its manifest
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Dependency {
+    /// Name of the crate depended on, even if it is renamed
+    pub name: String,
+    /// Key the dependency is declared under in the manifest, if it is renamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Whether the dependency has a `path`
+    #[serde(rename = "path")]
+    pub has_path: bool,
+    /// Registry the dependency comes from, if not the default one
+    #[serde(rename = "registry")]
+    pub from: Option<String>,
+    /// Git repository the dependency comes from
+    pub git: Option<String>,
+    /// Version requirement, "*" if none is set
+    pub req: String,
+    /// Section the dependency is declared in
+    pub kind: DepKind,
+    /// Whether the dependency is optional, only built when a feature enables it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// Features of the crate enabling the optional dependency, directly or through other
+    /// features, eg. "default" if it is built by default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_by: Vec<String>,
+}
+
+impl Dependency {
+    /// Key the dependency is declared under in the manifest, its name unless it is renamed
+    pub fn key(&self) -> &str {
+        self.key.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the dependency is built with the default features of the crate, ie. it is not
+    /// optional or the `default` feature enables it
+    pub fn enabled_by_default(&self) -> bool {
+        !self.optional || self.enabled_by.iter().any(|f| f == "default")
+    }
+}
+
+/// Features of `features`, the `[features]` table of a crate, enabling its optional dependency
+/// declared as `key`, directly or through other features, sorted
+///
+/// Unless some feature enables it with `dep:key`, the dependency also gets an implicit feature
+/// named after it.
+pub(crate) fn enabling_features(
+    features: &BTreeMap<String, Vec<String>>,
+    key: &str,
+) -> Vec<String> {
+    let explicit = format!("dep:{}", key);
+    let mut enabling = BTreeSet::new();
+    if !features.values().flatten().any(|v| *v == explicit) {
+        enabling.insert(key);
+    }
+    // Weak dependency features (`key?/feature`) do not enable the dependency
+    let enables = |value: &str, enabling: &BTreeSet<&str>| {
+        value == explicit
+            || value.split_once('/').is_some_and(|(dep, _)| dep == key)
+            || enabling.contains(value)
+    };
+    loop {
+        let new = features
+            .iter()
+            .filter(|(f, values)| {
+                !enabling.contains(f as &str) && values.iter().any(|v| enables(v, &enabling))
+            })
+            .map(|(f, _)| f as &str)
+            .collect::<Vec<_>>();
+        if new.is_empty() {
+            break;
+        }
+        enabling.extend(new);
+    }
+    enabling.into_iter().map(String::from).collect()
+}
+
+/// Crate found in a repository, with the information of its manifest that muregraph uses
+///
+/// Its serialized representation is the one of `muregraph export`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CrateInfo {
+    /// Name of the package
+    pub name: String,
+    /// Version of the package
+    pub version: String,
+    /// Edition of the package, "2015" if not set
+    pub edition: String,
+    /// License expression of the package
+    pub license: Option<String>,
+    /// Description of the package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Keywords of the package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Package metadata fields set in the manifest, among `METADATA_FIELDS`
+    #[serde(rename = "metadata", default)]
+    pub metadata_fields: Vec<String>,
+    /// Where the crate can be published
+    #[serde(rename = "publish")]
+    pub published_to: Publish,
+    /// Dependencies of all the kinds, including the target-specific ones
+    #[serde(rename = "dependencies")]
+    pub deps: Vec<Dependency>,
+    /// Path of the manifest, relative to the root of the repository, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+    /// Owners of the manifest, from the `CODEOWNERS` file of the repository if it has one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// Kinds of targets the package builds, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetKind>,
+    /// Revision of the repository the crate was read at: the commit of git repositories, or the
+    /// pinned ref of archives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Size of the Rust sources in the directory of the manifest, but those of the packages
+    /// nested in it, if they were measured with [`FetchOptions::measure_size`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<SourceSize>,
+}
+
+impl CrateInfo {
+    /// Add the targets that Cargo discovers from the layout of the package to those declared in
+    /// its manifest, from the paths of its files relative to the manifest, eg. "src/lib.rs" or
+    /// "src/bin/tool.rs", among which other files are ignored
+    pub fn discover_targets<'p>(&mut self, files: impl IntoIterator<Item = &'p Path>) {
+        let (mut lib, mut bin) = (false, false);
+        for file in files {
+            lib |= file == Path::new("src/lib.rs");
+            bin |= file == Path::new("src/main.rs") || is_bin_source(file);
+        }
+        let has_lib = self.targets.iter().any(|t| *t != TargetKind::Bin);
+        if !has_lib && lib {
+            self.targets.push(TargetKind::Lib);
+        }
+        if !self.targets.contains(&TargetKind::Bin) && bin {
+            self.targets.push(TargetKind::Bin);
+        }
+        self.targets.sort_unstable();
+    }
+}
+
+/// Whether Cargo discovers a binary from the file at `path`, relative to the manifest, ie.
+/// `src/bin/<name>.rs` or `src/bin/<name>/main.rs`
+fn is_bin_source(path: &Path) -> bool {
+    let file = match path.strip_prefix("src/bin") {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    match file.components().count() {
+        1 => file.extension() == Some(OsStr::new("rs")),
+        2 => file.ends_with("main.rs"),
+        _ => false,
+    }
+}
+
+/// Builds the crates of multiple repositories by hand instead of fetching them, eg. to run the
+/// analyses, lints and renderers on synthetic graphs
+///
+/// ```
+/// use muregraph::{find_cycles, DepKind, GraphBuilder};
+///
+/// let infos = GraphBuilder::new()
+///     .add_crate("app", "app-server")
+///     .add_crate("app", "app-proto")
+///     .add_crate("core", "core-base")
+///     .add_dependency("app-server", "core-base", DepKind::Normal)
+///     .add_dependency("core-base", "app-proto", DepKind::Dev)
+///     .build()?;
+/// assert_eq!(find_cycles(&infos).len(), 1);
+/// # Ok::<(), muregraph::Error>(())
+/// ```
+#[derive(Default)]
+pub struct GraphBuilder {
+    infos: BTreeMap<String, Vec<CrateInfo>>,
+    /// Dependencies to add, as `(from, to, kind)`
+    deps: Vec<(String, String, DepKind)>,
+}
+
+impl GraphBuilder {
+    /// Start with no repository
+    pub fn new() -> GraphBuilder {
+        GraphBuilder::default()
+    }
+
+    /// Add crate `name` to repository `repo`, at version 0.1.0 with edition 2018, publishable
+    /// to the default registry
+    pub fn add_crate(self, repo: &str, name: &str) -> GraphBuilder {
+        self.add_crate_info(
+            repo,
+            CrateInfo {
+                name: name.to_string(),
+                version: String::from("0.1.0"),
+                edition: String::from("2018"),
+                license: None,
+                description: None,
+                keywords: Vec::new(),
+                metadata_fields: Vec::new(),
+                published_to: Publish::Default,
+                deps: Vec::new(),
+                manifest: None,
+                owners: Vec::new(),
+                targets: Vec::new(),
+                revision: None,
+                size: None,
+            },
+        )
+    }
+
+    /// Add a crate to repository `repo`, with all its information and dependencies
+    pub fn add_crate_info(mut self, repo: &str, info: CrateInfo) -> GraphBuilder {
+        self.infos.entry(repo.to_string()).or_default().push(info);
+        self
+    }
+
+    /// Make crate `from` depend on crate `to`, through a path if both are in the same
+    /// repository and through the default registry otherwise
+    ///
+    /// `from` must be added by the time `build` is called, but `to` can be an external crate.
+    pub fn add_dependency(mut self, from: &str, to: &str, kind: DepKind) -> GraphBuilder {
+        self.deps.push((from.to_string(), to.to_string(), kind));
+        self
+    }
+
+    /// Return the crates of each repository, like [`Fetcher::fetch_all`]
+    pub fn build(mut self) -> Result<BTreeMap<String, Vec<CrateInfo>>> {
+        check_unique_names(&self.infos)?;
+        let repo_of = all_crates(&self.infos)
+            .map(|(repo, c)| (c.name.clone(), repo.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        for (from, to, kind) in self.deps {
+            let repo = repo_of
+                .get(&from)
+                .with_context(|| format!("Crate {} depends on {} but was not added", from, to))
+                .map_err(Error::Graph)?;
+            let krate = self
+                .infos
+                .get_mut(repo)
+                .and_then(|crates| crates.iter_mut().find(|c| c.name == from))
+                .unwrap();
+            krate.deps.push(Dependency {
+                has_path: repo_of.get(&to) == Some(repo),
+                name: to,
+                key: None,
+                from: None,
+                git: None,
+                req: String::from("0.1"),
+                kind,
+                optional: false,
+                enabled_by: Vec::new(),
+            });
+        }
+        Ok(self.infos)
+    }
+}
+
+#[cfg(test)]
+impl GraphBuilder {
+    /// Crates of the tests: app-server -> core-net -> app-proto -> core-base, core-net also
+    /// depending on core-base, and a toolkit on its own
+    pub(crate) fn example() -> GraphBuilder {
+        GraphBuilder::new()
+            .add_crate("core", "core-base")
+            .add_crate("core", "core-net")
+            .add_crate("app", "app-proto")
+            .add_crate("app", "app-server")
+            .add_crate("tools", "toolkit")
+            .add_dependency("app-server", "core-net", DepKind::Normal)
+            .add_dependency("core-net", "app-proto", DepKind::Normal)
+            .add_dependency("app-proto", "core-base", DepKind::Normal)
+            .add_dependency("core-net", "core-base", DepKind::Normal)
+    }
+}
+
+/// Parse the contents of a `Cargo.toml` file, returning `None` for virtual manifests
+pub fn parse_manifest(manifest: &[u8]) -> Result<Option<CrateInfo>> {
+    let manifest =
+        cargo_toml::Manifest::from_slice(manifest).map_err(|e| Error::Parse(e.into()))?;
+
+    // Verify whether it's a virtual manifest
+    let package = match manifest.package {
+        Some(p) => p,
+        None => return Ok(None), // Workspace Cargo.toml
+    };
+
+    // Create the dependency list
+    let mut deps = Vec::new();
+    for (kind, depname, dep) in std::iter::once(&manifest.dependencies)
+        .chain(manifest.target.values().map(|t| &t.dependencies))
+        .flat_map(|d| d.iter().map(|(n, d)| (DepKind::Normal, n, d)))
+        .chain(
+            std::iter::once(&manifest.dev_dependencies)
+                .chain(manifest.target.values().map(|t| &t.dev_dependencies))
+                .flat_map(|d| d.iter().map(|(n, d)| (DepKind::Dev, n, d))),
+        )
+        .chain(
+            std::iter::once(&manifest.build_dependencies)
+                .chain(manifest.target.values().map(|t| &t.build_dependencies))
+                .flat_map(|d| d.iter().map(|(n, d)| (DepKind::Build, n, d))),
+        )
+    {
+        match dep {
+            cargo_toml::Dependency::Simple(req) => deps.push(Dependency {
+                name: depname.clone(),
+                key: None,
+                has_path: false,
+                from: None,
+                git: None,
+                req: req.clone(),
+                kind,
+                optional: false,
+                enabled_by: Vec::new(),
+            }),
+            cargo_toml::Dependency::Detailed(d) => deps.push(Dependency {
+                name: d.package.clone().unwrap_or_else(|| depname.clone()),
+                key: d.package.as_ref().map(|_| depname.clone()),
+                has_path: d.path.is_some(),
+                from: d.registry.clone(),
+                git: d.git.clone(),
+                req: d.version.clone().unwrap_or_else(|| String::from("*")),
+                kind,
+                optional: d.optional,
+                enabled_by: match d.optional {
+                    true => enabling_features(&manifest.features, depname),
+                    false => Vec::new(),
+                },
+            }),
+        }
+    }
+
+    // Save the crate
+    let edition = toml::Value::try_from(package.edition)
+        .ok()
+        .and_then(|e| e.as_str().map(String::from))
+        .unwrap_or_else(|| String::from("2015"));
+    let metadata_fields = [
+        ("authors", !package.authors.is_empty()),
+        ("categories", !package.categories.is_empty()),
+        ("description", package.description.is_some()),
+        ("documentation", package.documentation.is_some()),
+        ("homepage", package.homepage.is_some()),
+        ("keywords", !package.keywords.is_empty()),
+        ("license", package.license.is_some()),
+        ("repository", package.repository.is_some()),
+    ]
+    .iter()
+    .filter(|(_, set)| *set)
+    .map(|(field, _)| field.to_string())
+    .collect();
+    // Only the declared targets, as discovering the others needs the files of the package
+    let mut targets = Vec::new();
+    match &manifest.lib {
+        Some(lib) if lib.proc_macro => targets.push(TargetKind::ProcMacro),
+        Some(_) => targets.push(TargetKind::Lib),
+        None => (),
+    }
+    if !manifest.bin.is_empty() {
+        targets.push(TargetKind::Bin);
+    }
+    targets.sort_unstable();
+    Ok(Some(CrateInfo {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        edition,
+        license: package.license.clone(),
+        description: package.description.clone(),
+        keywords: package.keywords.clone(),
+        metadata_fields,
+        published_to: match package.publish {
+            cargo_toml::Publish::Flag(true) => Publish::Default,
+            cargo_toml::Publish::Flag(false) => Publish::Nowhere,
+            cargo_toml::Publish::Registry(registries) => Publish::At(registries),
+        },
+        deps,
+        manifest: None,
+        owners: Vec::new(),
+        targets,
+        revision: None,
+        size: None,
+    }))
+}
+
+/// Find the `Cargo.toml` files of a tarball, possibly gzipped, calling `manifest` with the path
+/// and contents of each
+pub fn tarball_manifests(
+    archive: impl Read,
+    manifest: impl FnMut(PathBuf, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+    tarball_files(
+        archive,
+        |path| path.file_name() == Some(OsStr::new("Cargo.toml")),
+        manifest,
+    )
+}
+
+/// Find the files of a tarball, possibly gzipped, whose path is `wanted`, calling `file` with the
+/// path and contents of each
+pub fn tarball_files(
+    archive: impl Read,
+    wanted: impl Fn(&Path) -> bool,
+    mut file: impl FnMut(PathBuf, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+    // Uncompress if necessary, recognizing the format from the first bytes
+    let mut archive = std::io::BufReader::new(archive);
+    let kind = infer::get(
+        archive
+            .fill_buf()
+            .context("Failed to read the tarball")
+            .map_err(Error::Decode)?,
+    );
+    let archive: Box<dyn Read> = match kind {
+        Some(t) if t.mime_type() == "application/gzip" => {
+            Box::new(flate2::read::GzDecoder::new(archive)) as _
+        }
+        _ => Box::new(archive) as _,
+    };
+    let mut archive = tar::Archive::new(archive);
+
+    // Iterate through the files, looking for the ones we want
+    for entry in archive
+        .entries()
+        .context("Failed to enumerate the entries of the tarball")
+        .map_err(Error::Decode)?
+    {
+        let mut entry = entry
+            .context("Failed to retrieve information about an entry of the tarball")
+            .map_err(Error::Decode)?;
+        let path = entry
+            .path()
+            .context("Failed to retrieve the path for an entry of the tarball")
+            .map_err(Error::Decode)?;
+        let path = PathBuf::from(path);
+        if wanted(&path) {
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .with_context(|| format!("Failed to read file {:?} from the tarball", path))
+                .map_err(Error::Decode)?;
+            file(path, contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Iterate over all the crates, with the repository they come from
+pub fn all_crates(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> impl Iterator<Item = (&str, &CrateInfo)> {
+    infos
+        .iter()
+        .flat_map(|(k, v)| v.iter().map(move |v| (k as &str, v)))
+}
+
+/// Crates of all the repositories by name, to look them up without scanning the repositories
+struct CrateIndex<'a> {
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+    /// Repository of each crate, and its index among the crates of the repository
+    crates: HashMap<&'a str, (&'a str, usize)>,
+}
+
+impl<'a> CrateIndex<'a> {
+    fn new(infos: &'a BTreeMap<String, Vec<CrateInfo>>) -> CrateIndex<'a> {
+        let mut crates = HashMap::new();
+        for (repo, infos) in infos {
+            for (i, c) in infos.iter().enumerate() {
+                // As when scanning the repositories, the first crate wins if names are not unique
+                crates.entry(&c.name as &str).or_insert((repo as &str, i));
+            }
+        }
+        CrateIndex { infos, crates }
+    }
+
+    /// Crate `name` along with its repository, if it is one of the crates of the repositories
+    fn get(&self, name: &str) -> Option<(&'a str, &'a CrateInfo)> {
+        let &(repo, i) = self.crates.get(name)?;
+        Some((repo, &self.infos[repo][i]))
+    }
+}
+
+fn add_cycles_from(
+    root_repo: &str,
+    c: &CrateInfo,
+    parents: &mut Vec<(String, String)>,
+    index: &CrateIndex,
+    cycles: &mut Vec<Vec<(String, String)>>,
+) {
+    for d in c.deps.iter() {
+        if let Some((dep_repo, dep)) = index.get(&d.name) {
+            if parents.iter().any(|i| i.0 == dep_repo && i.1 == dep.name) {
+                return; // loop that doesn't go through the root, let's not care
+            }
+            parents.push((dep_repo.to_string(), dep.name.clone()));
+            if dep_repo == root_repo {
+                cycles.push(parents.clone());
+            } else {
+                add_cycles_from(root_repo, dep, parents, index, cycles);
+            }
+            parents.pop();
+        }
+    }
+}
+
+/// Returns the circular dependencies across repositories, as paths that go out of a
+/// repository and come back to it
+pub fn find_cycles(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Vec<Vec<(String, String)>> {
+    // Naive algorithm for now, because complexity is not really
+    // important with relatively few repositories: for each node in
+    // the graph (root), look down the dependency tree until finding
+    // one that has the same repo, while checking that the first
+    // dependency was in another repo
+    let index = CrateIndex::new(infos);
+    let mut cycles = Vec::new();
+    for (root_repo, root) in all_crates(infos) {
+        for d in root.deps.iter() {
+            let dep_name = &d.name;
+            if let Some((dep_repo, dep)) = index.get(dep_name) {
+                if dep_repo != root_repo {
+                    add_cycles_from(
+                        root_repo,
+                        dep,
+                        &mut vec![
+                            (root_repo.to_string(), root.name.clone()),
+                            (dep_repo.to_string(), dep_name.clone()),
+                        ],
+                        &index,
+                        &mut cycles,
+                    );
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// Returns the semver-compatibility class of a version requirement, ie. its
+/// major version, or its minor version for 0.x versions (eg. "1" for "^1.2.3",
+/// "0.4" for "0.4.1"), or None if it does not restrict the major version
+fn compat_class(req: &str) -> Option<String> {
+    let first = req.split(',').next()?;
+    let version = first.trim_start_matches(|c: char| "^~=<>".contains(c) || c.is_whitespace());
+    let mut parts = version.split('.');
+    let major = parts.next().filter(|m| m.parse::<u64>().is_ok())?;
+    if major != "0" {
+        return Some(major.to_string());
+    }
+    match parts.next().filter(|m| m.parse::<u64>().is_ok()) {
+        Some(minor) => Some(format!("0.{}", minor)),
+        None => Some(String::from("0")),
+    }
+}
+
+/// Formats a crate name along with its repository, for display in lint results
+pub fn styled_crate(repo: &str, krate: &str) -> String {
+    format!(
+        "{}{}",
+        console::style(krate).for_stderr().bold(),
+        console::style(format!("[{}]", repo))
+            .for_stderr()
+            .dim()
+            .italic(),
+    )
+}
+
+/// Severity of the findings of a lint
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    /// Findings make `muregraph lint` fail
+    Error,
+    /// Findings are only reported
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Outcome of one lint
+pub struct LintResult {
+    /// Identifier of the lint, eg. "cycles"
+    pub id: &'static str,
+    /// Severity of the findings
+    pub severity: Severity,
+    /// Number of issues found
+    pub findings: usize,
+    /// Line describing each issue found, without terminal styling, to tell new issues from
+    /// known ones
+    pub issues: Vec<String>,
+}
+
+impl LintResult {
+    /// Whether the lint does not make `muregraph lint` fail
+    pub fn passed(&self) -> bool {
+        self.severity == Severity::Warning || self.findings == 0
+    }
+}
+
+/// Findings of one lint, as listed by the HTML report
+#[derive(Clone, Debug)]
+pub struct LintReport {
+    /// Identifier of the lint, eg. "cycles"
+    pub id: &'static str,
+    /// Severity of the findings
+    pub severity: Severity,
+    /// What the findings are, eg. "Crates with a missing license"
+    pub title: String,
+    /// Issues found, their lines without terminal styling
+    pub findings: Vec<Finding>,
+}
+
+/// Issue found by a lint
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// Line describing the issue
+    pub text: String,
+    /// Requirement the issue is about, for the lints checking requirements
+    pub requirement: Option<RequirementFinding>,
+}
+
+impl From<String> for Finding {
+    fn from(text: String) -> Self {
+        Finding {
+            text,
+            requirement: None,
+        }
+    }
+}
+
+/// Requirement of a crate on another one that a lint found an issue with
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequirementFinding {
+    /// Crate with the requirement
+    pub krate: String,
+    /// Crate required
+    pub dependency: String,
+    /// Requirement, from the manifest
+    pub requirement: String,
+    /// Current version of the crate required
+    pub version: String,
+    /// Identifier of the issue, eg. "unmatched"
+    pub issue: &'static str,
+}
+
+/// Check that there are not two crates with the same name, which all the analyses rely on
+///
+/// A crate defined identically in several repositories, as when a monorepo is listed under
+/// several names, is the same crate, which the lookups by name attribute to the first of them.
+pub fn check_unique_names(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Result<()> {
+    let mut name_to_repo = BTreeMap::new();
+
+    for (repo, infos) in infos.iter() {
+        for i in infos.iter() {
+            match name_to_repo.get(&i.name) {
+                Some((_, first)) if *first == i => {}
+                Some((r, _)) => {
+                    return Err(Error::Graph(anyhow::anyhow!(
+                        "Crate {} was defined multiple times, eg. in repos {} and {}",
+                        i.name,
+                        r,
+                        repo
+                    )));
+                }
+                None => {
+                    name_to_repo.insert(i.name.clone(), (repo, i));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the lints of [`lint::registry`] enabled by `cfg`, returning their results, and an error
+/// if the input was too broken to be able to generate a graph
+///
+/// The findings about cycles mention the owners of the repositories from `ownership`, whose
+/// groups the rules of `policy` can also select, and the issues that its exceptions accept are
+/// left out until they expire.
+pub fn sanity_check(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    invalid_manifests: &[InvalidManifest],
+    cfg: &LintConfig,
+    policy: &PolicyConfig,
+    ownership: &Ownership,
+) -> Result<Vec<LintResult>> {
+    let cx = lint::Context {
+        infos,
+        invalid_manifests,
+        ownership,
+        exceptions: &policy.exceptions,
+        today: chrono::Utc::now().date_naive(),
+    };
+    Ok(lint::run(&lint::registry(cfg, policy, ownership), &cx))
+}
+
+/// Run the lints like [`sanity_check`], returning their findings instead of logging them
+pub fn lint_report(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    invalid_manifests: &[InvalidManifest],
+    cfg: &LintConfig,
+    policy: &PolicyConfig,
+    ownership: &Ownership,
+) -> Vec<LintReport> {
+    let cx = lint::Context {
+        infos,
+        invalid_manifests,
+        ownership,
+        exceptions: &policy.exceptions,
+        today: chrono::Utc::now().date_naive(),
+    };
+    lint::report(&lint::registry(cfg, policy, ownership), &cx)
+}
+
+/// Sorts the publishable crates of `graph` in groups, such that the crates of each group only
+/// depend on crates from the previous groups, ignoring dev-dependencies which are not needed for
+/// publishing
+pub fn publish_order<'a>(
+    graph: &graph::DependencyGraph<'a>,
+) -> Result<Vec<Vec<(&'a str, &'a CrateInfo)>>> {
+    use petgraph::visit::{EdgeRef, IntoNeighbors};
+
+    let publishable = |n: petgraph::graph::NodeIndex| {
+        !matches!(graph.node(n).info.published_to, Publish::Nowhere)
+    };
+    let deps = petgraph::visit::EdgeFiltered::from_fn(&graph.graph, |e| {
+        e.weight().kind != DepKind::Dev && publishable(e.source()) && publishable(e.target())
+    });
+    let order = petgraph::algo::toposort(&deps, None).map_err(|_| {
+        let cyclic = petgraph::algo::tarjan_scc(&deps)
+            .into_iter()
+            .filter(|scc| scc.len() > 1)
+            .flatten()
+            .map(|n| &graph.node(n).info.name as &str)
+            .collect::<BTreeSet<_>>();
+        Error::Graph(anyhow::anyhow!(
+            "Crates {} depend on each other, so they cannot be ordered",
+            cyclic.into_iter().collect::<Vec<_>>().join(", ")
+        ))
+    })?;
+    // Dependencies come after their dependents in the topological order, and a crate is in the
+    // group after the last one of its dependencies
+    let mut group = vec![0; graph.graph.node_count()];
+    for n in order.into_iter().rev() {
+        group[n.index()] = deps
+            .neighbors(n)
+            .map(|d| group[d.index()] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    let mut groups = Vec::<Vec<_>>::new();
+    for n in graph.graph.node_indices().filter(|n| publishable(*n)) {
+        let g = group[n.index()];
+        if groups.len() <= g {
+            groups.resize_with(g + 1, Vec::new);
+        }
+        groups[g].push((graph.node(n).repo, graph.node(n).info));
+    }
+    Ok(groups)
+}
+
+/// Returns the crates that crate `c` directly depends on, excluding itself
+fn internal_deps<'a>(c: &CrateInfo, index: &CrateIndex<'a>) -> Vec<(&'a str, &'a CrateInfo)> {
+    let mut deps = Vec::<(&str, &CrateInfo)>::new();
+    for d in c.deps.iter() {
+        if let Some((repo, dep)) = index.get(&d.name) {
+            if dep.name != c.name && !deps.iter().any(|(_, known)| known.name == dep.name) {
+                deps.push((repo, dep));
+            }
+        }
+    }
+    deps
+}
+
+/// Returns, for each crate name, the crates that directly depend on it
+fn reverse_deps(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> BTreeMap<&str, Vec<(&str, &CrateInfo)>> {
+    let mut rdeps = BTreeMap::<&str, Vec<(&str, &CrateInfo)>>::new();
+    for (repo, c) in all_crates(infos) {
+        for d in c.deps.iter() {
+            let dependents = rdeps.entry(&d.name).or_default();
+            if d.name != c.name && !dependents.iter().any(|(_, r)| r.name == c.name) {
+                dependents.push((repo, c));
+            }
+        }
+    }
+    rdeps
+}
+
+/// Returns all the crates of `graph` that transitively depend on crate `name`, by repository
+pub fn impact<'a>(
+    name: &str,
+    graph: &graph::DependencyGraph<'a>,
+) -> Result<BTreeMap<&'a str, BTreeSet<&'a str>>> {
+    let start = graph
+        .index(name)
+        .with_context(|| format!("Crate {} is not part of any repository", name))
+        .map_err(Error::Graph)?;
+    let dependents = petgraph::visit::Reversed(&graph.graph);
+    let mut dfs = petgraph::visit::Dfs::new(dependents, start);
+    let mut affected = BTreeMap::<&str, BTreeSet<&str>>::new();
+    while let Some(n) = dfs.next(dependents) {
+        let c = graph.node(n);
+        if c.info.name != name {
+            affected.entry(c.repo).or_default().insert(&c.info.name);
+        }
+    }
+    Ok(affected)
+}
+
+/// Internal dependencies, as (repository, crate, dependency repository, dependency) tuples
+pub fn internal_edges(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> BTreeSet<(&str, &str, &str, &str)> {
+    let index = CrateIndex::new(infos);
+    all_crates(infos)
+        .flat_map(|(repo, c)| {
+            let index = &index;
+            c.deps.iter().filter_map(move |d| {
+                index
+                    .get(&d.name)
+                    .map(|(dep_repo, dep)| (repo, &c.name as &str, dep_repo, &dep.name as &str))
+            })
+        })
+        .collect()
+}
+
+/// Returns the changes between two sets of repositories, as sections with a title and a line
+/// per change, leaving out the sections without changes
+pub fn diff_sections(
+    old: &BTreeMap<String, Vec<CrateInfo>>,
+    new: &BTreeMap<String, Vec<CrateInfo>>,
+) -> Vec<(&'static str, Vec<String>)> {
+    let old_crates = all_crates(old)
+        .map(|(r, c)| (&c.name as &str, (r, c)))
+        .collect::<BTreeMap<_, _>>();
+    let new_crates = all_crates(new)
+        .map(|(r, c)| (&c.name as &str, (r, c)))
+        .collect::<BTreeMap<_, _>>();
+    let old_edges = internal_edges(old);
+    let new_edges = internal_edges(new);
+    let old_cycles = find_cycles(old).into_iter().collect::<BTreeSet<_>>();
+    let new_cycles = find_cycles(new).into_iter().collect::<BTreeSet<_>>();
+
+    let mut sections = Vec::new();
+    let mut section = |title, lines: Vec<String>| {
+        if !lines.is_empty() {
+            sections.push((title, lines));
+        }
+    };
+    section(
+        "Added crates",
+        new_crates
+            .iter()
+            .filter(|(n, _)| !old_crates.contains_key(*n))
+            .map(|(n, (r, c))| match &c.description {
+                Some(description) => format!("+ {} [{}]: {}", n, r, description),
+                None => format!("+ {} [{}]", n, r),
+            })
+            .collect(),
+    );
+    section(
+        "Removed crates",
+        old_crates
+            .iter()
+            .filter(|(n, _)| !new_crates.contains_key(*n))
+            .map(|(n, (r, _))| format!("- {} [{}]", n, r))
+            .collect(),
+    );
+    let edge = |(r, c, dr, d): &(&str, &str, &str, &str)| {
+        let cross = if r != dr { " (cross-repository)" } else { "" };
+        format!("{} [{}] -> {} [{}]{}", c, r, d, dr, cross)
+    };
+    section(
+        "Added dependencies",
+        new_edges
+            .difference(&old_edges)
+            .map(|e| format!("+ {}", edge(e)))
+            .collect(),
+    );
+    section(
+        "Removed dependencies",
+        old_edges
+            .difference(&new_edges)
+            .map(|e| format!("- {}", edge(e)))
+            .collect(),
+    );
+    let cycle = |c: &Vec<(String, String)>| {
+        c.iter()
+            .map(|(r, k)| format!("{} [{}]", k, r))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    };
+    section(
+        "New cycles",
+        new_cycles
+            .difference(&old_cycles)
+            .map(|c| format!("+ {}", cycle(c)))
+            .collect(),
+    );
+    section(
+        "Fixed cycles",
+        old_cycles
+            .difference(&new_cycles)
+            .map(|c| format!("- {}", cycle(c)))
+            .collect(),
+    );
+    section(
+        "Publish status changes",
+        new_crates
+            .iter()
+            .filter_map(|(n, (_, c))| {
+                let (_, old) = old_crates.get(n)?;
+                if old.published_to == c.published_to {
+                    None
+                } else {
+                    Some(format!(
+                        "~ {}: {} -> {}",
+                        n, old.published_to, c.published_to
+                    ))
+                }
+            })
+            .collect(),
+    );
+    sections
+}
+
+/// Sorts all the crates in waves, such that the crates of each wave only depend on crates
+/// from the previous waves, ignoring dev-dependencies and the dependencies that would close
+/// a cycle
+pub fn build_waves(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Vec<Vec<&str>> {
+    let deps = adjacency(
+        &graph::DependencyGraph::new(infos),
+        &[DepKind::Normal, DepKind::Build],
+    );
+    let mut depths = BTreeMap::new();
+    let mut waves = Vec::<Vec<&str>>::new();
+    for c in deps.keys() {
+        let depth = longest_chain(c, &deps, &mut Vec::new(), &mut depths);
+        if waves.len() <= depth {
+            waves.resize(depth + 1, Vec::new());
+        }
+        waves[depth].push(c);
+    }
+    waves
+}
+
+/// Statistics about the crates at some point in time, saved to follow their trends
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Snapshot {
+    /// Version of the schema of the snapshot, snapshots saved before it was introduced being
+    /// version 1
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u32,
+    /// When the snapshot was taken
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Number of crates
+    pub crates: usize,
+    /// Dependencies between two different crates
+    pub edges: usize,
+    /// Cyclic dependencies across repositories
+    pub cycles: usize,
+    /// Length of the longest dependency chain
+    pub max_depth: usize,
+}
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+impl Snapshot {
+    /// Take a snapshot of the crates, timestamped now
+    pub fn new(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Snapshot {
+        let deps = adjacency(
+            &graph::DependencyGraph::new(infos),
+            &[DepKind::Normal, DepKind::Dev, DepKind::Build],
+        );
+        let mut depths = BTreeMap::new();
+        Snapshot {
+            schema_version: SCHEMA_VERSION,
+            timestamp: chrono::Utc::now(),
+            crates: all_crates(infos).count(),
+            edges: deps.values().map(|d| d.len()).sum(),
+            cycles: find_cycles(infos).len(),
+            max_depth: deps
+                .keys()
+                .map(|c| longest_chain(c, &deps, &mut Vec::new(), &mut depths))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Save the snapshot to a timestamped file in `dir`
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {:?}", dir))
+            .map_err(Error::Snapshot)?;
+        let path = dir.join(format!(
+            "snapshot-{}.json",
+            self.timestamp.format("%Y-%m-%dT%H-%M-%SZ")
+        ));
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {:?}", path))
+            .map_err(Error::Snapshot)?;
+        serde_json::to_writer_pretty(file, self)
+            .with_context(|| format!("Failed to write {:?}", path))
+            .map_err(Error::Snapshot)
+    }
+
+    /// Loads all the snapshots saved in a directory, oldest first
+    pub fn load_all(dir: &Path) -> Result<Vec<Snapshot>> {
+        let mut res = Vec::new();
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to list {:?}", dir))
+            .map_err(Error::Snapshot)?;
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("Failed to list {:?}", dir))
+                .map_err(Error::Snapshot)?
+                .path();
+            let is_snapshot = path.extension() == Some(OsStr::new("json"))
+                && matches!(
+                    path.file_name().and_then(|n| n.to_str()),
+                    Some(n) if n.starts_with("snapshot-")
+                );
+            if is_snapshot {
+                let file = std::fs::File::open(&path)
+                    .with_context(|| format!("Failed to open {:?}", path))
+                    .map_err(Error::Snapshot)?;
+                let snapshot: Snapshot = serde_json::from_reader(std::io::BufReader::new(file))
+                    .with_context(|| format!("Failed to parse {:?}", path))
+                    .map_err(Error::Snapshot)?;
+                if snapshot.schema_version > SCHEMA_VERSION {
+                    return Err(Error::Snapshot(anyhow::anyhow!(
+                        "{:?} uses schema version {}, while this version of muregraph only supports up to {}",
+                        path,
+                        snapshot.schema_version,
+                        SCHEMA_VERSION
+                    )));
+                }
+                res.push(snapshot);
+            }
+        }
+        res.sort_by_key(|s: &Snapshot| s.timestamp);
+        Ok(res)
+    }
+}
+
+/// Metrics of a crate
+#[derive(serde::Serialize)]
+pub struct CrateMetrics<'a> {
+    /// Repository the crate comes from
+    pub repo: &'a str,
+    /// Name of the crate
+    pub name: &'a str,
+    /// Number of crates directly depending on this crate
+    pub fan_in: usize,
+    /// Number of crates this crate directly depends on
+    pub fan_out: usize,
+    /// Number of crates transitively depending on this crate
+    pub dependents: usize,
+    /// Length of the longest dependency chain starting from this crate
+    pub depth: usize,
+    /// fan_out / (fan_in + fan_out), 0 being maximally stable
+    pub instability: f64,
+    /// Size of the Rust sources of the crate, if measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<SourceSize>,
+}
+
+/// Metrics of a repository
+#[derive(serde::Serialize)]
+pub struct RepoMetrics<'a> {
+    /// Name of the repository
+    pub name: &'a str,
+    /// Number of crates of the repository
+    pub crates: usize,
+    /// Dependencies between two crates of this repository
+    pub internal_edges: usize,
+    /// Dependencies from crates of this repository to crates of other repositories
+    pub outgoing_edges: usize,
+    /// Dependencies from crates of other repositories to crates of this repository
+    pub incoming_edges: usize,
+    /// Size of the Rust sources of the measured crates of the repository, if any was measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<SourceSize>,
+}
+
+/// Metrics of all the crates and repositories
+#[derive(serde::Serialize)]
+pub struct Metrics<'a> {
+    /// Metrics of each crate
+    pub crates: Vec<CrateMetrics<'a>>,
+    /// Metrics of each repository
+    pub repos: Vec<RepoMetrics<'a>>,
+}
+
+/// Returns, for each crate of `graph`, the crates it directly depends on, only considering the
+/// dependencies of the given kinds
+pub fn adjacency<'a>(
+    graph: &graph::DependencyGraph<'a>,
+    kinds: &[DepKind],
+) -> BTreeMap<&'a str, Vec<&'a str>> {
+    graph.adjacency(kinds)
+}
+
+/// Returns the number of crates of `graph` that transitively depend on each crate, by node
+///
+/// The dependents of the crates of a cycle are the same, so they are gathered once per strongly
+/// connected component, from those of its direct dependents, in topological order.
+fn dependents_counts(graph: &graph::DependencyGraph) -> Vec<usize> {
+    let n = graph.graph.node_count();
+    let sccs = petgraph::algo::tarjan_scc(&graph.graph);
+    let mut component = vec![0; n];
+    for (i, scc) in sccs.iter().enumerate() {
+        for node in scc {
+            component[node.index()] = i;
+        }
+    }
+    // Bits of the crates depending on each component, its own crates included
+    let words = n.div_ceil(64);
+    let mut dependents = vec![vec![0u64; words]; sccs.len()];
+    let mut counts = vec![0; n];
+    // Dependents come before their dependencies in topological order
+    for (i, scc) in sccs.iter().enumerate().rev() {
+        let mut bits = std::mem::take(&mut dependents[i]);
+        for node in scc {
+            bits[node.index() / 64] |= 1 << (node.index() % 64);
+            for from in graph
+                .graph
+                .neighbors_directed(*node, petgraph::Direction::Incoming)
+            {
+                let j = component[from.index()];
+                if j != i {
+                    for (b, d) in bits.iter_mut().zip(&dependents[j]) {
+                        *b |= d;
+                    }
+                }
+            }
+        }
+        let count = bits.iter().map(|b| b.count_ones() as usize).sum::<usize>();
+        for node in scc {
+            counts[node.index()] = count - 1;
+        }
+        dependents[i] = bits;
+    }
+    counts
+}
+
+/// Reverses the direction of all the edges of an adjacency map
+pub fn reverse_adjacency<'a>(
+    adjacency: &BTreeMap<&'a str, Vec<&'a str>>,
+) -> BTreeMap<&'a str, Vec<&'a str>> {
+    let mut res = adjacency
+        .keys()
+        .map(|k| (*k, Vec::new()))
+        .collect::<BTreeMap<_, _>>();
+    for (from, tos) in adjacency {
+        for to in tos {
+            res.entry(to).or_default().push(*from);
+        }
+    }
+    res
+}
+
+/// Returns the length of the longest chain starting from `node` in the adjacency map,
+/// ignoring the edges that would close a cycle
+fn longest_chain<'a>(
+    node: &'a str,
+    adjacency: &BTreeMap<&'a str, Vec<&'a str>>,
+    stack: &mut Vec<&'a str>,
+    known: &mut BTreeMap<&'a str, usize>,
+) -> usize {
+    if let Some(d) = known.get(node) {
+        return *d;
+    }
+    stack.push(node);
+    let mut res = 0;
+    for next in adjacency.get(node).into_iter().flatten() {
+        if !stack.contains(next) {
+            res = res.max(1 + longest_chain(next, adjacency, stack, known));
+        }
+    }
+    stack.pop();
+    known.insert(node, res);
+    res
+}
+
+/// Computes the critical path of the release train, ie. the longest chain of crates that
+/// must be released one after the other, and for each crate its slack, ie. by how many
+/// steps its release could be delayed without delaying the whole train
+pub fn critical_path(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> (Vec<&str>, BTreeMap<&str, usize>) {
+    let deps = adjacency(
+        &graph::DependencyGraph::new(infos),
+        &[DepKind::Normal, DepKind::Build],
+    );
+    let rdeps = reverse_adjacency(&deps);
+    let mut depths = BTreeMap::new();
+    let mut heights = BTreeMap::new();
+    for c in deps.keys() {
+        longest_chain(c, &deps, &mut Vec::new(), &mut depths);
+        longest_chain(c, &rdeps, &mut Vec::new(), &mut heights);
+    }
+    let length = depths.values().copied().max().unwrap_or(0);
+    let slack = deps
+        .keys()
+        .map(|c| (*c, length.saturating_sub(depths[c] + heights[c])))
+        .collect();
+
+    // Follow the longest chain from its top, then return it in release order
+    let mut path = Vec::new();
+    let mut next = depths.iter().find(|(_, d)| **d == length).map(|(c, _)| *c);
+    while let Some(c) = next {
+        path.push(c);
+        next = deps[c]
+            .iter()
+            .find(|d| depths[*d] + 1 == depths[c] && !path.contains(*d))
+            .copied();
+    }
+    path.reverse();
+    (path, slack)
+}
+
+/// Computes the `count` longest chains of crates that must be released one after the other, in
+/// release order, each crate along with its repository
+///
+/// Each chain starts from the crate with the longest chain that is not part of a previous one, so
+/// that the chains differ by more than the crates at their top.
+pub fn longest_chains(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    count: usize,
+) -> Vec<Vec<(&str, &str)>> {
+    let index = CrateIndex::new(infos);
+    let deps = adjacency(
+        &graph::DependencyGraph::new(infos),
+        &[DepKind::Normal, DepKind::Build],
+    );
+    let mut depths = BTreeMap::new();
+    for c in deps.keys() {
+        longest_chain(c, &deps, &mut Vec::new(), &mut depths);
+    }
+    let mut tops = depths.iter().map(|(c, d)| (*c, *d)).collect::<Vec<_>>();
+    tops.sort_by_key(|(c, d)| (std::cmp::Reverse(*d), *c));
+
+    let mut chained = BTreeSet::new();
+    let mut res = Vec::new();
+    for (top, _) in tops {
+        if res.len() == count {
+            break;
+        }
+        if chained.contains(top) {
+            continue;
+        }
+        // Follow the longest chain from its top, then return it in release order
+        let mut chain = Vec::new();
+        let mut next = Some(top);
+        while let Some(c) = next {
+            chain.push(c);
+            next = deps[c]
+                .iter()
+                .find(|d| depths[*d] + 1 == depths[c] && !chain.contains(*d))
+                .copied();
+        }
+        chained.extend(chain.iter().copied());
+        chain.reverse();
+        res.push(
+            chain
+                .into_iter()
+                .map(|c| (index.get(c).unwrap().0, c))
+                .collect(),
+        );
+    }
+    res
+}
+
+/// Compute the metrics of the crates and repositories
+pub fn metrics(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Result<Metrics<'_>> {
+    let index = CrateIndex::new(infos);
+    let rdeps = reverse_deps(infos);
+    let graph = graph::DependencyGraph::new(infos);
+    let deps = adjacency(&graph, &[DepKind::Normal, DepKind::Dev, DepKind::Build]);
+    let dependents = dependents_counts(&graph);
+    let mut depths = BTreeMap::new();
+    let mut crates = Vec::new();
+    // The nodes of the graph are in the order of the crates
+    for (i, (repo, c)) in all_crates(infos).enumerate() {
+        let fan_in = rdeps.get(&c.name as &str).map_or(0, |r| r.len());
+        let fan_out = internal_deps(c, &index).len();
+        crates.push(CrateMetrics {
+            repo,
+            name: &c.name,
+            fan_in,
+            fan_out,
+            dependents: dependents[i],
+            depth: longest_chain(&c.name, &deps, &mut Vec::new(), &mut depths),
+            instability: match fan_in + fan_out {
+                0 => 0.,
+                total => fan_out as f64 / total as f64,
+            },
+            size: c.size,
+        });
+    }
+    let edges = internal_edges(infos);
+    let repos = infos
+        .iter()
+        .map(|(name, crates)| RepoMetrics {
+            name,
+            crates: crates.len(),
+            internal_edges: edges
+                .iter()
+                .filter(|(r, c, dr, d)| r == name && dr == name && c != d)
+                .count(),
+            outgoing_edges: edges
+                .iter()
+                .filter(|(r, _, dr, _)| r == name && dr != name)
+                .count(),
+            incoming_edges: edges
+                .iter()
+                .filter(|(r, _, dr, _)| r != name && dr == name)
+                .count(),
+            size: crates
+                .iter()
+                .filter_map(|c| c.size)
+                .reduce(|mut total, size| {
+                    total += size;
+                    total
+                }),
+        })
+        .collect();
+    Ok(Metrics { crates, repos })
+}
+
+/// Crates with the most transitive dependents, overall and in each repository
+#[derive(serde::Serialize)]
+pub struct TopDependents<'a> {
+    /// Crates with the most dependents among all the crates, most depended on first
+    pub overall: Vec<&'a CrateMetrics<'a>>,
+    /// Crates with the most dependents of each repository, most depended on first
+    pub repos: BTreeMap<&'a str, Vec<&'a CrateMetrics<'a>>>,
+}
+
+/// Select the `count` crates with the most transitive dependents, overall and in each repository,
+/// the ones with more direct dependents first upon ties, leaving out the crates nothing depends on
+pub fn top_dependents<'a>(metrics: &'a Metrics<'a>, count: usize) -> TopDependents<'a> {
+    let mut ranked = metrics
+        .crates
+        .iter()
+        .filter(|c| c.dependents > 0)
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|c| (std::cmp::Reverse((c.dependents, c.fan_in)), c.name));
+    let mut repos = BTreeMap::<_, Vec<_>>::new();
+    for c in ranked.iter() {
+        let top = repos.entry(c.repo).or_default();
+        if top.len() < count {
+            top.push(*c);
+        }
+    }
+    ranked.truncate(count);
+    TopDependents {
+        overall: ranked,
+        repos,
+    }
+}
+
+/// Dependencies between two repositories
+pub struct Coupling<'a> {
+    /// Names of the two repositories
+    pub repos: (&'a str, &'a str),
+    /// Number of dependencies from crates of the first repository to crates of the second
+    pub forward: usize,
+    /// Number of dependencies from crates of the second repository to crates of the first
+    pub backward: usize,
+    /// Proportion of the pairs of crates across the two repositories that are linked by a
+    /// dependency, in either direction
+    pub score: f64,
+}
+
+/// Regroups the crates by owner instead of by repository, for the analyses and renderers to work
+/// on teams rather than repositories
+///
+/// Crates with several owners belong to the first one, and crates with none to "unowned".
+pub fn crates_by_owner(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> BTreeMap<String, Vec<CrateInfo>> {
+    let mut res = BTreeMap::<String, Vec<CrateInfo>>::new();
+    for (_, c) in all_crates(infos) {
+        let owner = c.owners.first().map_or("unowned", |o| o as &str);
+        res.entry(owner.to_string()).or_default().push(c.clone());
+    }
+    res
+}
+
+/// Regroups the crates whose name starts with one of `prefixes` by prefix instead of by
+/// repository, for the renderers to show the subsystems that naming conventions encode
+///
+/// Crates belong to the longest prefix they start with, named after it without a trailing `*`,
+/// `-` or `_` (`mure-storage-*` grouping the crates of `mure-storage`), and crates with none stay
+/// in their repository.
+pub fn crates_by_prefix(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    prefixes: &[String],
+) -> BTreeMap<String, Vec<CrateInfo>> {
+    let mut res = BTreeMap::<String, Vec<CrateInfo>>::new();
+    for (repo, c) in all_crates(infos) {
+        let group = prefixes
+            .iter()
+            .map(|p| p.trim_end_matches('*'))
+            .filter(|p| c.name.starts_with(p))
+            .max_by_key(|p| p.len())
+            .map_or(repo, |p| p.trim_end_matches(&['-', '_'] as &[char]));
+        res.entry(group.to_string()).or_default().push(c.clone());
+    }
+    res
+}
+
+/// Returns the coupling of each pair of repositories that have dependencies between them,
+/// most coupled first
+pub fn coupling(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Vec<Coupling<'_>> {
+    let edges = internal_edges(infos);
+    let count = |from: &str, to: &str| {
+        edges
+            .iter()
+            .filter(|(r, _, dr, _)| *r == from && *dr == to)
+            .count()
+    };
+    let mut res = Vec::new();
+    for (i, (a, a_crates)) in infos.iter().enumerate() {
+        for (b, b_crates) in infos.iter().skip(i + 1) {
+            let (forward, backward) = (count(a, b), count(b, a));
+            if forward + backward > 0 {
+                res.push(Coupling {
+                    repos: (a, b),
+                    forward,
+                    backward,
+                    score: (forward + backward) as f64
+                        / (2 * a_crates.len() * b_crates.len()) as f64,
+                });
+            }
+        }
+    }
+    res.sort_by(|x, y| {
+        y.score
+            .partial_cmp(&x.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    res
+}
+
+/// Partitions the crates of repository `repo` into communities of crates tightly coupled
+/// together, by label propagation over the dependencies between crates of this repository
+pub fn split(repo: &str, infos: &BTreeMap<String, Vec<CrateInfo>>) -> Result<Vec<Vec<String>>> {
+    let crates = infos
+        .get(repo)
+        .with_context(|| format!("Repository {} is not in the configuration", repo))
+        .map_err(Error::Graph)?;
+
+    // Build the undirected graph of dependencies between crates of the repository
+    let index = CrateIndex::new(infos);
+    let mut neighbors = crates
+        .iter()
+        .map(|c| (&c.name as &str, BTreeSet::new()))
+        .collect::<BTreeMap<_, _>>();
+    for c in crates.iter() {
+        for (dep_repo, dep) in internal_deps(c, &index) {
+            if dep_repo == repo {
+                neighbors
+                    .get_mut(&c.name as &str)
+                    .unwrap()
+                    .insert(&dep.name as &str);
+                neighbors
+                    .get_mut(&dep.name as &str)
+                    .unwrap()
+                    .insert(&c.name as &str);
+            }
+        }
+    }
+
+    // Propagate labels until reaching a fixpoint: each crate takes the label that is the
+    // most common among its neighbors, keeping its own upon ties
+    let mut labels = neighbors
+        .keys()
+        .map(|c| (*c, *c))
+        .collect::<BTreeMap<_, _>>();
+    for _ in 0..100 {
+        let mut changed = false;
+        for (c, ns) in neighbors.iter() {
+            let mut counts = BTreeMap::<&str, usize>::new();
+            for n in ns {
+                *counts.entry(labels[n]).or_default() += 1;
+            }
+            let max = match counts.values().max() {
+                Some(max) => *max,
+                None => continue,
+            };
+            if counts.get(labels[c]) != Some(&max) {
+                let best = counts.iter().find(|(_, n)| **n == max).unwrap().0;
+                labels.insert(c, best);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut groups = BTreeMap::<&str, Vec<String>>::new();
+    for (c, l) in labels {
+        groups.entry(l).or_default().push(c.to_string());
+    }
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+    Ok(groups)
+}
+
+/// Returns the crates that changed from `old` to `new`: the ones that were added, and the
+/// ones whose version, dependencies or publish status changed
+pub fn changed_crates<'a>(
+    old: &BTreeMap<String, Vec<CrateInfo>>,
+    new: &'a BTreeMap<String, Vec<CrateInfo>>,
+) -> Vec<&'a str> {
+    let deps = |c: &CrateInfo| {
+        c.deps
+            .iter()
+            .map(|d| (d.name.clone(), d.req.clone()))
+            .collect::<BTreeSet<_>>()
+    };
+    let old = CrateIndex::new(old);
+    all_crates(new)
+        .filter(|(_, c)| match old.get(&c.name) {
+            None => true,
+            Some((_, o)) => {
+                o.version != c.version || o.published_to != c.published_to || deps(o) != deps(c)
+            }
+        })
+        .map(|(_, c)| &c.name as &str)
+        .collect()
+}
+
+/// Releases needed after some crates changed, see [`bump_plan`]
+pub struct BumpPlan<'a> {
+    /// Crates to bump and publish, in order, with the reason why they need to be
+    pub to_publish: Vec<(&'a str, &'a CrateInfo, String)>,
+    /// Dependencies whose requirement must be updated to the new version of the dependency
+    pub to_update: Vec<(&'a str, &'a CrateInfo, &'a Dependency)>,
+    /// Version each crate to publish is bumped to, the first one semver-incompatible with its
+    /// current version, unless the latter is not a valid semver version
+    pub versions: BTreeMap<&'a str, String>,
+}
+
+impl BumpPlan<'_> {
+    /// Requirement the requirement of dependency `d` must be updated to, on the version its crate
+    /// is bumped to, with the operator and precision of its current requirement
+    pub fn updated_requirement(&self, d: &Dependency) -> Option<String> {
+        let version = self.versions.get(&d.name as &str)?;
+        Some(updated_requirement(&d.req, version))
+    }
+}
+
+/// First version semver-incompatible with `version`, bumping its leftmost non-zero component
+fn next_breaking(version: &semver::Version) -> semver::Version {
+    match (version.major, version.minor) {
+        (0, 0) => semver::Version::new(0, 0, version.patch + 1),
+        (0, minor) => semver::Version::new(0, minor + 1, 0),
+        (major, _) => semver::Version::new(major + 1, 0, 0),
+    }
+}
+
+/// Computes which crates must be bumped and published after crates `changed` changed,
+/// assuming all the bumps are breaking: this includes the changed crates, but also the
+/// publishable crates depending on them, as their requirements must be updated
+pub fn bump_plan<'a>(
+    changed: &[&str],
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+) -> Result<BumpPlan<'a>> {
+    let index = CrateIndex::new(infos);
+    for c in changed {
+        if index.get(c).is_none() {
+            return Err(Error::Graph(anyhow::anyhow!(
+                "Crate {} is not part of any repository",
+                c
+            )));
+        }
+    }
+
+    // Find all the crates to bump, along with the reason why
+    let mut reasons = BTreeMap::<&str, String>::new();
+    let mut todo = Vec::new();
+    for c in changed {
+        reasons.insert(c, String::from("changed"));
+        todo.push(*c);
+    }
+    let rdeps = reverse_deps(infos);
+    while let Some(n) = todo.pop() {
+        for (_, c) in rdeps.get(n).into_iter().flatten() {
+            let needs_publish = !matches!(c.published_to, Publish::Nowhere)
+                && c.deps.iter().any(|d| d.name == n && d.kind != DepKind::Dev);
+            if needs_publish && !reasons.contains_key(&c.name as &str) {
+                reasons.insert(&c.name, format!("depends on {}", n));
+                todo.push(&c.name);
+            }
+        }
+    }
+
+    // Order them as they must be published
+    let to_publish: Vec<_> = publish_order(&graph::DependencyGraph::new(infos))?
+        .into_iter()
+        .flatten()
+        .filter_map(|(repo, c)| {
+            reasons
+                .get(&c.name as &str)
+                .map(|reason| (repo, c, reason.clone()))
+        })
+        .collect();
+    let to_update = all_crates(infos)
+        .flat_map(|(repo, c)| c.deps.iter().map(move |d| (repo, c, d)))
+        .filter(|(_, c, d)| {
+            d.name != c.name
+                && !(d.has_path && d.req == "*")
+                && reasons.contains_key(&d.name as &str)
+        })
+        .collect();
+    let versions = to_publish
+        .iter()
+        .filter_map(|(_, c, _)| {
+            let version = semver::Version::parse(&c.version).ok()?;
+            Some((&c.name as &str, next_breaking(&version).to_string()))
+        })
+        .collect();
+    Ok(BumpPlan {
+        to_publish,
+        to_update,
+        versions,
+    })
+}
+
+/// How a requirement on a crate of the repositories disagrees with the current version of the
+/// crate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RequirementIssue {
+    /// The requirement is not valid, for this reason
+    Invalid(String),
+    /// The current version does not match the requirement
+    Unmatched,
+    /// The requirement would also match this version, the next semver-incompatible one
+    TooLoose(semver::Version),
+}
+
+impl RequirementIssue {
+    /// Identifier of the issue, eg. "unmatched"
+    pub fn id(&self) -> &'static str {
+        match self {
+            RequirementIssue::Invalid(_) => "invalid",
+            RequirementIssue::Unmatched => "unmatched",
+            RequirementIssue::TooLoose(_) => "too-loose",
+        }
+    }
+}
+
+/// Returns how requirement `req` disagrees with `version`, the current version of the crate
+/// required, following semver, or None if it does not or `version` is not valid
+pub fn requirement_issue(req: &str, version: &str) -> Option<RequirementIssue> {
+    let version = semver::Version::parse(version).ok()?;
+    match semver::VersionReq::parse(req) {
+        Err(e) => Some(RequirementIssue::Invalid(e.to_string())),
+        Ok(req) if !req.matches(&version) => Some(RequirementIssue::Unmatched),
+        Ok(req) if req.matches(&next_breaking(&version)) => {
+            Some(RequirementIssue::TooLoose(next_breaking(&version)))
+        }
+        Ok(_) => None,
+    }
+}
+
+/// Requirement of a crate on another crate of the repositories that the current version of the
+/// latter does not match
+pub struct OutdatedRequirement<'a> {
+    /// Repository of the dependent crate
+    pub repo: &'a str,
+    /// Dependent crate
+    pub krate: &'a CrateInfo,
+    /// Dependency with the outdated requirement
+    pub dep: &'a Dependency,
+    /// Current version of the dependency, from its manifest
+    pub version: &'a str,
+    /// Requirement matching the current version, with the operator and precision of the outdated
+    /// one
+    pub updated: String,
+}
+
+/// Returns the requirements of crates on other crates of the repositories that the current
+/// version of the crate depended on does not match, like the `semver-requirements` lint, by
+/// repository
+pub fn outdated_requirements(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> Vec<OutdatedRequirement<'_>> {
+    let index = CrateIndex::new(infos);
+    let mut res = Vec::new();
+    for (repo, c) in all_crates(infos) {
+        for d in c.deps.iter().filter(|d| d.name != c.name) {
+            let dep = match index.get(&d.name) {
+                Some((_, dep)) => dep,
+                None => continue,
+            };
+            if requirement_issue(&d.req, &dep.version) == Some(RequirementIssue::Unmatched) {
+                res.push(OutdatedRequirement {
+                    repo,
+                    krate: c,
+                    dep: d,
+                    version: &dep.version,
+                    updated: updated_requirement(&d.req, &dep.version),
+                });
+            }
+        }
+    }
+    res
+}
+
+/// Unified diff of `manifest`, the contents of the manifest of `krate`, updating the requirements
+/// of `updates`, pairs of a dependency of the crate and of its new requirement, that `git apply`
+/// applies at the root of the repository
+///
+/// The declarations are edited line by line, leaving the rest of the manifest as is, so that a
+/// dependency declared in an inline table spanning several lines cannot be updated.
+pub fn requirements_diff(
+    krate: &CrateInfo,
+    manifest: &str,
+    updates: &[(&Dependency, &str)],
+) -> Result<String> {
+    edit_requirements(krate, manifest, updates)
+        .with_context(|| format!("Failed to update the manifest of {}", krate.name))
+        .map_err(Error::Render)
+}
+
+fn edit_requirements(
+    krate: &CrateInfo,
+    contents: &str,
+    updates: &[(&Dependency, &str)],
+) -> anyhow::Result<String> {
+    let path = krate
+        .manifest
+        .as_ref()
+        .context("The path of the manifest is not known")?;
+    let path = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let old = contents.split_inclusive('\n').collect::<Vec<_>>();
+    let mut new = old.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+    for (d, req) in updates {
+        let line = find_requirement(&new, d).with_context(|| {
+            format!(
+                "No line declares {} = {:?} in [{}]",
+                d.key(),
+                d.req,
+                d.kind.section()
+            )
+        })?;
+        new[line] = new[line].replacen(&format!("{:?}", d.req), &format!("{:?}", req), 1);
+    }
+    Ok(unified_diff(&path, &old, &new))
+}
+
+/// Line of `lines`, those of a manifest, declaring the requirement of dependency `d`, either as
+/// `key = "req"`, `key = { version = "req", ... }` or `version = "req"` in a `[dependencies.key]`
+/// table, in any target-specific table too
+fn find_requirement(lines: &[String], d: &Dependency) -> Option<usize> {
+    let section = d.kind.section();
+    let req = format!("{:?}", d.req);
+    let unquote = |key: &str| key.trim().trim_matches(&['"', '\''] as &[char]).to_string();
+    let mut table = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            table = header.split('.').map(unquote).collect();
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (unquote(key), value.trim()),
+            None => continue,
+        };
+        let declares = match table.iter().map(|t| t as &str).collect::<Vec<_>>()[..] {
+            // The workspace declares requirements on behalf of its members
+            ["workspace", ..] => false,
+            // `[target.'cfg(unix)'.dependencies]` is split in the dots of its `cfg` too
+            [.., s] if s == section => key == d.key(),
+            [.., s, k] if s == section && k == d.key() => key == "version",
+            _ => false,
+        };
+        let matches = match value.strip_prefix('{') {
+            Some(inline) => inline
+                .split(',')
+                .filter_map(|field| field.split_once('='))
+                .any(|(k, v)| {
+                    k.trim() == "version" && v.trim().trim_end_matches('}').trim() == req
+                }),
+            None => value == req,
+        };
+        if declares && matches {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Unified diff, with 3 lines of context, of the file at `path` from `old` to `new`, which have
+/// the same number of lines, each with its line ending
+fn unified_diff(path: &str, old: &[&str], new: &[String]) -> String {
+    const CONTEXT: usize = 3;
+    let changed = (0..old.len())
+        .filter(|i| old[*i] != new[*i])
+        .collect::<Vec<_>>();
+    let mut out = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+    let mut hunks = Vec::<(usize, usize)>::new();
+    for i in changed.iter().copied() {
+        let (start, end) = (i.saturating_sub(CONTEXT), (i + CONTEXT + 1).min(old.len()));
+        match hunks.last_mut() {
+            Some((_, last)) if start <= *last => *last = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    let line = |out: &mut String, prefix: char, l: &str| {
+        out.push(prefix);
+        out.push_str(l);
+        if !l.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    for (start, end) in hunks {
+        let len = end - start;
+        out += &format!("@@ -{},{} +{},{} @@\n", start + 1, len, start + 1, len);
+        let mut i = start;
+        while i < end {
+            if old[i] == new[i] {
+                line(&mut out, ' ', old[i]);
+                i += 1;
+                continue;
+            }
+            let run = (i..end).take_while(|j| old[*j] != new[*j]).count();
+            for l in &old[i..i + run] {
+                line(&mut out, '-', l);
+            }
+            for l in &new[i..i + run] {
+                line(&mut out, '+', l);
+            }
+            i += run;
+        }
+    }
+    out
+}
+
+/// Requirements of crates of the repositories on another one that are semver-incompatible with
+/// each other, and the fewest requirement changes unifying them
+pub struct Unification<'a> {
+    /// Repository of the crate depended on
+    pub repo: &'a str,
+    /// Crate depended on
+    pub krate: &'a CrateInfo,
+    /// Number of requirements by compatibility class, eg. "1" or "0.4"
+    pub classes: BTreeMap<String, usize>,
+    /// Compatibility class the requirements are unified on
+    pub target: String,
+    /// Requirements to change, along with the requirement to change them to
+    pub changes: Vec<(&'a str, &'a CrateInfo, &'a Dependency, String)>,
+}
+
+/// Returns, for each crate of the repositories that other crates require with semver-incompatible
+/// requirements, the fewest requirement changes unifying them on a compatibility class
+///
+/// The class of the current version of the crate wins ties, then the most recent one. The
+/// requirements are changed to the current version if they are unified on its class, and to the
+/// most recent version required in their class otherwise, with their operator and precision.
+pub fn unify_requirements(infos: &BTreeMap<String, Vec<CrateInfo>>) -> Vec<Unification<'_>> {
+    let index = CrateIndex::new(infos);
+    let mut requirements = BTreeMap::<&str, Vec<_>>::new();
+    for (repo, c) in all_crates(infos) {
+        for d in c.deps.iter() {
+            if d.name == c.name || index.get(&d.name).is_none() {
+                continue;
+            }
+            if let Some(class) = compat_class(&d.req) {
+                requirements
+                    .entry(&d.name)
+                    .or_default()
+                    .push((repo, c, d, class));
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+    for (name, reqs) in requirements {
+        let mut classes = BTreeMap::<String, usize>::new();
+        for (_, _, _, class) in reqs.iter() {
+            *classes.entry(class.clone()).or_default() += 1;
+        }
+        if classes.len() < 2 {
+            continue;
+        }
+        let (repo, krate) = index.get(name).unwrap();
+        let current = compat_class(&krate.version);
+        let version_of = |req: &str| {
+            semver::VersionReq::parse(req).ok().and_then(|r| {
+                let c = r.comparators.first()?;
+                Some(semver::Version::new(
+                    c.major,
+                    c.minor.unwrap_or(0),
+                    c.patch.unwrap_or(0),
+                ))
+            })
+        };
+        let target = classes
+            .iter()
+            .max_by_key(|(class, count)| {
+                let newest = reqs
+                    .iter()
+                    .filter(|(_, _, _, c)| c == *class)
+                    .filter_map(|(_, _, d, _)| version_of(&d.req))
+                    .max();
+                (**count, current.as_ref() == Some(*class), newest)
+            })
+            .map(|(class, _)| class.clone())
+            .unwrap();
+        let version = match current.as_ref() == Some(&target) {
+            true => krate.version.clone(),
+            false => reqs
+                .iter()
+                .filter(|(_, _, _, c)| *c == target)
+                .filter_map(|(_, _, d, _)| version_of(&d.req))
+                .max()
+                .map_or(target.clone(), |v| v.to_string()),
+        };
+        let changes = reqs
+            .iter()
+            .filter(|(_, _, _, class)| *class != target)
+            .map(|(r, c, d, _)| (*r, *c, *d, updated_requirement(&d.req, &version)))
+            .collect();
+        res.push(Unification {
+            repo,
+            krate,
+            classes,
+            target,
+            changes,
+        });
+    }
+    res
+}
+
+/// Requirement on `version` written like `req`, eg. "^2.1" for "^1.4" and version 2.1.3, or just
+/// `version` if `req` has several comparators
+fn updated_requirement(req: &str, version: &str) -> String {
+    if req.contains(|c| ",<>*".contains(c)) {
+        return version.to_string();
+    }
+    let req = req.trim();
+    let operator = &req[..req.len() - req.trim_start_matches(&['^', '~', '='] as &[char]).len()];
+    let precision = req.trim_start_matches(operator).trim().split('.').count();
+    let version = version.split('.').take(precision).collect::<Vec<_>>();
+    format!("{}{}", operator, version.join("."))
+}
+
+/// Returns true iff `name` matches `pattern`, where `*` matches any string
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(i) => {
+            let (prefix, rest) = (&pattern[..i], &pattern[i + 1..]);
+            name.starts_with(prefix)
+                && (prefix.len()..=name.len())
+                    .any(|j| name.is_char_boundary(j) && glob_match(rest, &name[j..]))
+        }
+    }
+}
+
+/// Returns the crates reachable from crate `from` in at most `depth` steps, following the
+/// edges given by `next`, in breadth-first order
+fn reachable<'a>(
+    from: &'a CrateInfo,
+    depth: usize,
+    next: impl Fn(&'a CrateInfo) -> Vec<(&'a str, &'a CrateInfo)>,
+) -> Vec<(&'a str, &'a CrateInfo)> {
+    let mut res = Vec::<(&str, &CrateInfo)>::new();
+    let mut frontier = vec![from];
+    for _ in 0..depth {
+        if frontier.is_empty() {
+            break;
+        }
+        let mut new_frontier = Vec::new();
+        for c in frontier {
+            for (repo, n) in next(c) {
+                if n.name != from.name && !res.iter().any(|(_, r)| r.name == n.name) {
+                    res.push((repo, n));
+                    new_frontier.push(n);
+                }
+            }
+        }
+        frontier = new_frontier;
+    }
+    res
+}
+
+/// Returns the crates selected by `selector`, that is either `crate:pattern`,
+/// `repo:pattern` or `pattern` (for crates)
+pub fn select<'a>(
+    selector: &str,
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+) -> Result<Vec<(&'a str, &'a CrateInfo)>> {
+    let (kind, pattern) = selector.split_once(':').unwrap_or(("crate", selector));
+    let res = match kind {
+        "crate" => all_crates(infos)
+            .filter(|(_, c)| glob_match(pattern, &c.name))
+            .collect::<Vec<_>>(),
+        "repo" => all_crates(infos)
+            .filter(|(repo, _)| glob_match(pattern, repo))
+            .collect(),
+        _ => {
+            return Err(Error::Graph(anyhow::anyhow!(
+                "Unknown selector {:?}, expected `crate:pattern` or `repo:pattern`",
+                selector
+            )))
+        }
+    };
+    if res.is_empty() {
+        return Err(Error::Graph(anyhow::anyhow!(
+            "Selector {:?} matches no crate",
+            selector
+        )));
+    }
+    Ok(res)
+}
+
+/// Returns a shortest dependency path from any crate of `from` to each crate of `to` that
+/// can be reached
+pub fn paths_between<'a>(
+    from: &[(&'a str, &'a CrateInfo)],
+    to: &[(&'a str, &'a CrateInfo)],
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+) -> Vec<Vec<(&'a str, &'a CrateInfo)>> {
+    let index = CrateIndex::new(infos);
+    let mut parents = BTreeMap::<&str, Option<(&str, &CrateInfo)>>::new();
+    for (_, c) in from {
+        parents.insert(&c.name, None);
+    }
+    let mut frontier = from.to_vec();
+    while !frontier.is_empty() {
+        let mut new_frontier = Vec::new();
+        for (repo, c) in frontier {
+            for (dep_repo, d) in internal_deps(c, &index) {
+                if !parents.contains_key(&d.name as &str) {
+                    parents.insert(&d.name, Some((repo, c)));
+                    new_frontier.push((dep_repo, d));
+                }
+            }
+        }
+        frontier = new_frontier;
+    }
+    to.iter()
+        .filter(|(_, t)| matches!(parents.get(&t.name as &str), Some(Some(_))))
+        .map(|t| {
+            let mut path = vec![*t];
+            while let Some(Some(p)) = parents.get(&path.last().unwrap().1.name as &str) {
+                path.push(*p);
+            }
+            path.reverse();
+            path
+        })
+        .collect()
+}
+
+/// Evaluates a query, see `Command::Query` for the syntax
+pub fn query<'a>(
+    query: &str,
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+) -> Result<Vec<(&'a str, &'a CrateInfo)>> {
+    let query = query.trim();
+    let (function, args) = match (query.find('('), query.strip_suffix(')')) {
+        (Some(i), Some(q)) => (
+            q[..i].trim(),
+            q[i + 1..].split(',').map(str::trim).collect::<Vec<_>>(),
+        ),
+        _ => {
+            return Err(Error::Graph(anyhow::anyhow!(
+                "Query {:?} is not of the form `function(arguments)`",
+                query
+            )))
+        }
+    };
+    let index = CrateIndex::new(infos);
+    let find = |name: &str| {
+        index
+            .get(name)
+            .with_context(|| format!("Crate {} is not part of any repository", name))
+            .map_err(Error::Graph)
+    };
+    let depth = |arg: Option<&&str>| match arg {
+        None => Ok(usize::MAX),
+        Some(d) => d
+            .parse::<usize>()
+            .with_context(|| format!("Failed to parse depth {:?}", d))
+            .map_err(Error::Graph),
+    };
+    match (function, &args as &[&str]) {
+        ("deps", [krate]) | ("deps", [krate, _]) => {
+            let (_, c) = find(krate)?;
+            Ok(reachable(c, depth(args.get(1))?, |c| internal_deps(c, &index)))
+        }
+        ("rdeps", [krate]) | ("rdeps", [krate, _]) => {
+            let (_, c) = find(krate)?;
+            let rdeps = reverse_deps(infos);
+            Ok(reachable(c, depth(args.get(1))?, |c| {
+                rdeps.get(&c.name as &str).cloned().unwrap_or_default()
+            }))
+        }
+        ("path", [from, to]) => {
+            let from = find(from)?;
+            let (_, to) = find(to)?;
+            // Breadth-first search, remembering how each crate was reached
+            let mut parents = BTreeMap::<&str, (&str, &CrateInfo)>::new();
+            let mut frontier = vec![from];
+            while !frontier.is_empty() && !parents.contains_key(&to.name as &str) {
+                let mut new_frontier = Vec::new();
+                for (_, c) in frontier {
+                    for (repo, d) in internal_deps(c, &index) {
+                        if d.name != from.1.name && !parents.contains_key(&d.name as &str) {
+                            parents.insert(&d.name, (repo, c));
+                            new_frontier.push((repo, d));
+                        }
+                    }
+                }
+                frontier = new_frontier;
+            }
+            if !parents.contains_key(&to.name as &str) {
+                return Err(Error::Graph(anyhow::anyhow!(
+                    "There is no dependency path from {} to {}",
+                    from.1.name,
+                    to.name
+                )));
+            }
+            let mut path = vec![find(&to.name)?];
+            while let Some(parent) = parents.get(&path.last().unwrap().1.name as &str) {
+                let (repo, _) = find(&parent.1.name)?;
+                path.push((repo, parent.1));
+            }
+            path.reverse();
+            Ok(path)
+        }
+        ("repo", [pattern]) => Ok(infos
+            .iter()
+            .filter(|(repo, _)| glob_match(pattern, repo))
+            .flat_map(|(repo, crates)| crates.iter().map(move |c| (repo as &str, c)))
+            .collect()),
+        _ => Err(Error::Graph(anyhow::anyhow!(
+            "Unknown query function {}/{}, expected deps/1, deps/2, rdeps/1, rdeps/2, path/2 or repo/1",
+            function,
+            args.len()
+        ))),
+    }
+}
+
+/// Returns the strongly connected components of `graph`, only following the dependencies that
+/// `keep` selects, in reverse topological order, with petgraph's implementation of Tarjan's
+/// algorithm
+pub fn strongly_connected_components<'a>(
+    graph: &graph::DependencyGraph<'a>,
+    keep: impl Fn(&Dependency) -> bool,
+) -> Vec<Vec<&'a str>> {
+    let kept = petgraph::visit::EdgeFiltered::from_fn(&graph.graph, |e| keep(e.weight()));
+    petgraph::algo::tarjan_scc(&kept)
+        .into_iter()
+        .map(|scc| {
+            scc.into_iter()
+                .map(|n| &graph.node(n).info.name as &str)
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the immediate dominator of each crate of `graph` reachable from the crates `roots`
+/// through the dependencies of the given kinds, ie. the last crate that all the dependency paths
+/// from the roots to this crate go through, or `None` for crates that are not dominated by any
+/// single crate
+pub fn dominators<'a>(
+    graph: &graph::DependencyGraph<'a>,
+    roots: &[&str],
+    kinds: &[DepKind],
+) -> BTreeMap<&'a str, Option<&'a str>> {
+    // petgraph needs a single root, so add a virtual one, depending on all the roots
+    let mut deps = graph.graph.filter_map(
+        |_, c| Some(Some(&c.info.name as &'a str)),
+        |_, d| kinds.contains(&d.kind).then_some(()),
+    );
+    let root = deps.add_node(None);
+    for r in roots.iter().filter_map(|r| graph.index(r)) {
+        deps.add_edge(root, r, ());
+    }
+    let dominators = petgraph::algo::dominators::simple_fast(&deps, root);
+    deps.node_indices()
+        .filter(|n| *n != root)
+        .filter_map(|n| {
+            let idom = dominators.immediate_dominator(n)?;
+            Some((deps[n]?, deps[idom]))
+        })
+        .collect()
+}
+
+/// Crates of each repository and their dependencies, as serialized to JSON by [`export`]
+#[derive(Debug, serde::Serialize)]
+pub struct Export<'a> {
+    /// Version of the schema, [`SCHEMA_VERSION`]
+    pub schema_version: u32,
+    /// Crates of each repository
+    pub repositories: &'a BTreeMap<String, Vec<CrateInfo>>,
+    /// Groups and owners of the repositories, and the deprecated crates, as the `groups`,
+    /// `owners` and `deprecated` fields if any
+    #[serde(flatten)]
+    pub ownership: &'a Ownership,
+}
+
+/// Describe the crates of each repository and their dependencies as JSON
+///
+/// The crates are borrowed rather than copied into a JSON value, so that they can be
+/// serialized without holding them twice in memory. See [`SCHEMA_VERSION`] for the
+/// compatibility guarantees of the format.
+pub fn export<'a>(
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+    ownership: &'a Ownership,
+) -> Export<'a> {
+    Export {
+        schema_version: SCHEMA_VERSION,
+        repositories: infos,
+        ownership,
+    }
+}
+
+/// Read back the crates of each repository from the JSON written by [`export`]
+pub fn import(json: &[u8]) -> Result<BTreeMap<String, Vec<CrateInfo>>> {
+    import_model(json).map(|(infos, _)| infos)
+}
+
+/// Read back the crates of each repository, the groups and owners of the repositories, and the
+/// deprecated crates, from the JSON written by [`export`]
+pub fn import_model(json: &[u8]) -> Result<(BTreeMap<String, Vec<CrateInfo>>, Ownership)> {
+    #[derive(serde::Deserialize)]
+    struct Import {
+        schema_version: u32,
+        repositories: BTreeMap<String, Vec<CrateInfo>>,
+        #[serde(flatten)]
+        ownership: Ownership,
+    }
+
+    let import: Import = serde_json::from_slice(json)
+        .context("Failed to parse the exported crates")
+        .map_err(Error::Snapshot)?;
+    if import.schema_version != SCHEMA_VERSION {
+        return Err(Error::Snapshot(anyhow::anyhow!(
+            "The crates were exported with schema version {}, while version {} is supported",
+            import.schema_version,
+            SCHEMA_VERSION
+        )));
+    }
+    Ok((import.repositories, import.ownership))
+}
+
+/// Format the graph can be rendered to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Graphviz description language
+    Dot,
+    /// SVG, rendered from DOT with graphviz's `dot`
+    Svg,
+    /// Mermaid flowchart
+    Mermaid,
+    /// Output of [`export`]
+    Json,
+    /// Markdown document embedding the Mermaid flowchart
+    Markdown,
+    /// Self-contained HTML page with the SVG graph, the lint findings, the crates of each
+    /// repository and key metrics
+    HtmlReport,
+}
+
+impl Format {
+    /// Format matching the extension of `path`, if any
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "dot" | "gv" => Some(Format::Dot),
+            "svg" => Some(Format::Svg),
+            "mmd" => Some(Format::Mermaid),
+            "json" => Some(Format::Json),
+            "md" => Some(Format::Markdown),
+            "html" => Some(Format::HtmlReport),
+            _ => None,
+        }
+    }
+
+    /// Extension of the files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Dot => "dot",
+            Format::Svg => "svg",
+            Format::Mermaid => "mmd",
+            Format::Json => "json",
+            Format::Markdown => "md",
+            Format::HtmlReport => "html",
+        }
+    }
+
+    /// Renderer of the format, showing repositories as `options.graph_type` where supported
+    pub fn renderer(self, options: &RenderOptions) -> Box<dyn render::Renderer> {
+        let graph_type = options.graph_type;
+        match self {
+            Format::Dot => Box::new(render::Dot { graph_type }),
+            Format::Svg => Box::new(render::Svg {
+                graph_type,
+                graphviz: options.graphviz.clone(),
+            }),
+            Format::Mermaid => Box::new(render::Mermaid),
+            Format::Json => Box::new(render::Json),
+            Format::Markdown => Box::new(render::Markdown),
+            Format::HtmlReport => Box::new(render::HtmlReport {
+                graph_type,
+                graphviz: options.graphviz.clone(),
+                lints: options.lints.clone(),
+            }),
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "svg" => Ok(Format::Svg),
+            "mermaid" => Ok(Format::Mermaid),
+            "json" => Ok(Format::Json),
+            "md" => Ok(Format::Markdown),
+            "html-report" => Ok(Format::HtmlReport),
+            _ => Err(Error::Render(anyhow::anyhow!("Unknown format {:?}", s))),
+        }
+    }
+}
+
+/// How the graph shows the repository of each crate
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GraphType {
+    /// Crates are grouped in a cluster for each repository
+    #[default]
+    Cluster,
+    /// Crates are colored by repository
+    Colors,
+    /// Crates are collapsed into a node per group of repositories, repositories without a group
+    /// being a group of their own
+    Groups,
+}
+
+/// Dependencies drawn in the graph
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EdgeFilter {
+    /// Path-local dependencies and dependencies from our own registries
+    #[default]
+    All,
+    /// Path-local dependencies only, ie. the wiring inside the repositories
+    Path,
+    /// Dependencies from our own registries only, ie. those that matter for publishing
+    Registry,
+}
+
+impl std::str::FromStr for EdgeFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<EdgeFilter> {
+        match s {
+            "all" => Ok(EdgeFilter::All),
+            "path" => Ok(EdgeFilter::Path),
+            "registry" => Ok(EdgeFilter::Registry),
+            _ => Err(Error::Render(anyhow::anyhow!(
+                "Unknown edge filter {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+/// How the graph is drawn, whatever its format
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// How the repository of each crate is shown
+    pub graph_type: GraphType,
+    /// Whether each cycle is collapsed into a single node
+    pub condense: bool,
+    /// Patterns of the crates made to stand out, where `*` matches any string
+    pub highlight: Vec<String>,
+    /// Dependencies drawn
+    pub edges: EdgeFilter,
+    /// Kind of the only dependencies drawn, if not all of them are
+    pub only_kind: Option<DepKind>,
+    /// Whether the dependencies on crates outside of the repositories are drawn too, with a
+    /// single node per external crate
+    pub external: bool,
+    /// Number of crates kept, those with the most dependencies and dependents, the others being
+    /// replaced with a placeholder node per repository, if the graph is truncated
+    pub max_nodes: Option<usize>,
+    /// Engine and arguments of graphviz, for the SVG output
+    pub graphviz: GraphvizConfig,
+    /// Findings of the lints, listed by the HTML report
+    pub lints: Vec<LintReport>,
+}
+
+/// Render the graph in `format`, where the repositories of each group of `ownership` are nested
+/// in a cluster of the group, and show their owner
+pub fn render(
+    format: Format,
+    options: &RenderOptions,
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    ownership: &Ownership,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    render_to(format, options, infos, ownership, &mut out)?;
+    Ok(out)
+}
+
+/// Render the graph like [`render`], writing it to `out` as it goes rather than to memory
+pub fn render_to(
+    format: Format,
+    options: &RenderOptions,
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    ownership: &Ownership,
+    out: &mut dyn Write,
+) -> Result<()> {
+    check_render_options(format, options)?;
+    format
+        .renderer(options)
+        .render(&render::Graph::new(infos, ownership, options), out)
+}
+
+/// Check that `format` supports `options`, which [`render`] and [`render_to`] do first, to check
+/// all the formats before rendering any of them
+pub fn check_render_options(format: Format, options: &RenderOptions) -> Result<()> {
+    let RenderOptions {
+        graph_type,
+        condense,
+        ref highlight,
+        external,
+        max_nodes,
+        only_kind,
+        edges,
+        ..
+    } = *options;
+    if !matches!(format, Format::Dot | Format::Svg | Format::HtmlReport)
+        && (graph_type != GraphType::Cluster || condense || max_nodes.is_some())
+    {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--use-colors, --collapse-groups, --condense and --max-nodes are only supported by the dot, svg and html-report formats"
+        )));
+    }
+    if only_kind.is_some() && matches!(format, Format::Json) {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--only-dev-deps and --only-build-deps are not supported by the JSON output"
+        )));
+    }
+    if edges != EdgeFilter::All && matches!(format, Format::Json) {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--edges path and --edges registry are not supported by the JSON output"
+        )));
+    }
+    if max_nodes.is_some() && (graph_type == GraphType::Groups || condense) {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--max-nodes cannot be combined with --condense nor --collapse-groups"
+        )));
+    }
+    if graph_type == GraphType::Groups && condense {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--condense cannot be combined with --collapse-groups"
+        )));
+    }
+    if !highlight.is_empty() && (matches!(format, Format::Json) || graph_type == GraphType::Groups)
+    {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--highlight is only supported by the dot, svg and mermaid formats, without --collapse-groups"
+        )));
+    }
+    if external && (matches!(format, Format::Json) || graph_type == GraphType::Groups) {
+        return Err(Error::Render(anyhow::anyhow!(
+            "--external-deps is only supported by the dot, svg and mermaid formats, without --collapse-groups"
+        )));
+    }
+    Ok(())
+}
+
+/// Pages of the static site of the graph, by path relative to its root, the overview graph being
+/// drawn with `options` and the lint dashboard listing `options.lints`
+pub fn site_pages(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    ownership: &Ownership,
+    options: &RenderOptions,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    site::pages(infos, ownership, options)
+}
+
+/// Statistic shown by a [`badge`]
+#[derive(Clone, Copy, Debug)]
+pub enum BadgeMetric {
+    /// Cyclic dependencies across repositories, green when there are none
+    Cycles,
+    /// Dependencies between crates of different repositories
+    CrossRepoEdges,
+    /// Crates of all the repositories
+    Crates,
+}
+
+impl std::str::FromStr for BadgeMetric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<BadgeMetric> {
+        match s {
+            "cycles" => Ok(BadgeMetric::Cycles),
+            "cross-repo-edges" => Ok(BadgeMetric::CrossRepoEdges),
+            "crates" => Ok(BadgeMetric::Crates),
+            _ => Err(Error::Render(anyhow::anyhow!("Unknown metric {:?}", s))),
+        }
+    }
+}
+
+/// Render a shields-style badge showing `metric` for the crates of `infos`, as SVG
+pub fn badge(metric: BadgeMetric, infos: &BTreeMap<String, Vec<CrateInfo>>) -> String {
+    const GREEN: &str = "#4c1";
+    const RED: &str = "#e05d44";
+    const BLUE: &str = "#007ec6";
+    let (label, value, color) = match metric {
+        BadgeMetric::Cycles => {
+            let cycles = find_cycles(infos).len();
+            ("cycles", cycles, if cycles == 0 { GREEN } else { RED })
+        }
+        BadgeMetric::CrossRepoEdges => {
+            let edges = internal_edges(infos)
+                .into_iter()
+                .filter(|(repo, _, dep_repo, _)| repo != dep_repo)
+                .count();
+            ("cross-repo edges", edges, BLUE)
+        }
+        BadgeMetric::Crates => ("crates", all_crates(infos).count(), BLUE),
+    };
+    let value = value.to_string();
+
+    // There is no font to measure the text with, so assume the average width of Verdana 11px
+    let width = |text: &str| 10 + 7 * text.chars().count();
+    let (label_width, value_width) = (width(label), width(&value));
+    let total = label_width + value_width;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {value}">
+  <title>{label}: {value}</title>
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <clipPath id="r">
+    <rect width="{total}" height="20" rx="3" fill="#fff"/>
+  </clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{value_width}" height="20" fill="{color}"/>
+    <rect width="{total}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text>
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{value_x}" y="15" fill="#010101" fill-opacity=".3">{value}</text>
+    <text x="{value_x}" y="14">{value}</text>
+  </g>
+</svg>
+"##,
+        total = total,
+        label = label,
+        value = value,
+        label_width = label_width,
+        value_width = value_width,
+        color = color,
+        label_x = label_width as f64 / 2.,
+        value_x = label_width as f64 + value_width as f64 / 2.,
+    )
+}
+
+/// What each Backstage component of a [`backstage_catalog`] stands for
+#[derive(Clone, Copy, Debug)]
+pub enum CatalogGranularity {
+    /// A component per crate
+    Crate,
+    /// A component per repository, depending on the repositories its crates depend on
+    Repo,
+}
+
+impl std::str::FromStr for CatalogGranularity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<CatalogGranularity> {
+        match s {
+            "crate" => Ok(CatalogGranularity::Crate),
+            "repo" => Ok(CatalogGranularity::Repo),
+            _ => Err(Error::Render(anyhow::anyhow!(
+                "Unknown granularity {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Render the crates of `infos` as Backstage `Component` entities of `catalog-info.yaml`, with
+/// `dependsOn` relations for their normal and build dependencies
+///
+/// `owner` and `lifecycle` are those of every component, which are all of type `library`.
+pub fn backstage_catalog(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    per: CatalogGranularity,
+    owner: &str,
+    lifecycle: &str,
+) -> String {
+    let deps = adjacency(
+        &graph::DependencyGraph::new(infos),
+        &[DepKind::Normal, DepKind::Build],
+    );
+    // (name, annotation, dependencies) of each component
+    let components: Vec<(&str, (&str, String), Vec<&str>)> = match per {
+        CatalogGranularity::Crate => all_crates(infos)
+            .map(|(repo, c)| {
+                let name = &c.name as &str;
+                (name, ("repository", repo.to_string()), deps[name].clone())
+            })
+            .collect(),
+        CatalogGranularity::Repo => {
+            let repos = all_crates(infos)
+                .map(|(repo, c)| (&c.name as &str, repo))
+                .collect::<BTreeMap<_, _>>();
+            infos
+                .iter()
+                .map(|(repo, crates)| {
+                    let mut repo_deps = crates
+                        .iter()
+                        .flat_map(|c| deps[&c.name as &str].iter().map(|d| repos[d]))
+                        .filter(|r| r != repo)
+                        .collect::<Vec<_>>();
+                    repo_deps.sort_unstable();
+                    repo_deps.dedup();
+                    let names = crates.iter().map(|c| &c.name as &str).collect::<Vec<_>>();
+                    (repo as &str, ("crates", names.join(", ")), repo_deps)
+                })
+                .collect()
+        }
+    };
+
+    // JSON strings are valid YAML, and escape whatever the names contain
+    let quote = |s: &str| serde_json::Value::from(s).to_string();
+    let mut out = String::new();
+    for (name, (annotation, value), deps) in components {
+        out += "---\n";
+        out += "apiVersion: backstage.io/v1alpha1\n";
+        out += "kind: Component\n";
+        out += "metadata:\n";
+        out += &format!("  name: {}\n", quote(name));
+        out += "  annotations:\n";
+        out += &format!("    muregraph/{}: {}\n", annotation, quote(&value));
+        out += "spec:\n";
+        out += "  type: library\n";
+        out += &format!("  lifecycle: {}\n", quote(lifecycle));
+        out += &format!("  owner: {}\n", quote(owner));
+        if deps.is_empty() {
+            out += "  dependsOn: []\n";
+        } else {
+            out += "  dependsOn:\n";
+            for d in deps {
+                out += &format!("    - {}\n", quote(&format!("component:{}", d)));
+            }
+        }
+    }
+    out
+}
+
+/// Release tool that [`release_configs`] configures
+#[derive(Clone, Copy, Debug)]
+pub enum ReleaseTool {
+    /// cargo-release, configured by a `release.toml` at the root of the workspace
+    CargoRelease,
+    /// release-plz, configured by a `release-plz.toml` at the root of the workspace
+    ReleasePlz,
+}
+
+impl ReleaseTool {
+    /// Name of the configuration file of the tool, at the root of the workspace
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ReleaseTool::CargoRelease => "release.toml",
+            ReleaseTool::ReleasePlz => "release-plz.toml",
+        }
+    }
+}
+
+impl std::str::FromStr for ReleaseTool {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ReleaseTool> {
+        match s {
+            "cargo-release" => Ok(ReleaseTool::CargoRelease),
+            "release-plz" => Ok(ReleaseTool::ReleasePlz),
+            _ => Err(Error::Render(anyhow::anyhow!(
+                "Unknown release tool {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Place of the crates of a workspace in the publish order across repositories, written by
+/// [`release_configs`] for scripts driving the release tools
+#[derive(serde::Serialize)]
+struct WorkspaceOrder<'a> {
+    /// Number of groups of the publish order across repositories
+    groups: usize,
+    /// Publishable crates of the workspace, in publish order
+    crates: Vec<OrderedCrate<'a>>,
+    /// Requirements of crates of other repositories on those of the workspace, to update after
+    /// publishing them
+    requirements: Vec<ExternalRequirement<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct OrderedCrate<'a> {
+    name: &'a str,
+    version: &'a str,
+    /// Group of [`publish_order`] of the crate, from 1
+    group: usize,
+    /// Crates of other repositories to publish before
+    after: Vec<CrateRef<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct CrateRef<'a> {
+    name: &'a str,
+    repo: &'a str,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+struct ExternalRequirement<'a> {
+    #[serde(rename = "crate")]
+    krate: &'a str,
+    repo: &'a str,
+    dependency: &'a str,
+    requirement: &'a str,
+}
+
+/// Configuration of `tool` for the workspace of each repository with publishable crates, along
+/// with its place in the publish order across repositories, by path relative to the directory
+/// they are written to, eg. "core/release.toml" and "core/publish-order.json"
+///
+/// The tools only order the crates of a workspace, so comments of the configuration and
+/// `publish-order.json` tell how it fits in the publish order across repositories: the group of
+/// [`publish_order`] of each of its crates, the crates of other repositories to publish before,
+/// and the requirements of other repositories on its crates to update after publishing them.
+/// Directories are named after the repositories, keeping only the characters that are safe in
+/// file names.
+pub fn release_configs(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    tool: ReleaseTool,
+) -> Result<BTreeMap<String, String>> {
+    let index = CrateIndex::new(infos);
+    let groups = publish_order(&graph::DependencyGraph::new(infos))?;
+    let mut res = BTreeMap::new();
+    for (repo, crates) in infos {
+        let ordered = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| {
+                group
+                    .iter()
+                    .filter(|(r, _)| r == repo)
+                    .map(move |(_, c)| (i + 1, *c))
+            })
+            .collect::<Vec<_>>();
+        if ordered.is_empty() {
+            continue;
+        }
+
+        let mut order = WorkspaceOrder {
+            groups: groups.len(),
+            crates: Vec::new(),
+            requirements: Vec::new(),
+        };
+        let mut out =
+            String::from("# Generated by muregraph from the dependencies across repositories\n#\n");
+        out += &format!(
+            "# Crates of the workspace, in the publish order across repositories ({} groups):\n",
+            groups.len()
+        );
+        for (group, c) in ordered.iter() {
+            let after = internal_deps(c, &index)
+                .into_iter()
+                .filter(|(r, dep)| {
+                    r != repo
+                        && !matches!(dep.published_to, Publish::Nowhere)
+                        && c.deps
+                            .iter()
+                            .any(|d| d.name == dep.name && d.kind != DepKind::Dev)
+                })
+                .map(|(repo, dep)| CrateRef {
+                    name: &dep.name,
+                    repo,
+                })
+                .collect::<Vec<_>>();
+            out += &format!("#   group {}: {} {}", group, c.name, c.version);
+            if !after.is_empty() {
+                let after = after
+                    .iter()
+                    .map(|c| format!("{} [{}]", c.name, c.repo))
+                    .collect::<Vec<_>>();
+                out += &format!(", after {}", after.join(", "));
+            }
+            out += "\n";
+            order.crates.push(OrderedCrate {
+                name: &c.name,
+                version: &c.version,
+                group: *group,
+                after,
+            });
+        }
+        order.requirements = all_crates(infos)
+            .filter(|(r, _)| r != repo)
+            .flat_map(|(r, c)| c.deps.iter().map(move |d| (r, c, d)))
+            .filter(|(_, _, d)| {
+                !(d.has_path && d.req == "*") && ordered.iter().any(|(_, c)| c.name == d.name)
+            })
+            .map(|(r, c, d)| ExternalRequirement {
+                krate: &c.name,
+                repo: r,
+                dependency: &d.name,
+                requirement: &d.req,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if !order.requirements.is_empty() {
+            out += "#\n# Requirements of other repositories to update after publishing:\n";
+            for r in order.requirements.iter() {
+                out += &format!(
+                    "#   {} [{}]: {} = {:?}\n",
+                    r.krate, r.repo, r.dependency, r.requirement
+                );
+            }
+        }
+
+        out += "\n";
+        match tool {
+            ReleaseTool::CargoRelease => {
+                out += "# Update the requirements of the crates of the workspace on each other\n";
+                out += "dependent-version = \"upgrade\"\n";
+                let mut registries = ordered.iter().map(|(_, c)| match &c.published_to {
+                    Publish::At(registries) if registries.len() == 1 => Some(&registries[0]),
+                    _ => None,
+                });
+                if let Some(Some(registry)) = registries.next() {
+                    if registries.all(|r| r == Some(registry)) {
+                        out += &format!("registry = {:?}\n", registry);
+                    }
+                }
+            }
+            ReleaseTool::ReleasePlz => {
+                out += "[workspace]\n";
+                for c in crates
+                    .iter()
+                    .filter(|c| matches!(c.published_to, Publish::Nowhere))
+                {
+                    out += &format!(
+                        "\n[[package]]\nname = {:?}\npublish = false\nrelease = false\n",
+                        c.name
+                    );
+                }
+            }
+        }
+        let dir = render::page_name(repo);
+        let mut json = serde_json::to_string_pretty(&order)
+            .context("Failed to serialize the publish order")
+            .map_err(Error::Render)?;
+        json.push('\n');
+        res.insert(format!("{}/{}", dir, tool.file_name()), out);
+        res.insert(format!("{}/publish-order.json", dir), json);
+    }
+    Ok(res)
+}
+
+/// Render a graph in the DOT format to SVG, with the engine of graphviz set by `graphviz`
+pub fn render_svg(dot: &[u8], graphviz: &GraphvizConfig) -> Result<Vec<u8>> {
+    let engine = graphviz.engine();
+    let mut child = std::process::Command::new(engine)
+        .arg("-Tsvg")
+        .args(&graphviz.args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`, is graphviz installed?", engine))
+        .map_err(Error::Render)?;
+    let mut stdin = child.stdin.take().expect("stdin of graphviz was not piped");
+    // Write from another thread, as graphviz may fill its output before reading all its input
+    let output = std::thread::scope(|s| {
+        let writer = s.spawn(move || stdin.write_all(dot));
+        let output = child.wait_with_output();
+        writer.join().expect("Writing to graphviz panicked")?;
+        output
+    })
+    .with_context(|| format!("Failed to run `{}`", engine))
+    .map_err(Error::Render)?;
+    if !output.status.success() {
+        return Err(Error::Render(anyhow::anyhow!(
+            "`{}` failed with {}",
+            engine,
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Read the configuration file at `path`, resolving the directories relative to it
+pub fn load_config(path: &Path) -> Result<Config> {
+    let cfg = std::fs::read(path)
+        .with_context(|| format!("Failed to read {:?}", path))
+        .map_err(Error::Config)?;
+    let mut cfg: Config = toml::from_slice(&cfg)
+        .with_context(|| format!("Failed to parse {:?}", path))
+        .map_err(Error::Config)?;
+    let mut repos = BTreeSet::new();
+    for repo in cfg.repos() {
+        if !repos.insert(repo) {
+            return Err(Error::Config(anyhow::anyhow!(
+                "Repository {} is listed in multiple sections of {:?}",
+                repo,
+                path
+            )));
+        }
+    }
+    for (section, map) in [
+        ("groups", &cfg.ownership.groups),
+        ("owners", &cfg.ownership.owners),
+        ("pins", &cfg.pins),
+    ] {
+        if let Some(repo) = map.keys().find(|r| !repos.contains(r as &str)) {
+            return Err(Error::Config(anyhow::anyhow!(
+                "Repository {} of [{}] is not listed in {:?}",
+                repo,
+                section,
+                path
+            )));
+        }
+    }
+    if let Some(repo) = cfg
+        .network
+        .repos
+        .keys()
+        .find(|r| !repos.contains(r as &str))
+    {
+        return Err(Error::Config(anyhow::anyhow!(
+            "Repository {} of [network.repos] is not listed in {:?}",
+            repo,
+            path
+        )));
+    }
+    for (repo, rev) in cfg.pins.iter() {
+        if let Some(url) = cfg.tarballs.get_mut(repo) {
+            *url = pin_archive_url(url, rev)
+                .with_context(|| {
+                    format!(
+                        "Cannot pin repository {} of {:?}, its URL is not that of a GitHub or GitLab archive",
+                        repo, path
+                    )
+                })
+                .map_err(Error::Config)?;
+        } else if !cfg.git.contains_key(repo) {
+            return Err(Error::Config(anyhow::anyhow!(
+                "Repository {} of [pins] is neither a tarball nor a git repository in {:?}",
+                repo,
+                path
+            )));
+        }
+    }
+    // Directories are relative to the configuration file
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    for dir in cfg
+        .directories
+        .values_mut()
+        .chain(cfg.cargo_metadata.values_mut())
+    {
+        *dir = base.join(&dir);
+    }
+    cfg.lints
+        .validate()
+        .and_then(|()| cfg.policy.validate())
+        .and_then(|()| cfg.network.validate())
+        .and_then(|()| cfg.notify.validate())
+        .and_then(|()| cfg.graphviz.validate())
+        .with_context(|| format!("Invalid configuration in {:?}", path))
+        .map_err(Error::Config)?;
+    Ok(cfg)
+}
+
+/// URL of the archive at `url` for ref `rev` instead, if it is a GitHub or GitLab archive
+fn pin_archive_url(url: &str, rev: &str) -> Option<String> {
+    let (url, query) = match url.split_once('?') {
+        Some((url, query)) => (url, format!("?{}", query)),
+        None => (url, String::new()),
+    };
+    let pinned = if let Some((base, _)) = url.split_once("/-/archive/") {
+        // GitLab: <project>/-/archive/<ref>/<name>-<ref>.tar.gz
+        let name = base.rsplit('/').next()?;
+        format!(
+            "{}/-/archive/{}/{}-{}.tar.gz",
+            base,
+            rev,
+            name,
+            rev.replace('/', "-")
+        )
+    } else if let Some((base, _)) = url.split_once("/tarball/") {
+        // GitHub: <repo>/tarball/<ref>
+        format!("{}/tarball/{}", base, rev)
+    } else if let Some((base, _)) = url.split_once("/tar.gz/") {
+        // GitHub: codeload.github.com/<repo>/tar.gz/<ref>
+        format!("{}/tar.gz/{}", base, rev)
+    } else if let (Some((base, _)), true) = (url.split_once("/archive/"), url.ends_with(".tar.gz"))
+    {
+        // GitHub: <repo>/archive/<ref>.tar.gz
+        format!("{}/archive/{}.tar.gz", base, rev)
+    } else {
+        return None;
+    };
+    Some(pinned + &query)
+}
+
+/// Downloads and parses the repositories, a few at a time
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Fetcher {
+    /// Client and limits of the downloads, from the configuration of the last call to
+    /// `fetch_all`
+    network: std::sync::RwLock<Network>,
+    /// Directory the archives are downloaded to, when they are not cached
+    dir: PathBuf,
+    /// Removes `dir` when dropped, unless it is kept with `--keep-temp`
+    _temp: Option<tempfile::TempDir>,
+    /// Whether to display progress bars
+    progress: bool,
+    /// Number of repositories to download concurrently
+    jobs: usize,
+    /// Directory keeping the downloaded archives across runs
+    cache_dir: Option<PathBuf>,
+    cache_mode: CacheMode,
+    /// Whether to skip the repositories that fail instead of failing altogether
+    keep_going: bool,
+    /// Whether to skip the manifests that cannot be parsed
+    lenient: bool,
+    /// Whether to measure the Rust sources of the crates
+    measure_size: bool,
+    /// Whether to leave out the optional dependencies of the crates
+    skip_optional_deps: bool,
+    /// Whether to keep the contents of the manifests, see [`Fetcher::manifest`]
+    keep_manifests: bool,
+    /// Contents of the manifests fetched by the last call to `fetch_all`, by crate, if kept
+    manifests: std::sync::Mutex<BTreeMap<String, String>>,
+    /// Statistics about the last call to `fetch_all`
+    stats: std::sync::Mutex<FetchStats>,
+    /// Crates fetched from each URL listed for several repositories during the current call to
+    /// `fetch_all`, see [`Fetcher::fetch_once`]
+    fetched: std::sync::Mutex<BTreeMap<String, std::sync::Arc<std::sync::Mutex<SharedCrates>>>>,
+}
+
+/// Crates fetched from a URL listed for several repositories
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct SharedCrates {
+    /// Repository the crates were fetched for, and the crates, once fetched successfully
+    fetched: Option<(String, Vec<CrateInfo>)>,
+    /// Number of repositories still to get the crates, the last one taking them
+    remaining: usize,
+}
+
+/// HTTP clients and limits of the downloads, from the `[network]` section of the configuration
+#[cfg(not(target_arch = "wasm32"))]
+struct Network {
+    /// Limits of the repositories without `[network.repos.<name>]` section
+    default: Limits,
+    /// Limits of the other repositories, by name
+    repos: BTreeMap<String, Limits>,
+}
+
+/// HTTP client and limits of the downloads of a repository
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+struct Limits {
+    client: reqwest::blocking::Client,
+    max_download_size: Option<u64>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Limits {
+    fn new(limits: &NetworkLimits) -> Result<Limits> {
+        let (connect_timeout, timeout) = limits.timeouts()?;
+        Ok(Limits {
+            client: limits.client()?,
+            max_download_size: limits.max_download_size()?,
+            connect_timeout,
+            timeout,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Network {
+    fn new(cfg: &NetworkConfig) -> Result<Network> {
+        Ok(Network {
+            default: Limits::new(&cfg.limits)?,
+            repos: cfg
+                .repos
+                .keys()
+                .map(|repo| Ok((repo.clone(), Limits::new(&cfg.limits(repo))?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    fn get(&self, repo: &str) -> &Limits {
+        self.repos.get(repo).unwrap_or(&self.default)
+    }
+}
+
+/// Options of a [`Fetcher`]
+#[derive(Debug, Default)]
+pub struct FetchOptions {
+    /// Whether to display progress bars
+    pub progress: bool,
+    /// Number of repositories to download concurrently
+    pub jobs: usize,
+    /// Directory keeping the downloaded archives across runs, created if needed
+    pub cache_dir: Option<PathBuf>,
+    /// How to use the archives of `cache_dir`
+    pub cache_mode: CacheMode,
+    /// Whether to skip the repositories that fail instead of failing altogether
+    pub keep_going: bool,
+    /// Whether to skip the manifests that cannot be parsed
+    pub lenient: bool,
+    /// Whether to keep the temporary directory the archives are downloaded to
+    pub keep_temp: bool,
+    /// Whether to measure the Rust sources of the crates, reading all the `.rs` files of the
+    /// repositories, see [`CrateInfo::size`]
+    pub measure_size: bool,
+    /// Whether to leave out the optional dependencies that the default features of the crates do
+    /// not enable, see [`Dependency::enabled_by_default`]
+    pub skip_optional_deps: bool,
+    /// Whether to keep the contents of the manifests in memory, to edit them with
+    /// [`requirements_diff`], see [`Fetcher::manifest`]
+    pub keep_manifests: bool,
+}
+
+/// How a [`Fetcher`] uses the cached archives
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CacheMode {
+    /// Download the archives that changed since they were cached
+    #[default]
+    Revalidate,
+    /// Use the cached archives as they are, only downloading the ones that are not cached
+    Offline,
+    /// Use the cached archives as they are, failing if some are not cached
+    Frozen,
+}
+
+/// Name of the cached archive downloaded from `url`, a hash of the URL as URLs can contain
+/// tokens
+pub fn cache_key(url: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(url.as_bytes()))
+}
+
+/// HTTP validators of a cached archive, to download it again only if it changed
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Statistics about fetching the repositories, for all the configurations fetched, eg. both the
+/// old and the new one of `muregraph diff`
+#[derive(Default)]
+pub struct FetchStats {
+    /// Time it took to fetch all the repositories
+    pub total: std::time::Duration,
+    /// Time it took to fetch each repository
+    pub repos: BTreeMap<String, RepoTimings>,
+    /// Number of repositories whose cached archive was used
+    pub cache_hits: usize,
+    /// Repositories skipped with `--keep-going`, with the reason why
+    pub failed: BTreeMap<String, String>,
+    /// Manifests skipped with `--lenient`
+    pub invalid_manifests: Vec<InvalidManifest>,
+}
+
+/// Manifest that could not be parsed
+#[derive(Debug)]
+pub struct InvalidManifest {
+    /// Repository the manifest comes from
+    pub repo: String,
+    /// Path of the manifest, in the repository
+    pub path: PathBuf,
+    /// Why the manifest could not be parsed
+    pub error: String,
+}
+
+/// Time spent in each phase of fetching a repository
+#[derive(Debug, Default)]
+pub struct RepoTimings {
+    /// Downloading the archive, or checking that the cached one is up to date
+    pub download: std::time::Duration,
+    /// Reading the archive, excluding the parsing of the manifests
+    pub decompress: std::time::Duration,
+    /// Parsing the manifests
+    pub parse: std::time::Duration,
+    /// Fetching the repository as a whole
+    pub total: std::time::Duration,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Fetcher {
+    /// Create a fetcher, with the temporary directory the archives are downloaded to
+    pub fn new(options: FetchOptions) -> Result<Fetcher> {
+        let temp = tempfile::Builder::new()
+            .prefix("muregraph-")
+            .tempdir()
+            .context("Failed to create a temporary directory")
+            .map_err(Error::Fetch)?;
+        let (dir, temp) = match options.keep_temp {
+            true => {
+                let dir = temp.keep();
+                tracing::warn!(dir = ?dir, "Keeping the temporary directory, remove it once done");
+                (dir, None)
+            }
+            false => (temp.path().to_path_buf(), Some(temp)),
+        };
+        if let Some(dir) = &options.cache_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create the cache directory {:?}", dir))
+                .map_err(Error::Fetch)?;
+        }
+        Ok(Fetcher {
+            network: std::sync::RwLock::new(Network::new(&NetworkConfig::default())?),
+            dir,
+            _temp: temp,
+            progress: options.progress,
+            jobs: options.jobs,
+            cache_dir: options.cache_dir,
+            cache_mode: options.cache_mode,
+            keep_going: options.keep_going,
+            lenient: options.lenient,
+            measure_size: options.measure_size,
+            skip_optional_deps: options.skip_optional_deps,
+            keep_manifests: options.keep_manifests,
+            manifests: Default::default(),
+            stats: Default::default(),
+            fetched: Default::default(),
+        })
+    }
+
+    /// Statistics about the calls to `fetch_all` since the fetcher was created or the last call to
+    /// `reset_stats`
+    pub fn stats(&self) -> std::sync::MutexGuard<'_, FetchStats> {
+        self.stats.lock().unwrap()
+    }
+
+    /// Forget the statistics of the previous calls to `fetch_all`, eg. before fetching the
+    /// repositories again to watch them
+    pub fn reset_stats(&self) {
+        *self.stats.lock().unwrap() = FetchStats::default();
+    }
+
+    /// Contents of the manifest of crate `krate`, as fetched by the last call to `fetch_all`, if
+    /// the fetcher keeps them with [`FetchOptions::keep_manifests`]
+    pub fn manifest(&self, krate: &str) -> Option<String> {
+        self.manifests.lock().unwrap().get(krate).cloned()
+    }
+
+    /// Keep `contents`, the manifest of crate `krate`, if the manifests are kept
+    pub(crate) fn keep_manifest(&self, krate: &str, contents: &[u8]) {
+        if self.keep_manifests {
+            self.manifests.lock().unwrap().insert(
+                krate.to_string(),
+                String::from_utf8_lossy(contents).into_owned(),
+            );
+        }
+    }
+
+    /// Connection timeout and total timeout of the downloads of `repo`, from the configuration of
+    /// the last call to `fetch_all`
+    pub fn timeouts(&self, repo: &str) -> (Option<Duration>, Option<Duration>) {
+        let network = self.network.read().unwrap();
+        let limits = network.get(repo);
+        (limits.connect_timeout, limits.timeout)
+    }
+
+    /// Directory files can be downloaded to, removed at exit unless `keep_temp` is set
+    pub fn temp_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Download the archive of a repository, unless it is already cached, returning its path
+    pub fn download(&self, name: &str, url: &str, bar: &indicatif::ProgressBar) -> Result<PathBuf> {
+        self.download_cached(name, url, bar).map_err(Error::Fetch)
+    }
+
+    fn download_cached(
+        &self,
+        name: &str,
+        url: &str,
+        bar: &indicatif::ProgressBar,
+    ) -> anyhow::Result<PathBuf> {
+        let cache_dir = match &self.cache_dir {
+            Some(dir) => dir,
+            None => {
+                let path = self.dir.join(name);
+                self.download_to(name, url, &path, None, bar)?;
+                return Ok(path);
+            }
+        };
+
+        let key = cache_key(url);
+        let path = cache_dir.join(&key);
+        let validators_path = cache_dir.join(format!("{}.json", key));
+        let cached = path.exists();
+        match (self.cache_mode, cached) {
+            (CacheMode::Frozen, false) => {
+                anyhow::bail!("{} is not cached, and --frozen forbids downloading it", url)
+            }
+            (CacheMode::Offline, true) | (CacheMode::Frozen, true) => {
+                self.stats.lock().unwrap().cache_hits += 1;
+                return Ok(path);
+            }
+            _ => (),
+        }
+
+        let validators = match cached {
+            true => std::fs::read(&validators_path)
+                .ok()
+                .and_then(|v| serde_json::from_slice(&v).ok())
+                .unwrap_or_default(),
+            false => CacheValidators::default(),
+        };
+        match self.download_to(name, url, &path, Some(&validators), bar)? {
+            Some(validators) => {
+                let validators = serde_json::to_vec(&validators)?;
+                std::fs::write(&validators_path, validators)
+                    .with_context(|| format!("Failed to write {:?}", validators_path))?;
+            }
+            None => {
+                tracing::debug!(repo = name, "Cached archive is up to date");
+                self.stats.lock().unwrap().cache_hits += 1;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Download `url` to `path` with the limits of `repo`, unless it did not change according to
+    /// `validators`, returning the validators of the downloaded file
+    fn download_to(
+        &self,
+        repo: &str,
+        url: &str,
+        path: &Path,
+        validators: Option<&CacheValidators>,
+        bar: &indicatif::ProgressBar,
+    ) -> anyhow::Result<Option<CacheValidators>> {
+        let (client, max_size) = {
+            let network = self.network.read().unwrap();
+            let limits = network.get(repo);
+            (limits.client.clone(), limits.max_download_size)
+        };
+        let mut request = client.get(url);
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let download = request
+            .send()
+            .with_context(|| format!("Failed to send GET request to URL {:?}", url))?;
+        if validators.is_some() && download.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        ensure!(
+            download.status().is_success(),
+            "GET request to {:?} was unsuccessful: {}",
+            url,
+            download.status()
+        );
+        let header = |h| {
+            download
+                .headers()
+                .get(h)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(String::from)
+        };
+        let validators = CacheValidators {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+        if let (Some(max), Some(size)) = (max_size, download.content_length()) {
+            ensure!(
+                size <= max,
+                "{:?} is {} bytes, more than the {} bytes of network.max-download-size",
+                url,
+                size,
+                max
+            );
+        }
+
+        // Download to a temporary file first, so that interrupted downloads do not end up in
+        // the cache
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        let dest = std::fs::File::create(&partial)
+            .with_context(|| format!("Failed to create file {:?}", partial))?;
+        // The server may not announce the size, or lie about it
+        let limit = max_size.map_or(u64::MAX, |max| max.saturating_add(1));
+        let size = std::io::copy(&mut download.take(limit), &mut bar.wrap_write(dest))
+            .with_context(|| format!("Failed to download {:?} to {:?}", url, partial))?;
+        if let Some(max) = max_size.filter(|max| size > *max) {
+            std::fs::remove_file(&partial).ok();
+            anyhow::bail!(
+                "{:?} is more than the {} bytes of network.max-download-size",
+                url,
+                max
+            );
+        }
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("Failed to move {:?} to {:?}", partial, path))?;
+        Ok(Some(validators))
+    }
+
+    /// Turn the failure to parse the manifest at `path` into a warning with `--lenient`
+    pub fn skip_invalid(
+        &self,
+        repo: &str,
+        path: &Path,
+        info: Result<Option<CrateInfo>>,
+    ) -> Result<Option<CrateInfo>> {
+        match info {
+            Err(e) if self.lenient => {
+                tracing::warn!(repo, manifest = ?path, "Skipping invalid manifest: {:#}", e);
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .invalid_manifests
+                    .push(InvalidManifest {
+                        repo: repo.to_string(),
+                        path: path.to_path_buf(),
+                        error: format!("{:#}", e),
+                    });
+                Ok(None)
+            }
+            info => info,
+        }
+    }
+
+    /// Fetch the crates of repository `repo` from `url` with `fetch`, unless another repository
+    /// of `[tarballs]` listed with the same URL was already fetched, in which case its crates
+    /// are reused
+    ///
+    /// Repositories fetched concurrently from the same URL wait for the first one to be
+    /// fetched. If it failed, the next one tries again, to report its own error. The crates are
+    /// only kept until the last repository listed with the URL gets them.
+    pub fn fetch_once(
+        &self,
+        repo: &str,
+        url: &str,
+        fetch: impl FnOnce() -> Result<Vec<CrateInfo>>,
+    ) -> Result<Vec<CrateInfo>> {
+        let shared = match self.fetched.lock().unwrap().get(url) {
+            Some(shared) => shared.clone(),
+            None => return fetch(),
+        };
+        let mut shared = shared.lock().unwrap();
+        shared.remaining = shared.remaining.saturating_sub(1);
+        if let Some((first, crates)) = shared.fetched.take() {
+            tracing::info!(repo, url, from = %first, "Reusing the crates fetched from the same URL");
+            // Report the manifests skipped with `--lenient` for this repository too
+            let mut stats = self.stats.lock().unwrap();
+            let invalid = stats
+                .invalid_manifests
+                .iter()
+                .filter(|m| m.repo == first)
+                .map(|m| InvalidManifest {
+                    repo: repo.to_string(),
+                    path: m.path.clone(),
+                    error: m.error.clone(),
+                })
+                .collect::<Vec<_>>();
+            stats.invalid_manifests.extend(invalid);
+            if shared.remaining > 0 {
+                shared.fetched = Some((first, crates.clone()));
+            }
+            return Ok(crates);
+        }
+        let crates = fetch()?;
+        if shared.remaining > 0 {
+            shared.fetched = Some((repo.to_string(), crates.clone()));
+        }
+        Ok(crates)
+    }
+
+    /// Download and parse all the repositories of `cfg`, keyed by name, adding to the statistics
+    /// of the previous calls
+    pub fn fetch_all(&self, cfg: &Config) -> Result<BTreeMap<String, Vec<CrateInfo>>> {
+        let start = std::time::Instant::now();
+        let mut shared = BTreeMap::<String, std::sync::Arc<std::sync::Mutex<SharedCrates>>>::new();
+        for url in cfg.tarballs.values() {
+            shared
+                .entry(url.clone())
+                .or_default()
+                .lock()
+                .unwrap()
+                .remaining += 1;
+        }
+        shared.retain(|_, s| s.lock().unwrap().remaining > 1);
+        *self.fetched.lock().unwrap() = shared;
+        self.manifests.lock().unwrap().clear();
+        *self.network.write().unwrap() = Network::new(&cfg.network)?;
+        let multi = indicatif::MultiProgress::new();
+        if !self.progress {
+            multi.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        }
+        let sources = cfg.sources();
+        let overall = multi.add(indicatif::ProgressBar::new(sources.len() as u64));
+        overall.set_style(
+            indicatif::ProgressStyle::default_bar()
+                .template("  [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} repositories")
+                .progress_chars("=>-"),
+        );
+
+        // Add all the bars upfront, so that they are displayed in the order of the configuration
+        let queue = sources
+            .iter()
+            .map(|(name, source)| {
+                let bar = multi.add(indicatif::ProgressBar::new(0));
+                bar.set_style(
+                    indicatif::ProgressStyle::default_bar()
+                        .template("{spinner:.green} [{elapsed_precise}] {prefix}"),
+                );
+                bar.set_prefix(&format!("{}: waiting", name));
+                (*name, source, bar)
+            })
+            .collect::<Vec<_>>();
+        let queue = std::sync::Mutex::new(queue.into_iter());
+        let results = std::sync::Mutex::new(BTreeMap::new());
+
+        std::thread::scope(|s| {
+            let workers = (0..self.jobs.clamp(1, sources.len().max(1)))
+                .map(|_| {
+                    s.spawn(|| loop {
+                        let next = queue.lock().unwrap().next();
+                        let (name, source, bar) = match next {
+                            Some(job) => job,
+                            None => break,
+                        };
+                        let repo_start = std::time::Instant::now();
+                        let mut timings = RepoTimings::default();
+                        let res = source
+                            .fetch(&mut source::Context {
+                                fetcher: self,
+                                repo: name,
+                                bar: &bar,
+                                timings: &mut timings,
+                            })
+                            .map(|mut crates| {
+                                if self.skip_optional_deps {
+                                    for c in crates.iter_mut() {
+                                        c.deps.retain(Dependency::enabled_by_default);
+                                    }
+                                }
+                                crates
+                            })
+                            .map_err(|e| {
+                                e.context(format!(
+                                    "Failed to retrieve informations for repository {}",
+                                    name
+                                ))
+                            });
+                        let status = if res.is_ok() { "done" } else { "failed" };
+                        bar.set_prefix(&format!("{}: {}", name, status));
+                        bar.finish();
+                        overall.inc(1);
+                        timings.total = repo_start.elapsed();
+                        // A repository of both configurations of a diff took the time of both
+                        let mut stats = self.stats.lock().unwrap();
+                        let repo = stats.repos.entry(name.to_string()).or_default();
+                        repo.download += timings.download;
+                        repo.decompress += timings.decompress;
+                        repo.parse += timings.parse;
+                        repo.total += timings.total;
+                        drop(stats);
+                        results.lock().unwrap().insert(name.to_string(), res);
+                    })
+                })
+                .collect::<Vec<_>>();
+            s.spawn(|| multi.join().ok());
+            for w in workers {
+                w.join().expect("Fetching a repository panicked");
+            }
+            overall.finish();
+        });
+        let results = results.into_inner().unwrap();
+        let mut stats = self.stats.lock().unwrap();
+        stats.total += start.elapsed();
+
+        if self.keep_going {
+            let mut infos = BTreeMap::new();
+            for (name, res) in results {
+                match res {
+                    Ok(crates) => {
+                        infos.insert(name, crates);
+                    }
+                    Err(e) => {
+                        tracing::error!(repo = %name, "Skipping repository: {:?}", e);
+                        stats.failed.insert(name, format!("{:#}", e));
+                    }
+                }
+            }
+            return Ok(infos);
+        }
+        results
+            .into_iter()
+            .map(|(name, res)| Ok((name, res?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Crates of a single repository, with the dependencies `deps` between them
+    fn single_repo(deps: &[(&str, &str, DepKind)]) -> BTreeMap<String, Vec<CrateInfo>> {
+        let mut names = deps
+            .iter()
+            .flat_map(|(from, to, _)| [*from, *to])
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        let mut builder = GraphBuilder::new();
+        for name in names {
+            builder = builder.add_crate("repo", name);
+        }
+        for (from, to, kind) in deps {
+            builder = builder.add_dependency(from, to, *kind);
+        }
+        builder.build().unwrap()
+    }
+
+    fn krate<'a>(infos: &'a mut BTreeMap<String, Vec<CrateInfo>>, name: &str) -> &'a mut CrateInfo {
+        infos
+            .values_mut()
+            .flatten()
+            .find(|c| c.name == name)
+            .unwrap()
+    }
+
+    fn set_req(infos: &mut BTreeMap<String, Vec<CrateInfo>>, from: &str, to: &str, req: &str) {
+        let d = krate(infos, from)
+            .deps
+            .iter_mut()
+            .find(|d| d.name == to)
+            .unwrap();
+        d.req = req.to_string();
+    }
+
+    fn pair(repo: &str, krate: &str) -> (String, String) {
+        (repo.to_string(), krate.to_string())
+    }
+
+    #[test]
+    fn cycles_across_repositories() {
+        let infos = GraphBuilder::example().build().unwrap();
+        assert_eq!(
+            find_cycles(&infos),
+            vec![
+                vec![
+                    pair("app", "app-server"),
+                    pair("core", "core-net"),
+                    pair("app", "app-proto"),
+                ],
+                vec![
+                    pair("core", "core-net"),
+                    pair("app", "app-proto"),
+                    pair("core", "core-base"),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn no_cycles_within_a_repository() {
+        let infos = GraphBuilder::new()
+            .add_crate("core", "a")
+            .add_crate("core", "b")
+            .add_dependency("a", "b", DepKind::Normal)
+            .add_dependency("b", "a", DepKind::Normal)
+            .build()
+            .unwrap();
+        assert!(find_cycles(&infos).is_empty());
+    }
+
+    #[test]
+    fn strongly_connected_components_of_a_cycle() {
+        let infos = single_repo(&[
+            ("a", "b", DepKind::Normal),
+            ("b", "c", DepKind::Normal),
+            ("c", "a", DepKind::Dev),
+            ("c", "d", DepKind::Normal),
+        ]);
+        let graph = graph::DependencyGraph::new(&infos);
+        let sccs = |keep: &dyn Fn(&Dependency) -> bool| {
+            let mut sccs = strongly_connected_components(&graph, keep)
+                .into_iter()
+                .map(|mut scc| {
+                    scc.sort_unstable();
+                    scc
+                })
+                .collect::<Vec<_>>();
+            sccs.sort_unstable();
+            sccs
+        };
+        assert_eq!(sccs(&|_| true), vec![vec!["a", "b", "c"], vec!["d"]]);
+        // Without the dev-dependency closing it, there is no cycle
+        assert_eq!(
+            sccs(&|d| d.kind != DepKind::Dev),
+            vec![vec!["a"], vec!["b"], vec!["c"], vec!["d"]]
+        );
+    }
+
+    #[test]
+    fn dominators_of_a_diamond() {
+        let infos = single_repo(&[
+            ("a", "b", DepKind::Normal),
+            ("a", "c", DepKind::Normal),
+            ("b", "d", DepKind::Normal),
+            ("c", "d", DepKind::Build),
+            ("d", "e", DepKind::Normal),
+            ("unreachable", "a", DepKind::Normal),
+        ]);
+        let graph = graph::DependencyGraph::new(&infos);
+        let all = [DepKind::Normal, DepKind::Dev, DepKind::Build];
+        assert_eq!(
+            dominators(&graph, &["a"], &all),
+            BTreeMap::from([
+                ("a", None),
+                ("b", Some("a")),
+                ("c", Some("a")),
+                ("d", Some("a")),
+                ("e", Some("d")),
+            ])
+        );
+        // Crates reachable from several roots are only dominated by what all the paths share
+        assert_eq!(dominators(&graph, &["b", "c"], &all)["d"], None);
+        // Only through the normal dependencies, `d` is reached through `b` alone
+        assert_eq!(
+            dominators(&graph, &["a"], &[DepKind::Normal])["e"],
+            Some("d")
+        );
+        assert_eq!(
+            dominators(&graph, &["a"], &[DepKind::Normal])["d"],
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn critical_path_and_slack() {
+        let infos = GraphBuilder::example().build().unwrap();
+        let (path, slack) = critical_path(&infos);
+        assert_eq!(
+            path,
+            vec!["core-base", "app-proto", "core-net", "app-server"]
+        );
+        assert_eq!(slack["core-net"], 0);
+        assert_eq!(slack["toolkit"], 3);
+    }
+
+    #[test]
+    fn longest_chains_do_not_overlap() {
+        let infos = GraphBuilder::example().build().unwrap();
+        assert_eq!(
+            longest_chains(&infos, 5),
+            vec![
+                vec![
+                    ("core", "core-base"),
+                    ("app", "app-proto"),
+                    ("core", "core-net"),
+                    ("app", "app-server"),
+                ],
+                vec![("tools", "toolkit")],
+            ]
+        );
+        assert_eq!(longest_chains(&infos, 1).len(), 1);
+    }
+
+    #[test]
+    fn split_into_communities() {
+        let infos = GraphBuilder::new()
+            .add_crate("mono", "a1")
+            .add_crate("mono", "a2")
+            .add_crate("mono", "a3")
+            .add_crate("mono", "b1")
+            .add_crate("mono", "b2")
+            .add_crate("mono", "c")
+            .add_dependency("a1", "a2", DepKind::Normal)
+            .add_dependency("a2", "a3", DepKind::Normal)
+            .add_dependency("a3", "a1", DepKind::Dev)
+            .add_dependency("b1", "b2", DepKind::Normal)
+            .build()
+            .unwrap();
+        assert_eq!(
+            split("mono", &infos).unwrap(),
+            vec![vec!["a1", "a2", "a3"], vec!["b1", "b2"], vec!["c"]]
+        );
+        assert!(split("nope", &infos).is_err());
+    }
+
+    #[test]
+    fn bump_plan_follows_publishable_dependents() {
+        let mut infos = GraphBuilder::example()
+            .add_dependency("toolkit", "core-net", DepKind::Dev)
+            .build()
+            .unwrap();
+        krate(&mut infos, "app-server").published_to = Publish::Nowhere;
+        let plan = bump_plan(&["core-base"], &infos).unwrap();
+        let to_publish = plan
+            .to_publish
+            .iter()
+            .map(|(_, c, reason)| (&c.name as &str, reason as &str))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            to_publish,
+            vec![
+                ("core-base", "changed"),
+                ("app-proto", "depends on core-base"),
+                ("core-net", "depends on core-base"),
+            ]
+        );
+        // Requirements are updated even in the crates that are not published, or only on
+        // dev-dependencies
+        let mut to_update = plan
+            .to_update
+            .iter()
+            .map(|(_, c, d)| (&c.name as &str, &d.name as &str))
+            .collect::<Vec<_>>();
+        to_update.sort_unstable();
+        assert_eq!(
+            to_update,
+            vec![
+                ("app-proto", "core-base"),
+                ("app-server", "core-net"),
+                ("core-net", "app-proto"),
+                ("core-net", "core-base"),
+                ("toolkit", "core-net"),
+            ]
+        );
+        assert_eq!(plan.versions["core-base"], "0.2.0");
+        let (_, _, dep) = plan
+            .to_update
+            .iter()
+            .find(|(_, c, _)| c.name == "app-proto")
+            .unwrap();
+        assert_eq!(plan.updated_requirement(dep).as_deref(), Some("0.2"));
+        assert!(bump_plan(&["nope"], &infos).is_err());
+    }
+
+    #[test]
+    fn condensed_cycles_of_the_drawn_dependencies() {
+        let infos = GraphBuilder::example()
+            .add_dependency("core-base", "core-net", DepKind::Dev)
+            .build()
+            .unwrap();
+        let ownership = Ownership::default();
+        let label = |only_kind, name| {
+            let options = RenderOptions {
+                condense: true,
+                only_kind,
+                ..RenderOptions::default()
+            };
+            let graph = render::Graph::new(&infos, &ownership, &options);
+            graph.node(name).unwrap().label.clone()
+        };
+        assert_eq!(label(None, "core-base"), label(None, "core-net"));
+        assert!(label(None, "core-base").contains("\\n"));
+        // Without the dev-dependency closing it, the cycle is not drawn
+        assert_eq!(label(Some(DepKind::Normal), "core-base"), "core-base");
+        assert_eq!(label(Some(DepKind::Normal), "core-net"), "core-net");
+    }
+
+    #[test]
+    fn requirements_diff_of_each_declaration_form() {
+        let mut info = GraphBuilder::example().build().unwrap()["core"][0].clone();
+        let dep = |name: &str, key: Option<&str>, req: &str, kind| Dependency {
+            name: name.to_string(),
+            key: key.map(String::from),
+            has_path: false,
+            from: None,
+            git: None,
+            req: req.to_string(),
+            kind,
+            optional: false,
+            enabled_by: Vec::new(),
+        };
+        info.manifest = Some(PathBuf::from("crates/core/Cargo.toml"));
+        let manifest = String::from(
+            "[package]\nname = \"core\"\nversion = \"0.1.0\"\n\n\
+             [workspace.dependencies]\nbase = \"0.1\"\n\n\
+             [dependencies]\nbase = \"0.1\"\nnet2 = { package = \"net\", version = \"0.3\", optional = true }\n\n\
+             [target.'cfg(unix)'.dev-dependencies.proto]\nversion = \"=1.0.0\"",
+        );
+        let base = dep("base", None, "0.1", DepKind::Normal);
+        let net = dep("net", Some("net2"), "0.3", DepKind::Normal);
+        let proto = dep("proto", None, "=1.0.0", DepKind::Dev);
+        let updates = [(&base, "0.2"), (&net, "0.4"), (&proto, "=1.1.0")];
+        assert_eq!(
+            requirements_diff(&info, &manifest, &updates).unwrap(),
+            "diff --git a/crates/core/Cargo.toml b/crates/core/Cargo.toml\n\
+             --- a/crates/core/Cargo.toml\n\
+             +++ b/crates/core/Cargo.toml\n\
+             @@ -6,8 +6,8 @@\n base = \"0.1\"\n \n [dependencies]\n\
+             -base = \"0.1\"\n\
+             -net2 = { package = \"net\", version = \"0.3\", optional = true }\n\
+             +base = \"0.2\"\n\
+             +net2 = { package = \"net\", version = \"0.4\", optional = true }\n \n \
+             [target.'cfg(unix)'.dev-dependencies.proto]\n\
+             -version = \"=1.0.0\"\n\\ No newline at end of file\n\
+             +version = \"=1.1.0\"\n\\ No newline at end of file\n"
+        );
+        let other = dep("other", None, "1", DepKind::Normal);
+        assert!(requirements_diff(&info, &manifest, &[(&other, "2")]).is_err());
+    }
+
+    #[test]
+    fn unify_requirements_on_the_most_common_class() {
+        let mut infos = GraphBuilder::example()
+            .add_dependency("toolkit", "core-base", DepKind::Normal)
+            .build()
+            .unwrap();
+        krate(&mut infos, "core-base").version = String::from("1.2.0");
+        set_req(&mut infos, "app-proto", "core-base", "1.0");
+        set_req(&mut infos, "toolkit", "core-base", "^1.1");
+        set_req(&mut infos, "core-net", "core-base", "~0.9.3");
+        let unifications = unify_requirements(&infos);
+        assert_eq!(unifications.len(), 1);
+        let u = &unifications[0];
+        assert_eq!(u.krate.name, "core-base");
+        assert_eq!(u.target, "1");
+        assert_eq!(
+            u.classes,
+            BTreeMap::from([(String::from("0.9"), 1), (String::from("1"), 2)])
+        );
+        let changes = u
+            .changes
+            .iter()
+            .map(|(_, c, _, req)| (&c.name as &str, req as &str))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![("core-net", "~1.2.0")]);
+    }
+
+    #[test]
+    fn updated_requirement_keeps_operator_and_precision() {
+        assert_eq!(updated_requirement("^1.4", "2.1.3"), "^2.1");
+        assert_eq!(updated_requirement("~0.3.1", "0.4.0"), "~0.4.0");
+        assert_eq!(updated_requirement("=1.0.0", "1.1.0"), "=1.1.0");
+        assert_eq!(updated_requirement("1", "3.2.1"), "3");
+        assert_eq!(updated_requirement(">=1, <2", "2.0.0"), "2.0.0");
+    }
+
+    #[test]
+    fn features_enabling_an_optional_dependency() {
+        let features = BTreeMap::from([
+            (String::from("default"), vec![String::from("postgres")]),
+            (String::from("postgres"), vec![String::from("dep:sqlx")]),
+            (
+                String::from("full"),
+                vec![String::from("postgres"), String::from("tls")],
+            ),
+            (String::from("tls"), vec![String::from("sqlx?/tls")]),
+            (String::from("std"), vec![String::from("serde/std")]),
+        ]);
+        assert_eq!(
+            enabling_features(&features, "sqlx"),
+            vec!["default", "full", "postgres"]
+        );
+        // Without `dep:`, the dependency has an implicit feature
+        assert_eq!(enabling_features(&features, "serde"), vec!["serde", "std"]);
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("core-net", "core-net"));
+        assert!(glob_match("core-*", "core-net"));
+        assert!(glob_match("*-net", "core-net"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("core-*", "app-core"));
+        assert!(!glob_match("core", "core-net"));
+    }
+
+    #[test]
+    fn crates_shared_by_repositories_listed_twice() {
+        let mut infos = GraphBuilder::example().build().unwrap();
+        infos.insert(String::from("core-alias"), infos["core"].clone());
+        check_unique_names(&infos).unwrap();
+        assert_eq!(CrateIndex::new(&infos).get("core-net").unwrap().0, "core");
+        // Crates that only share their name are still defined twice
+        infos.get_mut("core-alias").unwrap()[0].version = String::from("2.0.0");
+        assert!(check_unique_names(&infos).is_err());
+    }
+
+    #[test]
+    fn sizes_with_units() {
+        assert_eq!(parse_size("10"), Some(10));
+        assert_eq!(parse_size("500MB"), Some(500_000_000));
+        assert_eq!(parse_size(" 1 kb "), Some(1_000));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1.5GB"), None);
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(parse_size("99999999TB"), None);
+    }
+
+    #[test]
+    fn network_limits_of_a_repository_override_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "[tarballs]\nmono = \"https://example.com/mono.tar.gz\"\nsmall = \"https://example.com/small.tar.gz\"\n\
+             [network]\nconnect-timeout = \"10s\"\ntimeout = \"1m\"\n\
+             [network.repos.mono]\ntimeout = \"15m\"\nmax-download-size = \"4GB\"\n",
+        )
+        .unwrap();
+        let cfg = load_config(&path).unwrap();
+        let mono = cfg.network.limits("mono");
+        assert_eq!(
+            mono.timeouts().unwrap(),
+            (
+                Some(Duration::from_secs(10)),
+                Some(Duration::from_secs(900))
+            )
+        );
+        assert_eq!(mono.max_download_size().unwrap(), Some(4_000_000_000));
+        let small = cfg.network.limits("small");
+        assert_eq!(small.timeouts().unwrap().1, Some(Duration::from_secs(60)));
+        assert_eq!(small.max_download_size().unwrap(), None);
+
+        std::fs::write(
+            &path,
+            "[tarballs]\nmono = \"https://example.com/mono.tar.gz\"\n[network.repos.other]\ntimeout = \"1m\"\n",
+        )
+        .unwrap();
+        match load_config(&path) {
+            Err(Error::Config(e)) => assert!(e.to_string().contains("other of [network.repos]")),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("The configuration was accepted"),
+        }
+    }
+
+    #[test]
+    fn targets_discovered_from_the_layout() {
+        let discover = |files: &[&str]| {
+            let mut info = GraphBuilder::example().build().unwrap()["core"][0].clone();
+            info.targets.clear();
+            info.discover_targets(files.iter().map(Path::new));
+            info.targets
+        };
+        assert_eq!(
+            discover(&["src/lib.rs", "README.md"]),
+            vec![TargetKind::Lib]
+        );
+        assert_eq!(discover(&["src/bin/tool.rs"]), vec![TargetKind::Bin]);
+        assert_eq!(
+            discover(&["src/lib.rs", "src/bin/tool/main.rs"]),
+            vec![TargetKind::Lib, TargetKind::Bin]
+        );
+        assert!(discover(&["src/bin/tool/util.rs", "examples/main.rs"]).is_empty());
+    }
+
+    #[test]
+    fn pinned_archive_urls() {
+        assert_eq!(
+            pin_archive_url("https://api.github.com/repos/o/r/tarball/main", "v1").as_deref(),
+            Some("https://api.github.com/repos/o/r/tarball/v1")
+        );
+        assert_eq!(
+            pin_archive_url("https://codeload.github.com/o/r/tar.gz/main", "v1").as_deref(),
+            Some("https://codeload.github.com/o/r/tar.gz/v1")
+        );
+        assert_eq!(
+            pin_archive_url("https://github.com/o/r/archive/main.tar.gz", "v1").as_deref(),
+            Some("https://github.com/o/r/archive/v1.tar.gz")
+        );
+        assert_eq!(
+            pin_archive_url(
+                "https://gitlab.com/g/p/-/archive/main/p-main.tar.gz?private_token=x",
+                "release/1"
+            )
+            .as_deref(),
+            Some("https://gitlab.com/g/p/-/archive/release/1/p-release-1.tar.gz?private_token=x")
+        );
+        assert_eq!(pin_archive_url("https://example.com/x.tar.gz", "v1"), None);
+    }
+}
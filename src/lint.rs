@@ -0,0 +1,1116 @@
+//! Lints run by `muregraph lint`, enabled and configured by the `[lints]` section of the
+//! configuration
+
+use std::collections::BTreeMap;
+
+use crate::{
+    all_crates, compat_class, find_cycles, glob_match, internal_edges, requirement_issue,
+    styled_crate, CrateIndex, CrateInfo, DenyRule, Exception, Finding, InvalidManifest, Layer,
+    LintConfig, LintReport, LintResult, Ownership, PolicyConfig, Publish, RequirementFinding,
+    RequirementIssue, Severity,
+};
+
+/// Check of the crates, reporting the issues it finds
+pub trait Lint {
+    /// Identifier of the lint, eg. "cycles"
+    fn id(&self) -> &'static str;
+
+    /// Severity of the findings
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// What the findings are, to introduce them, eg. "Crates with a missing license"
+    fn title(&self) -> String;
+
+    /// Check the crates, returning a line describing each issue found
+    fn check(&self, cx: &Context) -> Vec<String>;
+
+    /// Check the crates, returning each issue found along with its fields, for the lints whose
+    /// findings have some
+    fn findings(&self, cx: &Context) -> Vec<Finding> {
+        self.check(cx).into_iter().map(Finding::from).collect()
+    }
+}
+
+/// Log `finding` of `lint` at `level`, with the fields of the requirement it is about, if any
+macro_rules! log_finding {
+    ($level:ident, $lint:expr, $finding:expr) => {{
+        let r = $finding.requirement.as_ref();
+        tracing::$level!(
+            lint = $lint.id(),
+            krate = r.map(|r| &r.krate as &str),
+            dependency = r.map(|r| &r.dependency as &str),
+            requirement = r.map(|r| &r.requirement as &str),
+            version = r.map(|r| &r.version as &str),
+            issue = r.map(|r| r.issue),
+            "{}: {}",
+            $lint.title(),
+            $finding.text
+        )
+    }};
+}
+
+/// What a [`Lint`] checks
+pub struct Context<'a> {
+    /// Crates of each repository
+    pub infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+    /// Manifests skipped with `--lenient`
+    pub invalid_manifests: &'a [InvalidManifest],
+    /// Groups and owners of the repositories, for the findings to mention them
+    pub ownership: &'a Ownership,
+    /// Known issues accepted until they expire, from `policy.exceptions`
+    pub exceptions: &'a [Exception],
+    /// Day the exceptions are checked against
+    pub today: chrono::NaiveDate,
+}
+
+impl Context<'_> {
+    /// Mention of the owners of `repos`, if they have any, eg. " (owned by payments and search)"
+    fn owned_by<'r>(&self, repos: impl IntoIterator<Item = &'r str>) -> String {
+        let mut owners = repos
+            .into_iter()
+            .filter_map(|r| self.ownership.owners.get(r))
+            .map(|o| o as &str)
+            .collect::<Vec<_>>();
+        owners.sort_unstable();
+        owners.dedup();
+        match owners.as_slice() {
+            [] => String::new(),
+            [owner] => format!(" (owned by {})", owner),
+            [owners @ .., last] => format!(" (owned by {} and {})", owners.join(", "), last),
+        }
+    }
+
+    /// `finding`, unless an exception that did not expire accepts it, according to `accepts`,
+    /// mentioning an expired exception accepting it otherwise
+    fn unless_accepted(
+        &self,
+        finding: String,
+        accepts: impl Fn(&Exception) -> bool,
+    ) -> Option<String> {
+        let mut accepting = self.exceptions.iter().filter(|e| accepts(e));
+        if accepting.clone().any(|e| !e.expired(self.today)) {
+            return None;
+        }
+        match accepting.next() {
+            Some(e) => Some(format!(
+                "{} (exception expired on {})",
+                finding,
+                e.expires.as_deref().unwrap_or_default()
+            )),
+            None => Some(finding),
+        }
+    }
+}
+
+/// Lints enabled by `cfg`, by the rules of `policy` and by the deprecated crates of `ownership`,
+/// with their options, in the order they run
+pub fn registry(
+    cfg: &LintConfig,
+    policy: &PolicyConfig,
+    ownership: &Ownership,
+) -> Vec<Box<dyn Lint>> {
+    let mut lints: Vec<Box<dyn Lint>> = vec![
+        Box::new(Cycles),
+        Box::new(RepoCycles),
+        Box::new(SelfDependencies),
+        Box::new(MixedEditions),
+    ];
+    if let Some(target) = &cfg.edition {
+        lints.push(Box::new(Editions {
+            target: target.clone(),
+        }));
+    }
+    if let Some(allowed) = &cfg.licenses {
+        lints.push(Box::new(Licenses {
+            allowed: allowed.clone(),
+        }));
+    }
+    lints.push(Box::new(GitDependencies));
+    if cfg.divergent_versions {
+        lints.push(Box::new(DivergentVersions));
+    }
+    if cfg.semver_requirements {
+        lints.push(Box::new(SemverRequirements));
+    }
+    if let Some(allowed) = &cfg.allowed_registries {
+        lints.push(Box::new(AllowedRegistries {
+            allowed: allowed.clone(),
+        }));
+    }
+    if !cfg.required_metadata.is_empty() {
+        lints.push(Box::new(RequiredMetadata {
+            fields: cfg.required_metadata.clone(),
+        }));
+    }
+    if cfg.invalid_manifests {
+        lints.push(Box::new(InvalidManifests));
+    }
+    if !policy.deny.is_empty() {
+        lints.push(Box::new(Policy {
+            deny: policy.deny.clone(),
+        }));
+    }
+    if !policy.layers.is_empty() {
+        lints.push(Box::new(Layers {
+            layers: policy.layers.clone(),
+        }));
+    }
+    if !ownership.deprecated.is_empty() {
+        lints.push(Box::new(DeprecatedDependencies));
+    }
+    if !policy.exceptions.is_empty() {
+        lints.push(Box::new(Exceptions));
+    }
+    lints
+}
+
+/// Run `lints`, logging each of their findings at the level of their severity, and return their
+/// results
+pub fn run(lints: &[Box<dyn Lint>], cx: &Context) -> Vec<LintResult> {
+    lints
+        .iter()
+        .map(|lint| {
+            let findings = lint.findings(cx);
+            for f in findings.iter() {
+                match lint.severity() {
+                    Severity::Error => log_finding!(error, lint, f),
+                    Severity::Warning => log_finding!(warn, lint, f),
+                }
+            }
+            LintResult {
+                id: lint.id(),
+                severity: lint.severity(),
+                findings: findings.len(),
+                issues: findings
+                    .iter()
+                    .map(|f| console::strip_ansi_codes(&f.text).into_owned())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Run `lints`, returning their findings stripped of their terminal styling
+pub fn report(lints: &[Box<dyn Lint>], cx: &Context) -> Vec<LintReport> {
+    lints
+        .iter()
+        .map(|lint| LintReport {
+            id: lint.id(),
+            severity: lint.severity(),
+            title: lint.title(),
+            findings: lint
+                .findings(cx)
+                .into_iter()
+                .map(|f| Finding {
+                    text: console::strip_ansi_codes(&f.text).into_owned(),
+                    ..f
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Circular dependencies between crates of different repositories
+pub struct Cycles;
+
+impl Lint for Cycles {
+    fn id(&self) -> &'static str {
+        "cycles"
+    }
+
+    fn title(&self) -> String {
+        String::from("Cyclic dependencies across repositories")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        find_cycles(cx.infos)
+            .into_iter()
+            .filter_map(|c| {
+                let cycle = c
+                    .iter()
+                    .map(|(repo, krate)| styled_crate(repo, krate))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let crates = c.iter().map(|(_, krate)| krate as &str).collect::<Vec<_>>();
+                cx.unless_accepted(
+                    cycle + &cx.owned_by(c.iter().map(|(repo, _)| repo as &str)),
+                    |e| e.accepts_cycle(&crates),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Cycles between repositories, considering a repository depends on another one as soon as any
+/// of its crates depends on any crate of the other one
+pub struct RepoCycles;
+
+impl Lint for RepoCycles {
+    fn id(&self) -> &'static str {
+        "repo-cycles"
+    }
+
+    fn title(&self) -> String {
+        String::from("Repositories that depend on each other")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let mut repo_deps = petgraph::graphmap::DiGraphMap::<&str, ()>::new();
+        for repo in cx.infos.keys() {
+            repo_deps.add_node(repo);
+        }
+        for (repo, _, dep_repo, _) in internal_edges(cx.infos) {
+            if repo != dep_repo {
+                repo_deps.add_edge(repo, dep_repo, ());
+            }
+        }
+        petgraph::algo::tarjan_scc(&repo_deps)
+            .into_iter()
+            .filter(|scc| scc.len() > 1)
+            .filter_map(|mut scc| {
+                scc.sort_unstable();
+                let repos = scc
+                    .iter()
+                    .map(|r| console::style(r).for_stderr().bold().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                cx.unless_accepted(repos + &cx.owned_by(scc.iter().copied()), |e| {
+                    e.accepts_cycle(&scc)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Crates depending on themselves or on a crate with their name
+pub struct SelfDependencies;
+
+impl Lint for SelfDependencies {
+    fn id(&self) -> &'static str {
+        "self-dependencies"
+    }
+
+    fn title(&self) -> String {
+        String::from("Crates depending on themselves or on a same-named crate")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .flat_map(|(repo, c)| {
+                c.deps
+                    .iter()
+                    .filter(move |d| d.name == c.name)
+                    .map(move |d| (repo, c, d))
+            })
+            .map(|(repo, c, d)| {
+                let source = if d.has_path {
+                    String::from("itself")
+                } else if let Some(git) = &d.git {
+                    format!("a same-named crate from {}", git)
+                } else if let Some(registry) = &d.from {
+                    format!("a same-named crate from registry {}", registry)
+                } else {
+                    String::from("a same-named crate from the default registry")
+                };
+                format!("{} depends on {}", styled_crate(repo, &c.name), source)
+            })
+            .collect()
+    }
+}
+
+/// Crates using each edition, when they do not all use the same one, to track migrations
+pub struct MixedEditions;
+
+impl Lint for MixedEditions {
+    fn id(&self) -> &'static str {
+        "mixed-editions"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn title(&self) -> String {
+        String::from("Crates using each of the editions in use")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let mut editions = BTreeMap::<&str, Vec<String>>::new();
+        for (repo, c) in all_crates(cx.infos) {
+            editions
+                .entry(&c.edition)
+                .or_default()
+                .push(styled_crate(repo, &c.name));
+        }
+        if editions.len() < 2 {
+            return Vec::new();
+        }
+        editions
+            .into_iter()
+            .map(|(edition, crates)| format!("Edition {}: {}", edition, crates.join(", ")))
+            .collect()
+    }
+}
+
+/// Crates not using the target edition, from `lints.edition`
+pub struct Editions {
+    /// Edition all crates are expected to be using
+    pub target: String,
+}
+
+impl Lint for Editions {
+    fn id(&self) -> &'static str {
+        "editions"
+    }
+
+    fn title(&self) -> String {
+        format!("Crates not using edition {}", self.target)
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .filter(|(_, c)| c.edition != self.target)
+            .map(|(repo, c)| format!("{} uses edition {}", styled_crate(repo, &c.name), c.edition))
+            .collect()
+    }
+}
+
+/// Crates with no license or one not in the allow-list, from `lints.licenses`
+pub struct Licenses {
+    /// Licenses crates are allowed to use
+    pub allowed: Vec<String>,
+}
+
+impl Lint for Licenses {
+    fn id(&self) -> &'static str {
+        "licenses"
+    }
+
+    fn title(&self) -> String {
+        String::from("Crates with a missing or disallowed license")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .filter_map(|(repo, c)| match &c.license {
+                Some(l) if self.allowed.contains(l) => None,
+                Some(l) => Some(format!(
+                    "{} uses license {}",
+                    styled_crate(repo, &c.name),
+                    l
+                )),
+                None => Some(format!("{} has no license", styled_crate(repo, &c.name))),
+            })
+            .collect()
+    }
+}
+
+/// Publishable crates depending on git repositories
+pub struct GitDependencies;
+
+impl Lint for GitDependencies {
+    fn id(&self) -> &'static str {
+        "git-dependencies"
+    }
+
+    fn title(&self) -> String {
+        String::from("Git dependencies in publishable crates")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .filter(|(_, c)| !matches!(c.published_to, Publish::Nowhere))
+            .flat_map(|(repo, c)| {
+                c.deps.iter().filter_map(move |d| {
+                    d.git.as_ref().map(|git| {
+                        format!(
+                            "{} depends on {} from {}",
+                            styled_crate(repo, &c.name),
+                            console::style(&d.name).for_stderr().bold(),
+                            git
+                        )
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// External crates that repositories depend on with incompatible versions, from
+/// `lints.divergent-versions`
+pub struct DivergentVersions;
+
+impl Lint for DivergentVersions {
+    fn id(&self) -> &'static str {
+        "divergent-versions"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn title(&self) -> String {
+        String::from("External crates used with incompatible versions across repositories")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let index = CrateIndex::new(cx.infos);
+        let mut versions = BTreeMap::<&str, BTreeMap<String, Vec<&str>>>::new();
+        for (repo, c) in all_crates(cx.infos) {
+            for d in c.deps.iter() {
+                if d.has_path || d.git.is_some() || index.get(&d.name).is_some() {
+                    continue;
+                }
+                if let Some(class) = compat_class(&d.req) {
+                    let repos = versions
+                        .entry(&d.name)
+                        .or_default()
+                        .entry(class)
+                        .or_default();
+                    if !repos.contains(&repo) {
+                        repos.push(repo);
+                    }
+                }
+            }
+        }
+        versions
+            .into_iter()
+            .filter(|(_, classes)| classes.len() > 1)
+            .map(|(krate, classes)| {
+                format!(
+                    "{}: {}",
+                    console::style(krate).for_stderr().bold(),
+                    classes
+                        .iter()
+                        .map(|(class, repos)| format!("{} ({})", class, repos.join(", ")))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+            .collect()
+    }
+}
+
+/// Requirements on crates of the repositories that their current version does not match, or
+/// that would match their next semver-incompatible version, from `lints.semver-requirements`
+pub struct SemverRequirements;
+
+impl Lint for SemverRequirements {
+    fn id(&self) -> &'static str {
+        "semver-requirements"
+    }
+
+    fn title(&self) -> String {
+        String::from("Requirements not semver-compatible with the current version of the crate")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        self.findings(cx).into_iter().map(|f| f.text).collect()
+    }
+
+    fn findings(&self, cx: &Context) -> Vec<Finding> {
+        let index = CrateIndex::new(cx.infos);
+        let mut findings = Vec::new();
+        for (repo, c) in all_crates(cx.infos) {
+            for d in c.deps.iter() {
+                let (dep_repo, dep) = match index.get(&d.name) {
+                    Some(found) if d.name != c.name && !(d.has_path && d.req == "*") => found,
+                    _ => continue,
+                };
+                let issue = match requirement_issue(&d.req, &dep.version) {
+                    Some(issue) => issue,
+                    None => continue,
+                };
+                let text = format!(
+                    "{} requires {} {:?} in [{}], {}",
+                    styled_crate(repo, &c.name),
+                    styled_crate(dep_repo, &dep.name),
+                    d.req,
+                    d.kind.section(),
+                    match &issue {
+                        RequirementIssue::Invalid(e) => format!("which is invalid: {}", e),
+                        RequirementIssue::Unmatched =>
+                            format!("which does not match its version {}", dep.version),
+                        RequirementIssue::TooLoose(next) =>
+                            format!("which would match its incompatible version {}", next),
+                    }
+                );
+                let text =
+                    match cx.unless_accepted(text, |e| e.accepts_dependency(&c.name, &dep.name)) {
+                        Some(text) => text,
+                        None => continue,
+                    };
+                findings.push(Finding {
+                    text,
+                    requirement: Some(RequirementFinding {
+                        krate: c.name.clone(),
+                        dependency: dep.name.clone(),
+                        requirement: d.req.clone(),
+                        version: dep.version.clone(),
+                        issue: issue.id(),
+                    }),
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Crates publishable to registries not in the allow-list, from `lints.allowed-registries`
+pub struct AllowedRegistries {
+    /// Registries crates may be published to, "crates-io" allowing the default registry
+    pub allowed: Vec<String>,
+}
+
+impl Lint for AllowedRegistries {
+    fn id(&self) -> &'static str {
+        "allowed-registries"
+    }
+
+    fn title(&self) -> String {
+        String::from("Crates publishable to disallowed registries")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .filter_map(|(repo, c)| {
+                let bad = match &c.published_to {
+                    Publish::Nowhere => Vec::new(),
+                    Publish::Default => vec!["crates-io"],
+                    Publish::At(registries) => registries.iter().map(|r| r as &str).collect(),
+                }
+                .into_iter()
+                .filter(|r| !self.allowed.iter().any(|a| a == r))
+                .collect::<Vec<_>>();
+                if bad.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "{} can be published to {}",
+                        styled_crate(repo, &c.name),
+                        bad.join(", ")
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Published crates missing package metadata fields, from `lints.required-metadata`
+pub struct RequiredMetadata {
+    /// Fields published crates must set, among [`METADATA_FIELDS`](crate::METADATA_FIELDS)
+    pub fields: Vec<String>,
+}
+
+impl Lint for RequiredMetadata {
+    fn id(&self) -> &'static str {
+        "required-metadata"
+    }
+
+    fn title(&self) -> String {
+        String::from("Published crates missing required metadata")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        all_crates(cx.infos)
+            .filter(|(_, c)| !matches!(c.published_to, Publish::Nowhere))
+            .filter_map(|(repo, c)| {
+                let missing = self
+                    .fields
+                    .iter()
+                    .filter(|f| !c.metadata_fields.contains(f))
+                    .map(|f| f as &str)
+                    .collect::<Vec<_>>();
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "{} is missing {}",
+                        styled_crate(repo, &c.name),
+                        missing.join(", ")
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Manifests skipped with `--lenient`, from `lints.invalid-manifests`
+pub struct InvalidManifests;
+
+impl Lint for InvalidManifests {
+    fn id(&self) -> &'static str {
+        "invalid-manifests"
+    }
+
+    fn title(&self) -> String {
+        String::from("Invalid manifests")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        cx.invalid_manifests
+            .iter()
+            .map(|m| {
+                format!(
+                    "{:?} in {}: {}",
+                    m.path,
+                    console::style(&m.repo).for_stderr().bold(),
+                    m.error
+                )
+            })
+            .collect()
+    }
+}
+
+/// Whether `selector`, of the `[policy]` section, selects crate `krate`, whose repository is `repo`
+/// unless it is external
+fn selects(cx: &Context, selector: &str, repo: Option<&str>, krate: &str) -> bool {
+    let (kind, pattern) = selector.split_once(':').unwrap_or(("crate", selector));
+    match (kind, repo) {
+        ("crate", _) => glob_match(pattern, krate),
+        ("repo", Some(repo)) => glob_match(pattern, repo),
+        ("group", Some(repo)) => cx
+            .ownership
+            .groups
+            .get(repo)
+            .is_some_and(|group| glob_match(pattern, group)),
+        _ => false,
+    }
+}
+
+/// Dependencies forbidden by the rules of the `[policy]` section
+pub struct Policy {
+    /// Forbidden dependencies
+    pub deny: Vec<DenyRule>,
+}
+
+impl Lint for Policy {
+    fn id(&self) -> &'static str {
+        "policy"
+    }
+
+    fn title(&self) -> String {
+        String::from("Dependencies forbidden by the policy")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let index = CrateIndex::new(cx.infos);
+        let mut findings = Vec::new();
+        for (repo, c) in all_crates(cx.infos) {
+            for rule in self
+                .deny
+                .iter()
+                .filter(|r| selects(cx, &r.from, Some(repo), &c.name))
+            {
+                let mut deps = c.deps.iter().map(|d| &d.name as &str).collect::<Vec<_>>();
+                deps.sort_unstable();
+                deps.dedup();
+                for dep in deps {
+                    let dep_repo = index.get(dep).map(|(dep_repo, _)| dep_repo);
+                    if !selects(cx, &rule.to, dep_repo, dep) {
+                        continue;
+                    }
+                    let styled_dep = match dep_repo {
+                        Some(dep_repo) => styled_crate(dep_repo, dep),
+                        None => console::style(dep).for_stderr().bold().to_string(),
+                    };
+                    let reason = match &rule.reason {
+                        Some(reason) => format!(": {}", reason),
+                        None => String::new(),
+                    };
+                    let finding = format!(
+                        "{} depends on {}, denied by {} -> {}{}{}",
+                        styled_crate(repo, &c.name),
+                        styled_dep,
+                        rule.from,
+                        rule.to,
+                        reason,
+                        cx.owned_by([repo])
+                    );
+                    findings.extend(
+                        cx.unless_accepted(finding, |e| e.accepts_dependency(&c.name, dep)),
+                    );
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Crates depending on crates of a higher layer of `policy.layers`, directly or through crates in
+/// no layer
+pub struct Layers {
+    /// Layers, from the top one to the bottom one
+    pub layers: Vec<Layer>,
+}
+
+impl Lint for Layers {
+    fn id(&self) -> &'static str {
+        "layers"
+    }
+
+    fn title(&self) -> String {
+        String::from("Dependencies on higher layers")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let index = CrateIndex::new(cx.infos);
+        let layer = |repo: &str, krate: &str| {
+            self.layers.iter().position(|l| {
+                l.crates
+                    .iter()
+                    .any(|selector| selects(cx, selector, Some(repo), krate))
+            })
+        };
+        let mut findings = Vec::new();
+        for (repo, c) in all_crates(cx.infos) {
+            let level = match layer(repo, &c.name) {
+                Some(level) => level,
+                None => continue,
+            };
+            // Breadth-first search of the shortest paths to the crates of other layers, through
+            // crates in no layer, which the crates of the layers below have to stop at
+            let mut parents = BTreeMap::<&str, (&str, &str)>::new();
+            let mut frontier = vec![(repo, c)];
+            while !frontier.is_empty() {
+                let mut next = Vec::new();
+                for (r, from) in frontier {
+                    for d in from.deps.iter() {
+                        let (dep_repo, dep) = match index.get(&d.name) {
+                            Some(dep) => dep,
+                            None => continue,
+                        };
+                        if dep.name == c.name || parents.contains_key(&dep.name as &str) {
+                            continue;
+                        }
+                        parents.insert(&dep.name, (r, &from.name));
+                        match layer(dep_repo, &dep.name) {
+                            None => next.push((dep_repo, dep)),
+                            Some(l) if l < level => {
+                                let mut path = vec![styled_crate(dep_repo, &dep.name)];
+                                let mut krate = &dep.name as &str;
+                                while let Some(&(r, parent)) = parents.get(krate) {
+                                    path.push(styled_crate(r, parent));
+                                    krate = parent;
+                                }
+                                path.reverse();
+                                let finding = format!(
+                                    "{} ({}) depends on {} ({}): {}{}",
+                                    styled_crate(repo, &c.name),
+                                    self.layers[level].name,
+                                    styled_crate(dep_repo, &dep.name),
+                                    self.layers[l].name,
+                                    path.join(" -> "),
+                                    cx.owned_by([repo])
+                                );
+                                findings.extend(cx.unless_accepted(finding, |e| {
+                                    e.accepts_dependency(&c.name, &dep.name)
+                                }));
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+                frontier = next;
+            }
+        }
+        findings
+    }
+}
+
+/// Dependencies on the crates of the `[deprecated]` section, but from deprecated crates
+pub struct DeprecatedDependencies;
+
+impl Lint for DeprecatedDependencies {
+    fn id(&self) -> &'static str {
+        "deprecated"
+    }
+
+    fn title(&self) -> String {
+        String::from("Dependencies on deprecated crates")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        let deprecated = &cx.ownership.deprecated;
+        let index = CrateIndex::new(cx.infos);
+        let mut findings = Vec::new();
+        for (repo, c) in all_crates(cx.infos).filter(|(_, c)| !deprecated.contains_key(&c.name)) {
+            let mut deps = c
+                .deps
+                .iter()
+                .filter_map(|d| deprecated.get_key_value(&d.name))
+                .collect::<Vec<_>>();
+            deps.sort_unstable();
+            deps.dedup();
+            for (dep, replacement) in deps {
+                let styled_dep = match index.get(dep) {
+                    Some((dep_repo, _)) => styled_crate(dep_repo, dep),
+                    None => console::style(dep).for_stderr().bold().to_string(),
+                };
+                let replacement = match replacement.is_empty() {
+                    true => String::new(),
+                    false => format!(": {}", replacement),
+                };
+                let finding = format!(
+                    "{} depends on {}{}{}",
+                    styled_crate(repo, &c.name),
+                    styled_dep,
+                    replacement,
+                    cx.owned_by([repo])
+                );
+                findings
+                    .extend(cx.unless_accepted(finding, |e| e.accepts_dependency(&c.name, dep)));
+            }
+        }
+        findings
+    }
+}
+
+/// Issues accepted by `policy.exceptions` that did not expire yet, as a reminder
+pub struct Exceptions;
+
+impl Lint for Exceptions {
+    fn id(&self) -> &'static str {
+        "exceptions"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn title(&self) -> String {
+        String::from("Temporarily accepted issues")
+    }
+
+    fn check(&self, cx: &Context) -> Vec<String> {
+        cx.exceptions
+            .iter()
+            .filter(|e| !e.expired(cx.today))
+            .map(|e| {
+                let mut finding = e.accepted();
+                if let Some(owner) = &e.owner {
+                    finding += &format!(", to be fixed by {}", owner);
+                }
+                match &e.expires {
+                    Some(expires) => finding + &format!(" until {}", expires),
+                    None => finding + " with no expiry",
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DepKind, GraphBuilder};
+
+    /// Findings of `lint` on `infos`, without their styling, accepting the issues of `exceptions`
+    fn check(
+        lint: &dyn Lint,
+        infos: &BTreeMap<String, Vec<CrateInfo>>,
+        ownership: &Ownership,
+        exceptions: &[Exception],
+    ) -> Vec<String> {
+        let cx = Context {
+            infos,
+            invalid_manifests: &[],
+            ownership,
+            exceptions,
+            today: chrono::NaiveDate::from_ymd_opt(2026, 6, 1).unwrap(),
+        };
+        lint.check(&cx)
+            .iter()
+            .map(|f| console::strip_ansi_codes(f).into_owned())
+            .collect()
+    }
+
+    fn rule(from: &str, to: &str, reason: Option<&str>) -> DenyRule {
+        DenyRule {
+            from: from.to_string(),
+            to: to.to_string(),
+            reason: reason.map(String::from),
+        }
+    }
+
+    #[test]
+    fn mixed_editions_list_the_crates_of_each_edition() {
+        let mut infos = GraphBuilder::example().build().unwrap();
+        assert!(check(&MixedEditions, &infos, &Ownership::default(), &[]).is_empty());
+        for c in infos
+            .values_mut()
+            .flatten()
+            .filter(|c| c.name.starts_with("core"))
+        {
+            c.edition = String::from("2021");
+        }
+        assert_eq!(
+            check(&MixedEditions, &infos, &Ownership::default(), &[]),
+            vec![
+                "Edition 2018: app-proto[app], app-server[app], toolkit[tools]",
+                "Edition 2021: core-base[core], core-net[core]",
+            ]
+        );
+    }
+
+    #[test]
+    fn policy_denies_selected_dependencies() {
+        let infos = GraphBuilder::example()
+            .add_dependency("app-proto", "openssl", DepKind::Normal)
+            .build()
+            .unwrap();
+        let ownership = Ownership {
+            groups: BTreeMap::from([(String::from("app"), String::from("frontend"))]),
+            owners: BTreeMap::from([(String::from("core"), String::from("platform"))]),
+            ..Ownership::default()
+        };
+        let lint = Policy {
+            deny: vec![
+                rule(
+                    "repo:core",
+                    "group:frontend",
+                    Some("core is the bottom layer"),
+                ),
+                rule("group:frontend", "crate:openssl", None),
+            ],
+        };
+        assert_eq!(
+            check(&lint, &infos, &ownership, &[]),
+            vec![
+                "app-proto[app] depends on openssl, denied by group:frontend -> crate:openssl",
+                "core-net[core] depends on app-proto[app], denied by repo:core -> group:frontend: core is the bottom layer (owned by platform)",
+            ]
+        );
+        let exception = Exception {
+            from: Some(String::from("app-proto")),
+            to: Some(String::from("openssl")),
+            expires: Some(String::from("2026-12-31")),
+            ..Exception::default()
+        };
+        let expired = Exception {
+            expires: Some(String::from("2026-01-31")),
+            ..exception.clone()
+        };
+        assert_eq!(
+            check(&lint, &infos, &ownership, std::slice::from_ref(&expired))[0],
+            "app-proto[app] depends on openssl, denied by group:frontend -> crate:openssl (exception expired on 2026-01-31)"
+        );
+        // A renewed exception accepts the issue, even if the expired one is still listed first
+        assert_eq!(
+            check(&lint, &infos, &ownership, &[expired, exception]).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn layers_deny_dependencies_on_higher_layers() {
+        let infos = GraphBuilder::example()
+            .add_crate("misc", "glue")
+            .add_dependency("core-base", "glue", DepKind::Normal)
+            .add_dependency("glue", "app-server", DepKind::Normal)
+            .build()
+            .unwrap();
+        let lint = Layers {
+            layers: vec![
+                Layer {
+                    name: String::from("apps"),
+                    crates: vec![String::from("repo:app")],
+                },
+                Layer {
+                    name: String::from("core"),
+                    crates: vec![String::from("core-*")],
+                },
+            ],
+        };
+        assert_eq!(
+            check(&lint, &infos, &Ownership::default(), &[]),
+            vec![
+                "core-base[core] (core) depends on app-server[app] (apps): core-base[core] -> glue[misc] -> app-server[app]",
+                "core-net[core] (core) depends on app-proto[app] (apps): core-net[core] -> app-proto[app]",
+            ]
+        );
+    }
+
+    #[test]
+    fn semver_requirements_match_the_current_version() {
+        let mut infos = GraphBuilder::example()
+            .add_dependency("toolkit", "core-base", DepKind::Dev)
+            .add_dependency("app-server", "app-proto", DepKind::Normal)
+            .build()
+            .unwrap();
+        for c in infos.values_mut().flatten() {
+            if c.name == "core-base" {
+                c.version = String::from("1.2.0");
+            }
+            for d in c.deps.iter_mut() {
+                d.req = match (&c.name as &str, &d.name as &str) {
+                    ("app-proto", "core-base") => String::from("0.9"),
+                    ("core-net", "core-base") => String::from("1.0"),
+                    ("toolkit", "core-base") => String::from(">=1"),
+                    ("core-net", "app-proto") => String::from("not a requirement"),
+                    // Path dependencies without a version are not checked
+                    ("app-server", "app-proto") => String::from("*"),
+                    _ => continue,
+                };
+            }
+        }
+        let findings = check(&SemverRequirements, &infos, &Ownership::default(), &[]);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(
+            findings[0],
+            "app-proto[app] requires core-base[core] \"0.9\" in [dependencies], which does not match its version 1.2.0"
+        );
+        assert!(findings[1].starts_with(
+            "core-net[core] requires app-proto[app] \"not a requirement\" in [dependencies], which is invalid"
+        ));
+        assert_eq!(
+            findings[2],
+            "toolkit[tools] requires core-base[core] \">=1\" in [dev-dependencies], which would match its incompatible version 2.0.0"
+        );
+        let cx = Context {
+            infos: &infos,
+            invalid_manifests: &[],
+            ownership: &Ownership::default(),
+            exceptions: &[],
+            today: chrono::NaiveDate::from_ymd_opt(2026, 6, 1).unwrap(),
+        };
+        let requirement = |krate: &str, dependency: &str, requirement: &str, version, issue| {
+            Some(RequirementFinding {
+                krate: krate.to_string(),
+                dependency: dependency.to_string(),
+                requirement: requirement.to_string(),
+                version: String::from(version),
+                issue,
+            })
+        };
+        assert_eq!(
+            SemverRequirements
+                .findings(&cx)
+                .into_iter()
+                .map(|f| f.requirement)
+                .collect::<Vec<_>>(),
+            vec![
+                requirement("app-proto", "core-base", "0.9", "1.2.0", "unmatched"),
+                requirement(
+                    "core-net",
+                    "app-proto",
+                    "not a requirement",
+                    "0.1.0",
+                    "invalid"
+                ),
+                requirement("toolkit", "core-base", ">=1", "1.2.0", "too-loose"),
+            ]
+        );
+        // The same check tells the requirements to update
+        let outdated = crate::outdated_requirements(&infos)
+            .into_iter()
+            .map(|r| (&r.krate.name as &str, &r.dep.name as &str, r.updated))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outdated,
+            vec![("app-proto", "core-base", String::from("1.2"))]
+        );
+    }
+}
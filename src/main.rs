@@ -1,423 +1,2686 @@
-use std::{collections::BTreeMap, ffi::OsStr, io::Read, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{ensure, Context};
+use muregraph::{
+    adjacency, backstage_catalog, badge, build_waves, bump_plan, cache_key, changed_crates,
+    check_render_options, check_unique_names, coupling, crates_by_owner, crates_by_prefix,
+    critical_path, diff_sections, dominators, export, find_cycles, glob_match,
+    graph::DependencyGraph, impact, import, import_model, internal_edges, lint_report, load_config,
+    longest_chains, metrics, outdated_requirements, paths_between, publish_order, query,
+    release_configs, render, render_svg, render_to, requirements_diff, reverse_adjacency,
+    sanity_check, select, site_pages, split, styled_crate, top_dependents, unify_requirements,
+    BadgeMetric, BumpPlan, CacheMode, CatalogGranularity, Config, CrateInfo, DepKind, Dependency,
+    EdgeFilter, FetchOptions, FetchStats, Fetcher, Format, GraphType, GraphvizConfig, LintResult,
+    Ownership, ReleaseTool, RenderOptions, Severity, Snapshot, GRAPHVIZ_ENGINES,
+};
+use sha2::Digest;
 use structopt::StructOpt;
 
-const COLORS: &[&str] = &[
-    "#e6194b", "#3cb44b", "#ffe119", "#4363d8", "#f58231", "#911eb4", "#46f0f0", "#f032e6",
-    "#bcf60c", "#fabebe", "#008080", "#e6beff", "#9a6324", "#fffac8", "#800000", "#aaffc3",
-    "#808000", "#ffd8b1", "#000075", "#808080", "#ffffff", "#000000",
-];
-
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
-struct Config {
-    tarballs: BTreeMap<String, String>,
-}
+mod cli;
+mod tui;
 
 #[derive(Debug, structopt::StructOpt)]
 struct Opt {
+    /// Configuration file listing the repositories to analyze
+    #[structopt(
+        short,
+        long,
+        default_value = "config.toml",
+        global = true,
+        env = "MUREGRAPH_CONFIG"
+    )]
     config: PathBuf,
 
-    /// Use a colored graph instead of a clustered graph in the output
-    #[structopt(long)]
-    use_colors: bool,
+    /// Save statistics about the crates in a timestamped file in this directory, for use with
+    /// the `trends` subcommand
+    #[structopt(long, global = true, env = "MUREGRAPH_SAVE_SNAPSHOT")]
+    save_snapshot: Option<PathBuf>,
 
-    /// Return a non-zero value if some lints notice errors
-    #[structopt(long)]
-    lint: bool,
-}
+    /// Save the crates found to this file, like `muregraph export`, for later runs to read them
+    /// with `--from-model` instead of fetching the repositories again
+    #[structopt(long, global = true)]
+    save_model: Option<PathBuf>,
+
+    /// Read the crates from this file, written with `--save-model` or `muregraph export`,
+    /// instead of fetching the repositories
+    #[structopt(long, global = true, conflicts_with_all = &["save-model", "watch"])]
+    from_model: Option<PathBuf>,
+
+    /// Write a JSON summary of the run to this file, with the crates found in each repository,
+    /// the lint results and how long fetching took
+    #[structopt(long, global = true, env = "MUREGRAPH_SUMMARY")]
+    summary: Option<PathBuf>,
+
+    /// Summary of an earlier run, written with `--summary`, to only notify `notify.webhook` of
+    /// the lint findings and cycles that are new since then
+    #[structopt(long, global = true, env = "MUREGRAPH_BASELINE")]
+    baseline: Option<PathBuf>,
+
+    /// Only consider the repositories matching these comma-separated patterns, where `*`
+    /// matches any string [env: MUREGRAPH_ONLY]
+    #[structopt(long, global = true, require_delimiter = true)]
+    only: Vec<String>,
+
+    /// Ignore the repositories matching these comma-separated patterns, where `*` matches any
+    /// string [env: MUREGRAPH_EXCLUDE]
+    #[structopt(long, global = true, require_delimiter = true)]
+    exclude: Vec<String>,
+
+    /// Fetch the repositories again at this interval (eg. `30s` or `5m`), running the command
+    /// again whenever the configuration or the crates changed, until interrupted
+    #[structopt(long, global = true, env = "MUREGRAPH_WATCH")]
+    watch: Option<humantime::Duration>,
+
+    /// Keep the downloaded archives in this directory, only downloading them again when they
+    /// changed
+    #[structopt(long, global = true, env = "MUREGRAPH_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Use the cached archives without checking whether they changed, only downloading the
+    /// ones that are not cached yet
+    #[structopt(long, global = true, requires = "cache-dir")]
+    offline: bool,
+
+    /// Like `--offline`, but fail instead of downloading archives that are not cached yet
+    #[structopt(long, global = true, requires = "cache-dir")]
+    frozen: bool,
+
+    /// Keep the temporary directory the archives are downloaded to, eg. to inspect a manifest
+    /// that failed to parse
+    #[structopt(long, global = true)]
+    keep_temp: bool,
+
+    /// Report on standard error how long each phase took, for each repository
+    #[structopt(long, global = true)]
+    timings: bool,
+
+    /// Print what would be downloaded and which files would be written, without downloading or
+    /// writing anything
+    #[structopt(long, global = true)]
+    dry_run: bool,
+
+    /// Skip the repositories that cannot be downloaded or parsed instead of stopping, exiting
+    /// with a distinct status at the end
+    #[structopt(long, global = true)]
+    keep_going: bool,
 
-enum Publish {
-    Nowhere,
-    Default,
-    At(Vec<String>),
+    /// Skip the manifests that cannot be parsed, eg. templates with placeholders, logging a
+    /// warning instead of failing
+    #[structopt(long, global = true)]
+    lenient: bool,
+
+    /// Measure the Rust sources of each crate, reading all the `.rs` files of the repositories,
+    /// for the metrics and the sizes of the nodes of the graph
+    #[structopt(long, global = true)]
+    measure_size: bool,
+
+    /// Leave out the optional dependencies that the default features do not enable, as in a
+    /// build with the default features, for the graph and all the analyses
+    #[structopt(long, global = true)]
+    no_optional_deps: bool,
+
+    /// Number of repositories to download concurrently
+    #[structopt(
+        short,
+        long,
+        default_value = "4",
+        global = true,
+        env = "MUREGRAPH_JOBS"
+    )]
+    jobs: usize,
+
+    /// Log more details, can be repeated up to three times
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    verbose: u8,
+
+    /// Log less details and hide the progress bars, can be repeated to log nothing
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    quiet: u8,
+
+    /// Format of the logs, either `text` or `json`
+    #[structopt(
+        long,
+        default_value = "text",
+        global = true,
+        env = "MUREGRAPH_LOG_FORMAT"
+    )]
+    log_format: LogFormat,
+
+    #[structopt(subcommand)]
+    command: Command,
+
+    /// Workspace to analyze along with the configured repositories, as `(name, root)`, when run
+    /// as `cargo muregraph`
+    #[structopt(skip)]
+    workspace: Option<(String, PathBuf)>,
 }
 
-struct Dependency {
-    name: String,
-    has_path: bool,
-    from: Option<String>,
+#[derive(Debug)]
+enum LogFormat {
+    Text,
+    Json,
 }
 
-struct CrateInfo {
-    name: String,
-    published_to: Publish,
-    deps: Vec<Dependency>,
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<LogFormat> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => anyhow::bail!("Unknown log format {:?}, expected `text` or `json`", s),
+        }
+    }
 }
 
-fn handle_tarball(
-    client: &reqwest::blocking::Client,
-    dir: &tempfile::TempDir,
-    name: &str,
-    url: &str,
-) -> anyhow::Result<Vec<CrateInfo>> {
-    let url_display = if url.len() <= 40 {
-        format!("{}", url)
-    } else {
-        format!("…{}", &url[url.len() - 39..])
-    };
+#[derive(Debug, structopt::StructOpt)]
+enum Command {
+    /// Output the dependency graph in the DOT format
+    Graph {
+        /// Use a colored graph instead of a clustered graph
+        #[structopt(long)]
+        use_colors: bool,
 
-    // Prepare the progress bar
-    let bar = indicatif::ProgressBar::new(0);
-    bar.set_style(
-        indicatif::ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] {prefix}"),
-    );
+        /// Collapse each set of crates that all transitively depend on each other, through the
+        /// dependencies drawn, into a single node
+        #[structopt(long)]
+        condense: bool,
 
-    // Figure out the size of the download
-    // TODO: It looks like this significantly slows down the process. Also, trying to use HEAD
-    // instead of GET is even slower. Let's not have a pretty progress bar for now, it's probably
-    // not a big deal anyway.
-    /*
-    bar.set_prefix(&format!("figuring out the size of {}", url_display));
-    let r = client
-        .get(url)
-        .send()
-        .with_context(|| format!("Failed to send HEAD request to URL {:?}", url))?;
-    anyhow::ensure!(
-        r.status().is_success(),
-        "HEAD request to {:?} was unsuccessful",
-        url
-    );
-    if let Some(l) = r.content_length() {
-        bar.inc_length(l);
-        bar.set_style(
-            indicatif::ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes:<8}/{total_bytes:8} ({eta}) {prefix}")
-                .progress_chars("=>-"),
-        );
-    }
-    std::mem::drop(r);
-    */
+        /// Collapse the crates into a node per group of repositories, from the `[groups]`
+        /// section of the configuration
+        #[structopt(long, conflicts_with_all = &["use-colors", "condense"])]
+        collapse_groups: bool,
 
-    // Prepare the (compressed) archive file
-    let path = dir.path().join(name);
-    let dest = std::fs::File::create(&path)
-        .with_context(|| format!("Failed to create file {:?}", path))?;
+        /// Cluster, color or collapse the crates by owner, from the `CODEOWNERS` files of the
+        /// repositories, instead of by repository
+        #[structopt(long)]
+        by_owner: bool,
 
-    // Download to it
-    bar.set_prefix(&format!("downloading {}", url_display));
-    let mut download = client
-        .get(url)
-        .send()
-        .with_context(|| format!("Failed to send GET request to URL {:?}", url))?;
-    download
-        .copy_to(&mut bar.wrap_write(dest))
-        .with_context(|| format!("Failed to download {:?} to {:?}", url, path))?;
-
-    // Open the file, uncompressing if necessary
-    let kind = infer::get_from_path(&path)
-        .with_context(|| format!("Failed to read the file at {:?}", path))?;
-    let archive = std::fs::File::open(&path)
-        .with_context(|| format!("Failed to open the file at {:?} for reading", path))?;
-    let archive: Box<dyn Read> = match kind {
-        Some(t) if t.mime_type() == "application/gzip" => {
-            Box::new(flate2::read::GzDecoder::new(archive)) as _
-        }
-        _ => Box::new(archive) as _,
-    };
+        /// Cluster, color or collapse the crates starting with these comma-separated prefixes
+        /// by prefix instead of by repository, eg. `mure-storage-*,mure-net-*`
+        #[structopt(long, require_delimiter = true, conflicts_with = "by-owner")]
+        by_prefix: Vec<String>,
 
-    // Parse tarball
-    bar.set_prefix(&format!("parsing {}", url_display));
-    let mut archive = tar::Archive::new(archive);
+        /// Make the crates matching these comma-separated patterns stand out, where `*` matches
+        /// any string, eg. `*auth*`, still drawing all the others
+        #[structopt(long, require_delimiter = true)]
+        highlight: Vec<String>,
 
-    // Iterate through the files, looking for Cargo.toml's
-    let mut res = Vec::new();
-    for file in archive
-        .entries()
-        .context("Failed to enumerate the entries of downloaded tarball")?
-    {
-        let mut file = file
-            .context("Failed to retrieve information about an entry of the downloaded tarball")?;
-        let path = file
-            .path()
-            .context("Failed to retrieve the path for an entry of the downloaded tarball")?;
-        let path = PathBuf::from(path);
-        if path.file_name() == Some(OsStr::new("Cargo.toml")) {
-            // Parse the manifest
-            let mut manifest = Vec::new();
-            file.read_to_end(&mut manifest).with_context(|| {
-                format!("Failed to read file {:?} from downloaded tarball", path)
-            })?;
-            let manifest = cargo_toml::Manifest::from_slice(&manifest).with_context(|| {
-                format!(
-                    "Failed to parse file {:?} from downloaded tarball as a Cargo.toml file",
-                    path
-                )
-            })?;
+        /// Dependencies to draw: only the path-local ones, ie. the wiring inside the
+        /// repositories, only the ones from our own registries, ie. those that matter for
+        /// publishing, or both
+        #[structopt(long, default_value = "all", possible_values = &["path", "registry", "all"])]
+        edges: EdgeFilter,
 
-            // Verify whether it's a virtual manifest
-            let package = match manifest.package {
-                Some(p) => p,
-                None => continue, // Workspace Cargo.toml
-            };
+        /// Also draw the dependencies on crates outside of the repositories, eg. from crates.io,
+        /// each external crate being a single node showing how many crates depend on it
+        #[structopt(long)]
+        external_deps: bool,
 
-            // Create the dependency list
-            let mut deps = Vec::new();
-            for (depname, dep) in manifest
-                .dependencies
-                .iter()
-                .chain(manifest.dev_dependencies.iter())
-                .chain(manifest.build_dependencies.iter())
-                .chain(manifest.target.values().flat_map(|t| {
-                    t.dependencies
-                        .iter()
-                        .chain(t.dev_dependencies.iter())
-                        .chain(t.build_dependencies.iter())
-                }))
-            {
-                match dep {
-                    cargo_toml::Dependency::Simple(_) => deps.push(Dependency {
-                        name: depname.clone(),
-                        has_path: false,
-                        from: None,
-                    }),
-                    cargo_toml::Dependency::Detailed(d) => deps.push(Dependency {
-                        name: d.package.clone().unwrap_or_else(|| depname.clone()),
-                        has_path: d.path.is_some(),
-                        from: d.registry.clone(),
-                    }),
+        /// Only draw the dev-dependencies, ie. the test wiring between crates and repositories
+        #[structopt(long)]
+        only_dev_deps: bool,
+
+        /// Only draw the build-dependencies, ie. the crates of code generation and build scripts
+        #[structopt(long, conflicts_with = "only-dev-deps")]
+        only_build_deps: bool,
+
+        /// Only draw the N crates with the most dependencies and dependents, replacing the others
+        /// with a placeholder node per repository, to get an overview of huge graphs
+        #[structopt(long, value_name = "N", conflicts_with_all = &["condense", "collapse-groups"])]
+        max_nodes: Option<usize>,
+
+        /// Graphviz engine rendering the SVG output, overriding `graphviz.engine`, eg. `sfdp` for
+        /// huge graphs
+        #[structopt(long, possible_values = GRAPHVIZ_ENGINES)]
+        engine: Option<String>,
+
+        /// Extra argument passed to graphviz when rendering the SVG output, after those of
+        /// `graphviz.args`, eg. `-Goverlap=prism`, and may be repeated
+        #[structopt(long, number_of_values = 1, allow_hyphen_values = true)]
+        graphviz_arg: Vec<String>,
+
+        /// Write the graph to this file instead of the standard output, in the format matching
+        /// its extension (`.dot` or `.gv`, `.svg`, `.mmd`, `.json`, `.md` or `.html` for the
+        /// HTML report) unless `--format` is set, or to this directory with `--emit`
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+
+        /// Format of the graph, defaulting to the one matching the extension of `--output`, or
+        /// DOT
+        #[structopt(
+            long,
+            possible_values = &["dot", "svg", "mermaid", "json", "md", "html-report"],
+            env = "MUREGRAPH_FORMAT"
+        )]
+        format: Option<Format>,
+
+        /// Write the graph in each of these comma-separated formats at once, eg. `dot,svg,md`, to
+        /// `graph.<extension>` files in the `--output` directory
+        #[structopt(
+            long,
+            require_delimiter = true,
+            requires = "output",
+            conflicts_with = "format",
+            possible_values = &["dot", "svg", "mermaid", "json", "md", "html-report"]
+        )]
+        emit: Vec<Format>,
+    },
+
+    /// Run the lints, and fail if some of them notice errors
+    Lint,
+
+    /// Output the crates and their dependencies as JSON
+    Export,
+
+    /// Print the order in which publishable crates must be released, by groups of crates that
+    /// can be released in parallel
+    PublishOrder,
+
+    /// Print all the crates that transitively depend on a crate, by repository
+    Impact {
+        /// Name of the crate that would be changed
+        krate: String,
+    },
+
+    /// Compare the crates and dependencies with the ones described by another configuration, or
+    /// saved as JSON by `export` or `--save-model`
+    Diff {
+        /// Configuration file describing the old state of the repositories, or JSON snapshot of
+        /// their crates if it ends with `.json`
+        old: PathBuf,
+
+        /// Configuration file or JSON snapshot describing the new state of the repositories,
+        /// instead of `--config`
+        new: Option<PathBuf>,
+    },
+
+    /// Post the lint summary, and the dependency changes if `--against` is set, as a comment on
+    /// a GitHub pull request, updating the comment of a previous run in place, and fail like
+    /// `lint`
+    PrComment {
+        /// Repository of the pull request, as `owner/name`
+        #[structopt(long)]
+        github_repo: String,
+
+        /// Number of the pull request
+        #[structopt(long)]
+        pr: u64,
+
+        /// GitHub token allowed to comment on the pull request
+        #[structopt(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        github_token: String,
+
+        /// Configuration file describing the repositories before the pull request, to report
+        /// the dependency changes and draw them as a graph
+        #[structopt(long)]
+        against: Option<PathBuf>,
+    },
+
+    /// Print the longest chain of crates that must be released one after the other, and by
+    /// how many steps the release of each crate could be delayed without delaying the others
+    CriticalPath,
+
+    /// Print the longest chains of crates that must be released one after the other, along with
+    /// the repository of each crate
+    LongestChains {
+        /// Number of chains to print
+        #[structopt(long, default_value = "5")]
+        count: usize,
+    },
+
+    /// Print how coupled each pair of repositories is, and suggest which could be merged
+    Coupling {
+        /// Coupling score above which two mutually-dependent repositories are considered as
+        /// being effectively one codebase
+        #[structopt(long, default_value = "0.25")]
+        threshold: f64,
+
+        /// Report the coupling between owners, from the `CODEOWNERS` files of the repositories,
+        /// instead of between repositories
+        #[structopt(long)]
+        by_owner: bool,
+    },
+
+    /// Suggest how to split a repository into groups of loosely-coupled crates
+    Split {
+        /// Name of the repository to split
+        repo: String,
+    },
+
+    /// Print which crates must be bumped and published, in order, and which requirements must
+    /// be updated, after some crates changed
+    BumpPlan {
+        /// Names of the crates that changed
+        changed: Vec<String>,
+
+        /// Also consider as changed the crates that were added, or whose version, dependencies
+        /// or publish status differ, from the repositories described by this configuration
+        #[structopt(long)]
+        against: Option<PathBuf>,
+    },
+
+    /// Print, by repository, the requirements on crates of the repositories that the current
+    /// version of these crates is not compatible with, and how to update them
+    OutdatedRequirements {
+        /// Print the updates as diffs of the dependency declarations, crate by crate
+        #[structopt(long)]
+        diff: bool,
+    },
+
+    /// Print, for each crate of the repositories that other crates require with semver-incompatible
+    /// requirements, the fewest requirement changes unifying them, manifest by manifest
+    UnifyRequirements,
+
+    /// Print a Markdown checklist of the crates to bump and publish, in order, after some crates
+    /// changed, and of the requirements to update in downstream repositories
+    ReleasePlan {
+        /// Comma-separated names of the crates that changed
+        #[structopt(long, required = true, require_delimiter = true)]
+        changed: Vec<String>,
+    },
+
+    /// Print the crates matching a query, which can be one of `deps(crate)` or
+    /// `deps(crate, depth)` for the crates that `crate` depends on, `rdeps(crate)` or
+    /// `rdeps(crate, depth)` for the crates that depend on `crate`, `path(from, to)` for a
+    /// shortest dependency path from `from` to `to`, and `repo(pattern)` for the crates of the
+    /// repositories matching `pattern`, where `*` matches any string
+    Query {
+        query: String,
+
+        /// Output the crates as JSON instead of one per line
+        #[structopt(long)]
+        json: bool,
+    },
+
+    /// Fail if any crate selected by `from` depends, directly or not, on any crate selected by
+    /// `to`, where selectors are either `crate:pattern` or `repo:pattern` (or just `pattern`
+    /// for crates), and `*` in patterns matches any string
+    CheckUnreachable { from: String, to: String },
+
+    /// Print how many crates could be built in parallel at each step, and the minimal number
+    /// of sequential build waves
+    Parallelism,
+
+    /// Print how the statistics saved with `--save-snapshot` in a directory evolved over time,
+    /// without fetching any repository
+    Trends {
+        /// Directory the snapshots were saved in
+        dir: PathBuf,
+    },
+
+    /// Print the crates that every dependency path from the application crates to other crates
+    /// goes through, along with the crates they dominate this way
+    Dominators {
+        /// Selector for the application crates (either `crate:pattern` or `repo:pattern`),
+        /// defaulting to the crates no other crate depends on
+        #[structopt(long)]
+        from: Option<String>,
+    },
+
+    /// Print statistics about each crate and repository
+    Metrics {
+        /// Output the statistics as JSON instead of tables
+        #[structopt(long)]
+        json: bool,
+    },
+
+    /// Print the crates with the most transitive dependents, overall and in each repository,
+    /// which deserve the strictest review and compatibility guarantees
+    TopDependents {
+        /// Number of crates to print, overall and for each repository
+        #[structopt(long, default_value = "10")]
+        count: usize,
+
+        /// Output the crates as JSON instead of tables
+        #[structopt(long)]
+        json: bool,
+    },
+
+    /// Serve the dependency graph as an HTML page, DOT, SVG and JSON over HTTP, fetching the
+    /// repositories again periodically
+    Serve {
+        /// Address to listen on
+        #[structopt(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Interval at which to fetch the repositories again (eg. `30s` or `1h`)
+        #[structopt(long, default_value = "5m")]
+        refresh: humantime::Duration,
+    },
+
+    /// Write a configuration file listing the repositories of a GitHub organization that
+    /// contain Rust crates
+    Init {
+        /// GitHub organization whose repositories to list
+        #[structopt(long)]
+        github_org: String,
+
+        /// GitHub token used to list private repositories, and to avoid rate limits
+        #[structopt(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        github_token: Option<String>,
+
+        /// Overwrite the configuration file if it already exists
+        #[structopt(long)]
+        force: bool,
+    },
+
+    /// Check that the configuration file is valid and that every repository it lists can be
+    /// fetched, without fetching them: that URLs can be downloaded, that git repositories have
+    /// their pinned refs, and that directories have a Cargo.toml
+    CheckConfig,
+
+    /// Browse the crates, their dependencies and the cycles across repositories in an
+    /// interactive terminal interface
+    Tui,
+
+    /// Output a shields-style SVG badge showing a statistic, eg. to embed in a README
+    Badge {
+        /// Statistic to show
+        #[structopt(long, possible_values = &["cycles", "cross-repo-edges", "crates"])]
+        metric: BadgeMetric,
+
+        /// Write the badge to this file instead of the standard output
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Output Backstage `catalog-info.yaml` Component entities, with `dependsOn` relations
+    /// derived from the dependencies, for the crates to show up in a Backstage developer portal
+    Backstage {
+        /// Whether each component is a crate or a repository
+        #[structopt(long, default_value = "crate", possible_values = &["crate", "repo"])]
+        per: CatalogGranularity,
+
+        /// Owner of the components, as a Backstage entity reference, eg. "group:platform"
+        #[structopt(long)]
+        owner: String,
+
+        /// Lifecycle of the components, eg. "experimental"
+        #[structopt(long, default_value = "production")]
+        lifecycle: String,
+
+        /// Write the entities to this file instead of the standard output
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write a static site of the dependency graph to a directory, eg. to publish with GitHub
+    /// Pages: an overview with the graph and the key metrics, a page per repository and per
+    /// crate, and the dashboard of the lints
+    Site {
+        /// Directory to write the site to
+        #[structopt(short, long)]
+        output: PathBuf,
+    },
+
+    /// Write the configuration of cargo-release or release-plz for the workspace of each
+    /// repository with publishable crates, telling in comments how it fits in the publish order
+    /// across repositories
+    ReleaseConfig {
+        /// Release tool to configure
+        #[structopt(long, possible_values = &["cargo-release", "release-plz"])]
+        tool: ReleaseTool,
+
+        /// Directory to write the configuration of each repository to, in a subdirectory named
+        /// after the repository
+        #[structopt(short, long)]
+        output: PathBuf,
+    },
+
+    /// Check, eg. from a git pre-commit or pre-push hook, whether a local checkout adds
+    /// dependencies between repositories or cycles across them, only scanning the checkout and
+    /// taking the other repositories from the output of `muregraph export`, and fail like
+    /// `lint` if it does
+    Hook {
+        /// Name of the repository that is checked out locally
+        #[structopt(long)]
+        repo: String,
+
+        /// Root of the local checkout
+        #[structopt(long, default_value = ".")]
+        path: PathBuf,
+
+        /// Crates of all the repositories before the change, as output by `muregraph export`
+        #[structopt(long)]
+        snapshot: PathBuf,
+    },
+
+    /// Check that the crates have no dependencies between repositories nor cycles across them
+    /// that a baseline, committed alongside the configuration, does not have, and fail like
+    /// `lint` if they do, eg. as an architectural regression test in CI
+    Check {
+        /// Crates of all the repositories at the time of the baseline, as output by `muregraph
+        /// export`
+        #[structopt(long)]
+        against: PathBuf,
+
+        /// Write the current crates to the baseline instead of checking them, eg. once the new
+        /// dependencies are accepted
+        #[structopt(long)]
+        update_baseline: bool,
+    },
+
+    /// Output a completion script for a shell, completing repository names from the
+    /// configuration file if it can be read
+    Completions {
+        #[structopt(possible_values = &structopt::clap::Shell::variants(), case_insensitive = true)]
+        shell: structopt::clap::Shell,
+    },
+}
+
+impl Opt {
+    /// Fill the options with multiple values from the environment when they are not set on the
+    /// command line, which clap would otherwise merge with the environment
+    fn read_env_lists(&mut self) {
+        for (values, var) in [
+            (&mut self.only, "MUREGRAPH_ONLY"),
+            (&mut self.exclude, "MUREGRAPH_EXCLUDE"),
+        ] {
+            match std::env::var(var) {
+                Ok(env) if values.is_empty() && !env.is_empty() => {
+                    *values = env.split(',').map(String::from).collect()
                 }
+                _ => (),
             }
+        }
+    }
 
-            // Save the crate
-            res.push(CrateInfo {
-                name: package.name.clone(),
-                published_to: match package.publish {
-                    cargo_toml::Publish::Flag(true) => Publish::Default,
-                    cargo_toml::Publish::Flag(false) => Publish::Nowhere,
-                    cargo_toml::Publish::Registry(registries) => Publish::At(registries),
-                },
-                deps,
-            });
+    /// Load a configuration file, only keeping the repositories selected by `--only` and
+    /// `--exclude`
+    fn load_config(&self, path: &Path) -> Result<Config, Failure> {
+        let cfg = load_config(path)?;
+        self.select_repos(path, cfg)
+    }
+
+    /// Load the main configuration, adding the current workspace when running as a cargo
+    /// subcommand, in which case the configuration file is optional
+    fn load_main_config(&self) -> Result<Config, Failure> {
+        let (name, root) = match &self.workspace {
+            Some(workspace) => workspace,
+            None => return self.load_config(&self.config),
+        };
+        let mut cfg = match self.config.exists() {
+            true => load_config(&self.config)?,
+            false => Config::default(),
+        };
+        // The local checkout takes precedence over the configured download
+        cfg.retain_repos(|repo| repo != name);
+        cfg.directories.insert(name.clone(), root.clone());
+        self.select_repos(&self.config, cfg)
+    }
+
+    fn select_repos(&self, path: &Path, mut cfg: Config) -> Result<Config, Failure> {
+        for pattern in self.only.iter().chain(self.exclude.iter()) {
+            if !cfg.repos().any(|r| glob_match(pattern, r)) {
+                return Err(Failure::Config(anyhow::anyhow!(
+                    "No repository of {:?} matches {:?}",
+                    path,
+                    pattern
+                )));
+            }
         }
+        cfg.retain_repos(|repo| self.selected(repo));
+        Ok(cfg)
     }
 
-    bar.set_prefix(&format!("handling {}", url_display));
-    bar.finish();
-    Ok(res)
-}
+    /// Whether `repo` is selected by `--only` and `--exclude`
+    fn selected(&self, repo: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|p| glob_match(p, repo)))
+            && !self.exclude.iter().any(|p| glob_match(p, repo))
+    }
 
-fn all_crates(
-    infos: &BTreeMap<String, Vec<CrateInfo>>,
-) -> impl Iterator<Item = (&str, &CrateInfo)> {
-    infos
-        .iter()
-        .flat_map(|(k, v)| v.iter().map(move |v| (k as &str, v)))
-}
+    /// Crates of the repositories described by `path`, a JSON snapshot saved by `export` or
+    /// `--save-model` if it ends with `.json`, or a configuration whose repositories are fetched
+    fn load_state(
+        &self,
+        fetcher: &Fetcher,
+        path: &Path,
+    ) -> Result<BTreeMap<String, Vec<CrateInfo>>, Failure> {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            let cfg = self.load_config(path)?;
+            return Ok(fetcher.fetch_all(&cfg)?);
+        }
+        let json = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut infos = import(&json).with_context(|| format!("Failed to load {:?}", path))?;
+        infos.retain(|repo, _| self.selected(repo));
+        if self.no_optional_deps {
+            for c in infos.values_mut().flatten() {
+                c.deps.retain(Dependency::enabled_by_default);
+            }
+        }
+        Ok(infos)
+    }
 
-fn find_info<'a>(
-    name: &str,
-    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
-) -> Option<(&'a str, &'a CrateInfo)> {
-    for (repo, crates) in infos {
-        for c in crates {
-            if c.name == name {
-                return Some((repo, c));
+    /// Load the crates saved with `--save-model` to `path`, along with the configuration if it
+    /// exists, for the settings of the lints and notifications, the groups and owners of the
+    /// repositories coming from the model
+    fn load_model(
+        &self,
+        path: &Path,
+    ) -> Result<(Config, BTreeMap<String, Vec<CrateInfo>>), Failure> {
+        let json = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let (mut infos, ownership) =
+            import_model(&json).with_context(|| format!("Failed to load {:?}", path))?;
+        let mut cfg = match self.config.exists() {
+            true => load_config(&self.config)?,
+            false => Config::default(),
+        };
+        // The repositories are those of the model rather than those to fetch
+        cfg.retain_repos(|_| false);
+        cfg.ownership = ownership;
+        infos.retain(|repo, _| self.selected(repo));
+        if self.no_optional_deps {
+            for c in infos.values_mut().flatten() {
+                c.deps.retain(Dependency::enabled_by_default);
             }
         }
+        Ok((cfg, infos))
     }
-    return None;
 }
 
-fn add_cycles_from(
-    root_repo: &str,
-    c: &CrateInfo,
-    parents: &mut Vec<(String, String)>,
-    infos: &BTreeMap<String, Vec<CrateInfo>>,
-    cycles: &mut Vec<Vec<(String, String)>>,
-) {
-    for d in c.deps.iter() {
-        if let Some((dep_repo, dep)) = find_info(&d.name, infos) {
-            if parents.iter().any(|i| i.0 == dep_repo && i.1 == dep.name) {
-                return; // loop that doesn't go through the root, let's not care
-            }
-            parents.push((dep_repo.to_string(), dep.name.clone()));
-            if dep_repo == root_repo {
-                cycles.push(parents.clone());
-            } else {
-                add_cycles_from(root_repo, dep, parents, infos, cycles);
-            }
-            parents.pop();
+/// Reason for which muregraph failed, determining its exit code
+enum Failure {
+    /// Some lints reported issues
+    Lint,
+    /// The configuration file could not be read, or is invalid
+    Config(anyhow::Error),
+    /// A repository could not be downloaded or parsed
+    Fetch(anyhow::Error),
+    /// Some repositories could not be downloaded or parsed, and were skipped with `--keep-going`
+    Partial,
+    /// Any other error
+    Other(anyhow::Error),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Other(_) => 1,
+            Failure::Lint => 2,
+            Failure::Config(_) => 3,
+            Failure::Fetch(_) => 4,
+            Failure::Partial => 5,
         }
     }
 }
 
-/// Returns true iff no lints returned any issue, false if a lint
-/// returned an issue, and an error if the input was too broken to be
-/// able to generate a graph
-fn sanity_check(infos: &BTreeMap<String, Vec<CrateInfo>>) -> anyhow::Result<bool> {
-    // Check that there are not two crates with the same name
-    let mut name_to_repo = BTreeMap::new();
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Failure {
+        Failure::Other(e)
+    }
+}
 
-    for (repo, infos) in infos.iter() {
-        for i in infos.iter() {
-            if let Some(r) = name_to_repo.get(&i.name) {
-                anyhow::bail!(
-                    "Crate {} was defined multiple times, eg. in repos {} and {}",
-                    i.name,
-                    r,
-                    repo
-                );
-            }
-            name_to_repo.insert(i.name.clone(), repo);
-        }
-    }
-
-    // Check the circular dependencies across repositories
-    //
-    // Naive algorithm for now, because complexity is not really
-    // important with relatively few repositories: for each node in
-    // the graph (root), look down the dependency tree until finding
-    // one that has the same repo, while checking that the first
-    // dependency was in another repo
-    let mut cycles = Vec::new();
-    for (root_repo, root) in all_crates(infos) {
-        for d in root.deps.iter() {
-            let dep_name = &d.name;
-            if let Some((dep_repo, dep)) = find_info(dep_name, infos) {
-                if dep_repo != root_repo {
-                    add_cycles_from(
-                        root_repo,
-                        dep,
-                        &mut vec![
-                            (root_repo.to_string(), root.name.clone()),
-                            (dep_repo.to_string(), dep_name.clone()),
-                        ],
-                        infos,
-                        &mut cycles,
-                    );
-                }
-            }
+impl From<muregraph::Error> for Failure {
+    fn from(e: muregraph::Error) -> Failure {
+        match e {
+            muregraph::Error::Config(e) => Failure::Config(e),
+            muregraph::Error::Fetch(e)
+            | muregraph::Error::Decode(e)
+            | muregraph::Error::Parse(e) => Failure::Fetch(e),
+            e => Failure::Other(e.into_inner()),
         }
     }
-    if !cycles.is_empty() {
-        eprintln!(
-            "Cyclic dependencies across repositories ({}):",
-            cycles.len()
-        );
+}
+
+/// Generate the completion script for `shell`, completing the arguments naming a repository
+/// with `repos`
+fn completions(shell: structopt::clap::Shell, repos: &[String]) -> String {
+    let mut script = Vec::new();
+    Opt::clap().gen_completions_to("muregraph", shell, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+    if repos.is_empty() {
+        return script;
     }
-    let all_lints_passed = cycles.is_empty();
-    for c in cycles {
-        eprint!(" *");
-        for (repo, krate) in c {
-            eprint!(
-                " {}{}",
-                console::style(krate).for_stderr().bold(),
-                console::style(format!("[{}]", repo))
-                    .for_stderr()
-                    .dim()
-                    .italic(),
+    let repos = repos.join(" ");
+    // clap does not support completing a positional argument with dynamic values, so patch the
+    // placeholders it generates
+    match shell {
+        structopt::clap::Shell::Bash => script.replace("<repo>", &repos),
+        structopt::clap::Shell::Zsh => script.replace(
+            "Name of the repository to split:_files'",
+            &format!("Name of the repository to split:({})'", repos),
+        ),
+        structopt::clap::Shell::Fish => format!(
+            "{}complete -c muregraph -n \"__fish_seen_subcommand_from split\" -f -a \"{}\"\n",
+            script, repos
+        ),
+        _ => script,
+    }
+}
+
+/// Model held in memory by `muregraph serve`
+struct Served {
+    infos: BTreeMap<String, Vec<CrateInfo>>,
+    ownership: Ownership,
+    graphviz: GraphvizConfig,
+    /// Whether the graphviz engine could be run at the last refresh, to render the SVG graph
+    svg: bool,
+    updated: chrono::DateTime<chrono::Utc>,
+}
+
+/// Answer a request for `url`, returning its content type and body, or `None` if there is
+/// nothing at this URL
+///
+/// The crates are only read while rendering in memory, so that graphviz does not hold off their
+/// refresh.
+fn handle_request(
+    url: &str,
+    served: &std::sync::RwLock<Served>,
+) -> anyhow::Result<Option<(&'static str, Vec<u8>)>> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query.split('&').collect::<Vec<_>>();
+    // Only the graphs drawn with graphviz have several types and can be condensed
+    let options = match path {
+        "/graph.dot" | "/graph.svg" => RenderOptions {
+            graph_type: match (params.contains(&"colors"), params.contains(&"groups")) {
+                (true, _) => GraphType::Colors,
+                (false, true) => GraphType::Groups,
+                (false, false) => GraphType::Cluster,
+            },
+            condense: params.contains(&"condense"),
+            ..RenderOptions::default()
+        },
+        _ => RenderOptions::default(),
+    };
+    let served = served.read().unwrap();
+    Ok(Some(match path {
+        "/" => ("text/html", include_bytes!("serve.html").to_vec()),
+        "/graph.dot" => (
+            "text/vnd.graphviz",
+            render(Format::Dot, &options, &served.infos, &served.ownership)?,
+        ),
+        // Like `muregraph graph`, fall back to the DOT graph when graphviz is missing
+        "/graph.svg" if !served.svg => {
+            let mut dot = format!(
+                "// `{}` was not found on the server, is graphviz installed? This is the graph in the DOT format instead of SVG, which `dot -Tsvg` renders\n",
+                served.graphviz.engine()
+            )
+            .into_bytes();
+            dot.extend(render(
+                Format::Dot,
+                &options,
+                &served.infos,
+                &served.ownership,
+            )?);
+            ("text/vnd.graphviz", dot)
+        }
+        "/graph.svg" => {
+            let dot = render(Format::Dot, &options, &served.infos, &served.ownership)?;
+            let graphviz = served.graphviz.clone();
+            drop(served);
+            ("image/svg+xml", render_svg(&dot, &graphviz)?)
+        }
+        "/graph.mmd" => (
+            "text/plain",
+            render(Format::Mermaid, &options, &served.infos, &served.ownership)?,
+        ),
+        "/crates.json" => (
+            "application/json",
+            render(Format::Json, &options, &served.infos, &served.ownership)?,
+        ),
+        "/metrics.json" => (
+            "application/json",
+            serde_json::to_vec_pretty(&metrics(&served.infos)?)?,
+        ),
+        _ => return Ok(None),
+    }))
+}
+
+fn serve(
+    opt: &Opt,
+    fetcher: &Fetcher,
+    listen: &str,
+    refresh: std::time::Duration,
+) -> Result<(), Failure> {
+    let fetch = || -> Result<Served, Failure> {
+        let cfg = opt.load_main_config()?;
+        fetcher.reset_stats();
+        let infos = fetcher.fetch_all(&cfg)?;
+        check_unique_names(&infos).context("Failed to sanity-check the computed information")?;
+        let svg = cfg.graphviz.installed();
+        if !svg {
+            tracing::warn!(
+                "`{}` was not found, is graphviz installed? Serving the graph in the DOT format as /graph.svg",
+                cfg.graphviz.engine()
             );
         }
-        eprintln!();
-    }
+        Ok(Served {
+            infos,
+            ownership: cfg.ownership,
+            graphviz: cfg.graphviz,
+            svg,
+            updated: chrono::Utc::now(),
+        })
+    };
+    let served = std::sync::RwLock::new(fetch()?);
+
+    let server = tiny_http::Server::http(listen)
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", listen, e))?;
+    tracing::info!("Serving the dependency graph on http://{}/", listen);
+
+    std::thread::scope(|s| {
+        s.spawn(|| loop {
+            std::thread::sleep(refresh);
+            match fetch() {
+                Ok(new) => *served.write().unwrap() = new,
+                Err(failure) => log_failure(&failure),
+            }
+        });
+
+        for request in server.incoming_requests() {
+            let last_modified = served
+                .read()
+                .unwrap()
+                .updated
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string();
+            let (status, content_type, body) = match handle_request(request.url(), &served) {
+                Ok(Some((content_type, body))) => (200, content_type, body),
+                Ok(None) => (404, "text/plain", b"Not found".to_vec()),
+                Err(e) => (500, "text/plain", format!("{:?}", e).into_bytes()),
+            };
+            tracing::info!(url = request.url(), status, "Answered a request");
+            let response = tiny_http::Response::from_data(body)
+                .with_status_code(status)
+                .with_header(
+                    tiny_http::Header::from_bytes("Content-Type", content_type)
+                        .expect("Invalid Content-Type header"),
+                )
+                .with_header(
+                    tiny_http::Header::from_bytes("Last-Modified", last_modified)
+                        .expect("Invalid Last-Modified header"),
+                );
+            if let Err(e) = request.respond(response) {
+                tracing::warn!("Failed to answer a request: {}", e);
+            }
+        }
+    });
 
-    Ok(all_lints_passed)
+    Ok(())
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum GraphType {
-    Cluster,
-    Colors,
+/// Check that `url` is well-formed and can be downloaded, with a HEAD request
+fn check_url(client: &reqwest::blocking::Client, url: &str) -> anyhow::Result<()> {
+    let parsed = reqwest::Url::parse(url).context("Invalid URL")?;
+    ensure!(
+        parsed.scheme() == "http" || parsed.scheme() == "https",
+        "Unsupported URL scheme {:?}",
+        parsed.scheme()
+    );
+    let response = client
+        .head(url)
+        .send()
+        .context("Failed to reach the server")?;
+    match response.status() {
+        s if s.is_success() => Ok(()),
+        s @ reqwest::StatusCode::UNAUTHORIZED | s @ reqwest::StatusCode::FORBIDDEN => {
+            anyhow::bail!("Authentication failed ({}), is the token valid?", s)
+        }
+        s @ reqwest::StatusCode::NOT_FOUND => anyhow::bail!(
+            "Not found ({}), or private and the token is missing or lacks access",
+            s
+        ),
+        s => anyhow::bail!("Unexpected response ({})", s),
+    }
 }
 
-fn make_graph(
-    graph_type: GraphType,
-    infos: &BTreeMap<String, Vec<CrateInfo>>,
-) -> anyhow::Result<()> {
-    println!("digraph G {{");
-    println!("    node [shape=rectangle]");
-
-    // First, put all the nodes in their repository
-    if graph_type == GraphType::Cluster {
-        for (repo, infos) in infos.iter() {
-            println!("    subgraph \"cluster_{}\" {{", repo);
-            println!("        label = \"{}\";", repo);
-            println!("        style = filled;");
-            for i in infos.iter() {
-                let color = match i.published_to {
-                    Publish::Nowhere => "color=blue",
-                    Publish::Default => "color=green",
-                    Publish::At(_) => "",
-                };
-                println!("        \"{}\" [{}];", i.name, color);
+/// Check that `url` is a git repository that can be cloned, with `git ls-remote`, and that it
+/// has ref `rev`, if set and not a commit, which only cloning would check
+fn check_git(url: &str, rev: Option<&str>) -> anyhow::Result<()> {
+    let mut command = std::process::Command::new("git");
+    // Fail rather than wait for credentials
+    command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["ls-remote", "--exit-code", url]);
+    let is_commit = |rev: &str| rev.len() >= 7 && rev.chars().all(|c| c.is_ascii_hexdigit());
+    if let Some(rev) = rev.filter(|rev| !is_commit(rev)) {
+        command.arg(rev);
+    }
+    let out = command.output().context("Failed to run git")?;
+    match (out.status.code(), rev) {
+        (Some(0), _) => Ok(()),
+        // `--exit-code` exits with 2 when no ref matches
+        (Some(2), Some(rev)) => anyhow::bail!("No branch or tag {:?}", rev),
+        _ => anyhow::bail!("{}", String::from_utf8_lossy(&out.stderr).trim()),
+    }
+}
+
+/// Check that `path` is a directory with a `Cargo.toml` file, in it or in its subdirectories if
+/// `nested`, skipping build outputs and hidden directories like a scan does
+fn check_directory(path: &Path, nested: bool) -> anyhow::Result<()> {
+    ensure!(path.is_dir(), "No directory at {:?}", path);
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if dir.join("Cargo.toml").is_file() {
+            return Ok(());
+        }
+        if !nested {
+            break;
+        }
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to list {:?}", dir))? {
+            let entry = entry.with_context(|| format!("Failed to list {:?}", dir))?;
+            let name = entry.file_name();
+            if entry.path().is_dir() && name != "target" && !name.to_string_lossy().starts_with('.')
+            {
+                pending.push(entry.path());
             }
-            println!("    }}");
         }
+    }
+    anyhow::bail!("No Cargo.toml in {:?}", path)
+}
+
+/// Check that repository `repo` of `cfg` can be fetched, without fetching it, the URLs of the
+/// pinned tarballs being those of their pinned refs already
+fn check_repo(cfg: &Config, client: &reqwest::blocking::Client, repo: &str) -> anyhow::Result<()> {
+    if let Some(url) = cfg.tarballs.get(repo) {
+        check_url(client, url)
+    } else if let Some(url) = cfg.git.get(repo) {
+        check_git(url, cfg.pins.get(repo).map(|rev| rev as &str))
+    } else if let Some(path) = cfg.directories.get(repo) {
+        check_directory(path, true)
+    } else if let Some(path) = cfg.cargo_metadata.get(repo) {
+        check_directory(path, false)
     } else {
-        ensure!(infos.len() <= COLORS.len(), "asked for a color-based output while there are more repositories than colors available");
-        for (idx, (_, infos)) in infos.iter().enumerate() {
-            for i in infos.iter() {
-                println!(
-                    "    \"{}\" [style=filled, fillcolor=\"{}\"];",
-                    i.name, COLORS[idx]
-                );
+        unreachable!("repository {} is not listed in any section", repo)
+    }
+}
+
+fn check_config(opt: &Opt) -> Result<(), Failure> {
+    let path = &opt.config;
+    let cfg = opt.load_main_config()?;
+    let client = cfg.network.client()?;
+
+    let mut problems = 0;
+    for repo in cfg.repos() {
+        match check_repo(&cfg, &client, repo) {
+            Ok(()) => println!("{:<30} {}", repo, console::style("ok").green()),
+            Err(e) => {
+                problems += 1;
+                println!("{:<30} {} {:#}", repo, console::style("error").red(), e);
             }
         }
     }
 
-    // Then, draw all arrows
-    for (_, infos) in infos.iter() {
-        for i in infos.iter() {
-            for d in i.deps.iter() {
-                // For now we're interested only in stuff from our own registry or that has
-                // path-local dependencies
-                if d.from.is_some() || d.has_path {
-                    let color = if d.has_path { "[color=blue]" } else { "" };
-                    println!("    \"{}\" -> \"{}\" {};", i.name, d.name, color);
-                }
-            }
+    if problems > 0 {
+        return Err(Failure::Config(anyhow::anyhow!(
+            "{} of the {} repositories of {:?} cannot be fetched",
+            problems,
+            cfg.repos().count(),
+            path
+        )));
+    }
+    Ok(())
+}
+
+/// Hide the credentials that `url` may contain
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.username() != "" || parsed.password().is_some() => {
+            let _ = parsed.set_username("***");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
         }
+        _ => url.to_string(),
     }
+}
+
+/// Size of the archive at `url` according to a HEAD request, if the server tells it
+fn head_size(client: &reqwest::blocking::Client, url: &str) -> anyhow::Result<Option<u64>> {
+    let response = client
+        .head(url)
+        .send()
+        .context("Failed to reach the server")?;
+    ensure!(
+        response.status().is_success(),
+        "Unexpected response ({})",
+        response.status()
+    );
+    Ok(response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|l| l.to_str().ok())
+        .and_then(|l| l.parse().ok()))
+}
 
-    println!("}}");
+fn dry_run(opt: &Opt) -> Result<(), Failure> {
+    let (cfg, model) = match &opt.from_model {
+        Some(path) => {
+            let (cfg, infos) = opt.load_model(path)?;
+            (cfg, Some((path, infos)))
+        }
+        None => (opt.load_main_config()?, None),
+    };
+    let client = cfg.network.client()?;
+
+    println!("Repositories:");
+    if let Some((path, infos)) = &model {
+        for repo in infos.keys() {
+            println!("  {:<30} {:?}: read from the model", repo, path);
+        }
+    }
+    for (repo, url) in cfg.tarballs.iter() {
+        let cached = opt
+            .cache_dir
+            .as_ref()
+            .is_some_and(|dir| dir.join(cache_key(url)).exists());
+        let action = match (cached, opt.offline || opt.frozen) {
+            (true, true) => String::from("use the cached archive"),
+            (true, false) => String::from("download if the cached archive changed"),
+            (false, _) if opt.frozen => String::from("fail, as the archive is not cached"),
+            (false, _) => match head_size(&client, url) {
+                Ok(Some(size)) => format!("download {}", indicatif::HumanBytes(size)),
+                Ok(None) => String::from("download, size unknown"),
+                Err(e) => format!("download, but HEAD request failed: {:#}", e),
+            },
+        };
+        println!("  {:<30} {}: {}", repo, redact_url(url), action);
+    }
+    for (repo, dir) in cfg.directories.iter() {
+        println!("  {:<30} {:?}: scan the directory", repo, dir);
+    }
+    for (repo, url) in cfg.git.iter() {
+        match cfg.pins.get(repo) {
+            Some(rev) => println!(
+                "  {:<30} {}: clone the repository at {}",
+                repo,
+                redact_url(url),
+                rev
+            ),
+            None => println!("  {:<30} {}: clone the repository", repo, redact_url(url)),
+        }
+    }
+    for (repo, dir) in cfg.cargo_metadata.iter() {
+        println!("  {:<30} {:?}: run cargo metadata", repo, dir);
+    }
 
+    println!("Outputs:");
+    match &opt.command {
+        Command::Graph {
+            output: Some(output),
+            ..
+        }
+        | Command::Badge {
+            output: Some(output),
+            ..
+        }
+        | Command::Backstage {
+            output: Some(output),
+            ..
+        } => println!("  {:?}", output),
+        Command::ReleaseConfig { tool, output } => {
+            println!(
+                "  {:?}, a {} and a publish-order.json per repository",
+                output,
+                tool.file_name()
+            )
+        }
+        Command::Site { output } => println!("  {:?}, a static site", output),
+        Command::Serve { listen, .. } => println!("  HTTP server on {}", listen),
+        Command::Tui => println!("  terminal interface"),
+        Command::PrComment {
+            github_repo, pr, ..
+        } => println!("  comment on pull request {}#{}", github_repo, pr),
+        Command::Check {
+            against,
+            update_baseline: true,
+        } => println!("  {:?}", against),
+        _ => println!("  standard output"),
+    }
+    if let Some(dir) = &opt.cache_dir {
+        if !opt.frozen {
+            println!("  {:?}, archives that are downloaded", dir);
+        }
+    }
+    if let Some(dir) = &opt.save_snapshot {
+        println!("  {:?}, a new snapshot", dir);
+    }
+    if let Some(path) = &opt.save_model {
+        println!("  {:?}, the model of the crates", path);
+    }
+    if let Some(path) = &opt.summary {
+        println!("  {:?}", path);
+    }
+    if let Some(url) = &cfg.notify.webhook {
+        println!(
+            "  {}, if there are new lint findings or cycles",
+            redact_url(url)
+        );
+    }
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    let opt = Opt::from_args();
+#[derive(serde::Deserialize)]
+struct GithubRepo {
+    name: String,
+    private: bool,
+    archived: bool,
+    default_branch: String,
+}
 
-    let cfg =
-        std::fs::read(&opt.config).with_context(|| format!("Failed to read {:?}", opt.config))?;
-    let cfg: Config =
-        toml::from_slice(&cfg).with_context(|| format!("Failed to parse {:?}", opt.config))?;
+/// Call the GitHub API at `path`, returning `None` upon a 404
+fn github_get(
+    client: &reqwest::blocking::Client,
+    token: Option<&str>,
+    path: &str,
+) -> anyhow::Result<Option<reqwest::blocking::Response>> {
+    github_request(client, token, reqwest::Method::GET, path, None)
+}
 
-    let dir = tempfile::tempdir().context("Failed to create a temporary directory")?;
+/// Call the GitHub API at `path` with `method`, sending `body` as JSON, returning `None` upon a
+/// 404
+fn github_request(
+    client: &reqwest::blocking::Client,
+    token: Option<&str>,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> anyhow::Result<Option<reqwest::blocking::Response>> {
+    let url = format!("https://api.github.com{}", path);
+    let mut request = client
+        .request(method.clone(), &url)
+        .header("User-Agent", "muregraph")
+        .header("Accept", "application/vnd.github.v3+json");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("token {}", token));
+    }
+    if let Some(body) = body {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+    }
+    let response = request
+        .send()
+        .with_context(|| format!("Failed to send {} request to URL {:?}", method, url))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    ensure!(
+        response.status().is_success(),
+        "{} request to {:?} was unsuccessful: {}",
+        method,
+        url,
+        response.status()
+    );
+    Ok(Some(response))
+}
 
-    let client = reqwest::blocking::Client::builder()
-        .build()
-        .context("Failed to initialize reqwest")?;
+/// Marker of the comments posted by `pr-comment`, to find and update them on the next run
+const PR_COMMENT_MARKER: &str = "<!-- muregraph pr-comment -->";
 
-    let infos: BTreeMap<String, Vec<CrateInfo>> = cfg
-        .tarballs
-        .iter()
-        .map(|(name, url)| -> anyhow::Result<(String, Vec<CrateInfo>)> {
-            Ok((
-                name.clone(),
-                handle_tarball(&client, &dir, name, url).with_context(|| {
-                    format!("Failed to retrieve informations for repository {}", name)
-                })?,
-            ))
-        })
-        .collect::<anyhow::Result<_>>()?;
+#[derive(serde::Deserialize)]
+struct GithubComment {
+    id: u64,
+    #[serde(default)]
+    body: Option<String>,
+}
 
-    let all_lints_passed =
-        sanity_check(&infos).context("Failed to sanity-check the computed information")?;
+/// `outputs` of `muregraph graph` with the SVG ones replaced by DOT ones, written next to where the
+/// SVG would have been, as the graphviz `engine` could not be run
+fn svg_fallback(
+    outputs: Vec<(Format, Option<PathBuf>)>,
+    engine: &str,
+) -> Vec<(Format, Option<PathBuf>)> {
+    let mut res = Vec::new();
+    for (format, output) in outputs {
+        let (format, output) = match format {
+            Format::Svg => {
+                let output = output.map(|o| o.with_extension("dot"));
+                let to = output
+                    .as_ref()
+                    .map_or("the standard output".to_string(), |o| format!("{:?}", o));
+                tracing::warn!(
+                    "`{}` was not found, is graphviz installed? Writing the graph in the DOT format to {} instead of SVG, which `dot -Tsvg` renders",
+                    engine,
+                    to
+                );
+                (Format::Dot, output)
+            }
+            _ => (format, output),
+        };
+        // With `--emit dot,svg`, the DOT file is already written
+        if !res.contains(&(format, output.clone())) {
+            res.push((format, output));
+        }
+    }
+    res
+}
 
-    let graph_type = match opt.use_colors {
-        true => GraphType::Colors,
-        false => GraphType::Cluster,
+/// Markdown checklist of `plan`: the crates to bump and publish in order, each after updating its
+/// requirements on the crates published before it, then the requirements to update afterwards in
+/// the repositories depending on the published crates, by repository
+fn release_checklist(plan: &BumpPlan) -> String {
+    let requirement = |c: &CrateInfo, d: &Dependency| match plan.updated_requirement(d) {
+        Some(updated) => format!(
+            "- [ ] Update the requirement of `{}` on `{}` from `{}` to `{}`\n",
+            c.name, d.name, d.req, updated
+        ),
+        None => format!(
+            "- [ ] Update the requirement of `{}` on `{}` from `{}` to its new version\n",
+            c.name, d.name, d.req
+        ),
     };
-    make_graph(graph_type, &infos).context("Failed to output the dependency graph")?;
+    let mut body = String::from("# Release plan\n\n## Bump and publish, in order\n\n");
+    for (i, (repo, c, reason)) in plan.to_publish.iter().enumerate() {
+        let target = match plan.versions.get(&c.name as &str) {
+            Some(version) => format!("from {} to {}", c.version, version),
+            None => format!("from {} to a breaking version", c.version),
+        };
+        body += &format!(
+            "{}. [ ] Bump `{}` {} and publish it from `{}` ({})\n",
+            i + 1,
+            c.name,
+            target,
+            repo,
+            reason
+        );
+        for (_, _, d) in plan.to_update.iter().filter(|(_, u, _)| u.name == c.name) {
+            body += &format!("    {}", requirement(c, d));
+        }
+    }
 
-    if opt.lint {
-        anyhow::ensure!(
-            all_lints_passed,
-            "Some lints reported issues, see error log above"
+    let mut downstream = BTreeMap::<&str, Vec<_>>::new();
+    for (repo, c, d) in plan.to_update.iter() {
+        if !plan.to_publish.iter().any(|(_, p, _)| p.name == c.name) {
+            downstream.entry(repo).or_default().push((c, d));
+        }
+    }
+    if !downstream.is_empty() {
+        body += "\n## Update the downstream repositories\n";
+        for (repo, updates) in downstream {
+            body += &format!("\n### `{}`\n\n", repo);
+            for (c, d) in updates {
+                body += &requirement(c, d);
+            }
+        }
+    }
+    body
+}
+
+/// Markdown body of the pull request comment: the lint summary, then the changes from
+/// `old_infos` if any, as a diff and as a mermaid graph of the added and removed dependencies
+fn pr_comment_body(
+    results: &[LintResult],
+    old_infos: Option<&BTreeMap<String, Vec<CrateInfo>>>,
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> String {
+    let mut body = format!("{}\n### Dependency report\n\n", PR_COMMENT_MARKER);
+    body += "| lint | findings | severity | result |\n|---|--:|---|---|\n";
+    for r in results {
+        let result = if r.findings == 0 {
+            "pass"
+        } else if r.severity == Severity::Error {
+            "**fail**"
+        } else {
+            "warn"
+        };
+        body += &format!(
+            "| `{}` | {} | {} | {} |\n",
+            r.id, r.findings, r.severity, result
         );
     }
+    body += "\nRun `muregraph lint` for the findings.\n";
 
+    let old_infos = match old_infos {
+        Some(old_infos) => old_infos,
+        None => return body,
+    };
+    body += "\n### Dependency changes\n\n";
+    let sections = diff_sections(old_infos, infos);
+    if sections.is_empty() {
+        body += "No changes\n";
+        return body;
+    }
+    body += "```diff\n";
+    for (title, lines) in sections {
+        body += &format!("{}:\n", title);
+        for l in lines {
+            body += &format!("{}\n", l);
+        }
+    }
+    body += "```\n";
+
+    let old_edges = internal_edges(old_infos);
+    let new_edges = internal_edges(infos);
+    let changes = new_edges
+        .difference(&old_edges)
+        .map(|e| (e, "== added ==>"))
+        .chain(
+            old_edges
+                .difference(&new_edges)
+                .map(|e| (e, "-. removed .->")),
+        )
+        .collect::<Vec<_>>();
+    if !changes.is_empty() {
+        // Mermaid identifiers cannot contain dashes, so number the crates instead
+        let mut ids = BTreeMap::new();
+        let mut node = |c| match ids.get(c) {
+            Some(id) => format!("c{}", id),
+            None => {
+                let id = ids.len();
+                ids.insert(c, id);
+                format!("c{}[\"{}\"]", id, c)
+            }
+        };
+        let mut graph = String::new();
+        for ((_, from, _, to), arrow) in changes {
+            let from = node(*from);
+            let to = node(*to);
+            graph += &format!("    {} {} {}\n", from, arrow, to);
+        }
+        body += &format!(
+            "\n<details><summary>Graph of the changed dependencies</summary>\n\n```mermaid\nflowchart LR\n{}```\n\n</details>\n",
+            graph
+        );
+    }
+    body
+}
+
+/// Post `body` as a comment on pull request `pr` of `repo`, or update the comment posted by a
+/// previous run
+fn upsert_pr_comment(
+    client: &reqwest::blocking::Client,
+    token: &str,
+    repo: &str,
+    pr: u64,
+    body: &str,
+) -> anyhow::Result<()> {
+    let mut existing = None;
+    for page in 1.. {
+        let path = format!(
+            "/repos/{}/issues/{}/comments?per_page=100&page={}",
+            repo, pr, page
+        );
+        let response = github_get(client, Some(token), &path)?
+            .with_context(|| format!("Pull request {}#{} does not exist", repo, pr))?;
+        let comments: Vec<GithubComment> = serde_json::from_reader(response)
+            .context("Failed to parse the comments of the pull request")?;
+        if comments.is_empty() {
+            break;
+        }
+        if let Some(c) = comments.into_iter().find(|c| {
+            c.body
+                .as_deref()
+                .is_some_and(|b| b.contains(PR_COMMENT_MARKER))
+        }) {
+            existing = Some(c.id);
+            break;
+        }
+    }
+    let (method, path) = match existing {
+        Some(id) => (
+            reqwest::Method::PATCH,
+            format!("/repos/{}/issues/comments/{}", repo, id),
+        ),
+        None => (
+            reqwest::Method::POST,
+            format!("/repos/{}/issues/{}/comments", repo, pr),
+        ),
+    };
+    let body = serde_json::json!({ "body": body });
+    github_request(client, Some(token), method, &path, Some(&body))?
+        .with_context(|| format!("Pull request {}#{} does not exist", repo, pr))?;
+    Ok(())
+}
+
+/// List the repositories of `org` with a `Cargo.toml` at their root
+fn github_rust_repos(
+    client: &reqwest::blocking::Client,
+    token: Option<&str>,
+    org: &str,
+) -> anyhow::Result<Vec<GithubRepo>> {
+    let mut repos = Vec::new();
+    for page in 1.. {
+        let path = format!("/orgs/{}/repos?per_page=100&page={}", org, page);
+        let response = github_get(client, token, &path)?
+            .with_context(|| format!("GitHub organization {} does not exist", org))?;
+        let page: Vec<GithubRepo> = serde_json::from_reader(response)
+            .with_context(|| format!("Failed to parse the repositories of {}", org))?;
+        if page.is_empty() {
+            break;
+        }
+        repos.extend(page);
+    }
+
+    let mut rust_repos = Vec::new();
+    for repo in repos {
+        if repo.archived {
+            tracing::info!(repo = %repo.name, "Skipping archived repository");
+            continue;
+        }
+        let path = format!("/repos/{}/{}/contents/Cargo.toml", org, repo.name);
+        if github_get(client, token, &path)?.is_some() {
+            rust_repos.push(repo);
+        } else {
+            tracing::info!(repo = %repo.name, "Skipping repository without a Cargo.toml");
+        }
+    }
+    Ok(rust_repos)
+}
+
+fn init(path: &Path, org: &str, token: Option<&str>, force: bool) -> Result<(), Failure> {
+    if !force && path.exists() {
+        return Err(Failure::Config(anyhow::anyhow!(
+            "{:?} already exists, pass --force to overwrite it",
+            path
+        )));
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .build()
+        .context("Failed to initialize reqwest")?;
+    let repos = github_rust_repos(&client, token, org)
+        .with_context(|| format!("Failed to list the repositories of {}", org))
+        .map_err(Failure::Fetch)?;
+
+    // Private repositories use a placeholder for the token, so that it does not end up in a
+    // file that could be committed
+    let tarballs = repos
+        .iter()
+        .map(|r| {
+            let auth = if r.private {
+                "GITHUB_TOKEN:x-oauth-basic@"
+            } else {
+                ""
+            };
+            let url = format!(
+                "https://{}github.com/{}/{}/tarball/{}",
+                auth, org, r.name, r.default_branch
+            );
+            (r.name.clone(), toml::Value::String(url))
+        })
+        .collect::<toml::value::Table>();
+    let mut cfg = toml::value::Table::new();
+    cfg.insert(String::from("tarballs"), toml::Value::Table(tarballs));
+    let mut cfg = toml::to_string(&cfg).context("Failed to serialize the configuration")?;
+
+    // Suggest the lints from the example configuration
+    let example = include_str!("../config.toml.example");
+    if let Some(lints) = example.find("[lints]") {
+        cfg.push('\n');
+        cfg.push_str(&example[lints..]);
+    }
+
+    std::fs::write(path, cfg).with_context(|| format!("Failed to write {:?}", path))?;
+    tracing::info!(
+        repos = repos.len(),
+        "Wrote {:?}, replace GITHUB_TOKEN with a GitHub token if there are private repositories",
+        path
+    );
+    Ok(())
+}
+
+/// Compare the crates of the checkout of `repo` at `path` with those of `snapshot`, reporting the
+/// new dependencies between repositories and cycles across them
+fn hook(fetcher: &Fetcher, repo: &str, path: &Path, snapshot: &Path) -> Result<(), Failure> {
+    let json = std::fs::read(snapshot).with_context(|| format!("Failed to read {:?}", snapshot))?;
+    let old = import(&json).with_context(|| format!("Failed to load {:?}", snapshot))?;
+    if !old.contains_key(repo) {
+        tracing::warn!(repo, "The repository is not in the snapshot");
+    }
+    let cfg = Config {
+        directories: BTreeMap::from([(repo.to_string(), path.to_path_buf())]),
+        ..Config::default()
+    };
+    let mut new = old.clone();
+    new.extend(fetcher.fetch_all(&cfg)?);
+    check_unique_names(&new).context("Failed to sanity-check the computed information")?;
+    match report_new_dependencies(&old, &new) {
+        true => Err(Failure::Lint),
+        false => Ok(()),
+    }
+}
+
+/// Report the dependencies between repositories and the cycles across them that `new` has but
+/// `old` does not, returning whether there are any
+fn report_new_dependencies(
+    old: &BTreeMap<String, Vec<CrateInfo>>,
+    new: &BTreeMap<String, Vec<CrateInfo>>,
+) -> bool {
+    let cross_repo = |infos| {
+        internal_edges(infos)
+            .into_iter()
+            .filter(|(repo, _, dep_repo, _)| repo != dep_repo)
+            .collect::<BTreeSet<_>>()
+    };
+    let old_edges = cross_repo(old);
+    let new_edges = cross_repo(new);
+    let edges = new_edges.difference(&old_edges).collect::<Vec<_>>();
+    let old_cycles = find_cycles(old).into_iter().collect::<BTreeSet<_>>();
+    let cycles = find_cycles(new)
+        .into_iter()
+        .filter(|c| !old_cycles.contains(c))
+        .collect::<Vec<_>>();
+
+    for (repo, krate, dep_repo, dep) in edges.iter() {
+        tracing::error!(
+            repo,
+            krate,
+            dependency = dep,
+            dependency_repo = dep_repo,
+            "New dependency between repositories: {} depends on {}",
+            styled_crate(repo, krate),
+            styled_crate(dep_repo, dep)
+        );
+    }
+    for c in cycles.iter() {
+        let path = c.iter().map(|(_, krate)| krate as &str).collect::<Vec<_>>();
+        let c = c
+            .iter()
+            .map(|(repo, krate)| styled_crate(repo, krate))
+            .collect::<Vec<_>>();
+        tracing::error!(
+            path = %path.join(" -> "),
+            "New cyclic dependency across repositories: {}",
+            c.join(" ")
+        );
+    }
+    !cycles.is_empty() || !edges.is_empty()
+}
+
+fn run(opt: Opt) -> Result<(), Failure> {
+    if let Command::Trends { dir } = &opt.command {
+        let snapshots = Snapshot::load_all(dir).context("Failed to load the snapshots")?;
+        cli::print_trends(&snapshots);
+        return Ok(());
+    }
+
+    if let Command::Completions { shell } = opt.command {
+        // Completion scripts are still useful without a configuration, just less complete
+        let repos = load_config(&opt.config)
+            .map(|cfg| cfg.repos().map(String::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+        print!("{}", completions(shell, &repos));
+        return Ok(());
+    }
+
+    if let Command::Init {
+        github_org,
+        github_token,
+        force,
+    } = &opt.command
+    {
+        return init(&opt.config, github_org, github_token.as_deref(), *force);
+    }
+
+    if let Command::CheckConfig = &opt.command {
+        return check_config(&opt);
+    }
+
+    if opt.dry_run {
+        return dry_run(&opt);
+    }
+
+    let fetcher = Fetcher::new(FetchOptions {
+        progress: opt.quiet == 0,
+        jobs: opt.jobs,
+        cache_dir: opt.cache_dir.clone(),
+        cache_mode: match (opt.offline, opt.frozen) {
+            (_, true) => CacheMode::Frozen,
+            (true, false) => CacheMode::Offline,
+            (false, false) => CacheMode::Revalidate,
+        },
+        keep_going: opt.keep_going,
+        lenient: opt.lenient,
+        keep_temp: opt.keep_temp,
+        measure_size: opt.measure_size,
+        skip_optional_deps: opt.no_optional_deps,
+        // Only the diffs of the requirements edit the manifests
+        keep_manifests: matches!(opt.command, Command::OutdatedRequirements { diff: true }),
+    })?;
+
+    if let Command::Serve { listen, refresh } = &opt.command {
+        return serve(&opt, &fetcher, listen, **refresh);
+    }
+
+    if let Command::Hook {
+        repo,
+        path,
+        snapshot,
+    } = &opt.command
+    {
+        return hook(&fetcher, repo, path, snapshot);
+    }
+
+    if let Command::Diff {
+        old,
+        new: Some(new),
+    } = &opt.command
+    {
+        let old_infos = opt.load_state(&fetcher, old)?;
+        let new_infos = opt.load_state(&fetcher, new)?;
+        cli::print_diff(&old_infos, &new_infos);
+        return Ok(());
+    }
+
+    let interval = match opt.watch {
+        Some(interval) => interval,
+        None => {
+            let (cfg, infos) = match &opt.from_model {
+                Some(path) => opt.load_model(path)?,
+                None => {
+                    let cfg = opt.load_main_config()?;
+                    let infos = fetcher.fetch_all(&cfg)?;
+                    (cfg, infos)
+                }
+            };
+            return analyze(&opt, &cfg, &fetcher, &infos);
+        }
+    };
+
+    // Only analyze again when the configuration or the crates changed, comparing them through
+    // a hash of their JSON export, so as not to keep a copy of the previous crates around
+    let mut last = None;
+    loop {
+        let res = opt.load_main_config().and_then(|cfg| {
+            fetcher.reset_stats();
+            let infos = fetcher.fetch_all(&cfg)?;
+            let mut hash = sha2::Sha256::new();
+            serde_json::to_writer(&mut hash, &export(&infos, &cfg.ownership))
+                .context("Failed to hash the crates")?;
+            let current = (
+                cfg.repos().map(String::from).collect::<Vec<_>>(),
+                hash.finalize(),
+            );
+            if last.as_ref() == Some(&current) {
+                tracing::info!("Nothing changed");
+                return Ok(());
+            }
+            last = Some(current);
+            analyze(&opt, &cfg, &fetcher, &infos)
+        });
+        if let Err(failure) = res {
+            log_failure(&failure);
+        }
+        std::thread::sleep(*interval);
+    }
+}
+
+#[derive(serde::Serialize)]
+struct RunSummary<'a> {
+    /// Number of crates found in each repository
+    crates: BTreeMap<&'a str, usize>,
+    /// Number of dependencies between internal crates
+    edges: usize,
+    /// Number of cyclic dependencies across repositories
+    cycles: usize,
+    /// Cyclic dependencies across repositories, each as its crates separated by " -> ", eg.
+    /// "core/core-net -> app/app-proto"
+    cyclic_dependencies: Vec<String>,
+    /// Number of findings of each lint, if the lints ran
+    lints: Option<BTreeMap<&'static str, usize>>,
+    /// Findings of each lint, without terminal styling, if the lints ran
+    lint_findings: Option<BTreeMap<&'static str, &'a [String]>>,
+    /// Whether all the lints passed, if they ran
+    lints_passed: Option<bool>,
+    /// Time it took to fetch all the repositories, in seconds
+    fetch_seconds: f64,
+    /// Time it took to fetch each repository, in seconds
+    repo_fetch_seconds: BTreeMap<&'a str, f64>,
+    /// Time it took to run the command once the repositories were fetched, in seconds
+    command_seconds: f64,
+    /// Number of repositories whose cached archive was used
+    cache_hits: usize,
+    /// Repositories skipped with `--keep-going`, with the reason why
+    failed_repos: &'a BTreeMap<String, String>,
+}
+
+/// Part of the summary of an earlier run that new lint findings and cycles are found against
+///
+/// Summaries written before they listed the cycles and the findings only have their numbers.
+#[derive(serde::Deserialize)]
+struct Baseline {
+    cycles: usize,
+    #[serde(default)]
+    cyclic_dependencies: Option<BTreeSet<String>>,
+    #[serde(default)]
+    lints: Option<BTreeMap<String, usize>>,
+    #[serde(default)]
+    lint_findings: Option<BTreeMap<String, BTreeSet<String>>>,
+}
+
+fn load_baseline(path: &Path) -> anyhow::Result<Baseline> {
+    let baseline =
+        std::fs::read(path).with_context(|| format!("Failed to read the baseline {:?}", path))?;
+    serde_json::from_slice(&baseline)
+        .with_context(|| format!("Failed to parse the baseline {:?}", path))
+}
+
+/// Findings of the lints that `baseline` does not have, or cycles if the lints did not run,
+/// counting everything as new without a baseline
+///
+/// Against a baseline that only has the numbers of findings and cycles, all of them are new if
+/// there are more of them than in the baseline.
+fn regressions<'a>(
+    summary: &'a RunSummary,
+    baseline: Option<&Baseline>,
+) -> Vec<(String, Vec<&'a str>)> {
+    let new = |current: &'a [String], known: Option<&BTreeSet<String>>, count: Option<usize>| match (
+        known, count,
+    ) {
+        (Some(known), _) => current
+            .iter()
+            .filter(|c| !known.contains(*c))
+            .map(|c| c as &str)
+            .collect(),
+        (None, Some(count)) if current.len() <= count => Vec::new(),
+        _ => current.iter().map(|c| c as &str).collect::<Vec<_>>(),
+    };
+    let mut res = Vec::new();
+    let findings = match &summary.lint_findings {
+        Some(findings) => findings,
+        None => {
+            let cycles = new(
+                &summary.cyclic_dependencies,
+                baseline.and_then(|b| b.cyclic_dependencies.as_ref()),
+                baseline.map(|b| b.cycles),
+            );
+            if !cycles.is_empty() {
+                res.push((String::from("cycles"), cycles));
+            }
+            return res;
+        }
+    };
+    // Lints that did not run in the baseline had no findings then
+    for (id, current) in findings {
+        let issues = new(
+            current,
+            baseline.and_then(|b| b.lint_findings.as_ref()?.get(*id)),
+            baseline.map(|b| {
+                b.lints
+                    .as_ref()
+                    .and_then(|l| l.get(*id).copied())
+                    .unwrap_or(0)
+            }),
+        );
+        if !issues.is_empty() {
+            res.push((format!("lint {}", id), issues));
+        }
+    }
+    res
+}
+
+/// Post the summary of the run to `notify.webhook` if there are new lint findings or cycles
+fn notify(
+    cfg: &Config,
+    webhook: &str,
+    summary: &RunSummary,
+    baseline: Option<&Baseline>,
+) -> anyhow::Result<()> {
+    let regressions = regressions(summary, baseline);
+    if regressions.is_empty() {
+        return Ok(());
+    }
+    let mut text = String::from("muregraph found new lint findings or cycles:");
+    for (what, issues) in regressions {
+        text += &format!("\n• {} ({} new):", what, issues.len());
+        for issue in issues {
+            text += &format!("\n    ◦ {}", issue);
+        }
+    }
+    let summary = serde_json::to_string(summary).context("Failed to serialize the summary")?;
+    let payload = cfg.notify.payload(&text, &summary)?;
+    let response = cfg
+        .network
+        .client()?
+        .post(webhook)
+        .header("Content-Type", "application/json")
+        .body(payload)
+        .send()
+        .with_context(|| {
+            format!(
+                "Failed to send POST request to URL {:?}",
+                redact_url(webhook)
+            )
+        })?;
+    ensure!(
+        response.status().is_success(),
+        "POST request to {:?} was unsuccessful: {}",
+        redact_url(webhook),
+        response.status()
+    );
     Ok(())
 }
+
+fn run_summary<'a>(
+    infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+    lint_results: Option<&'a [LintResult]>,
+    fetch: &'a FetchStats,
+    command: std::time::Duration,
+) -> RunSummary<'a> {
+    let cycles = find_cycles(infos);
+    RunSummary {
+        crates: infos.iter().map(|(r, c)| (r as &str, c.len())).collect(),
+        edges: internal_edges(infos).len(),
+        cycles: cycles.len(),
+        cyclic_dependencies: cycles
+            .iter()
+            .map(|c| {
+                c.iter()
+                    .map(|(repo, krate)| format!("{}/{}", repo, krate))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            })
+            .collect(),
+        lints: lint_results.map(|r| r.iter().map(|l| (l.id, l.findings)).collect()),
+        lint_findings: lint_results.map(|r| r.iter().map(|l| (l.id, &l.issues[..])).collect()),
+        lints_passed: lint_results.map(|r| r.iter().all(LintResult::passed)),
+        fetch_seconds: fetch.total.as_secs_f64(),
+        repo_fetch_seconds: fetch
+            .repos
+            .iter()
+            .map(|(r, t)| (r as &str, t.total.as_secs_f64()))
+            .collect(),
+        command_seconds: command.as_secs_f64(),
+        cache_hits: fetch.cache_hits,
+        failed_repos: &fetch.failed,
+    }
+}
+
+fn write_summary(path: &Path, summary: &RunSummary) -> anyhow::Result<()> {
+    let mut json = serde_json::to_vec_pretty(summary).context("Failed to serialize the summary")?;
+    json.push(b'\n');
+    std::fs::write(path, json).with_context(|| format!("Failed to write the summary to {:?}", path))
+}
+
+/// Print how long fetching each repository took, by phase, along with the time spent fetching
+/// them all and running `command` afterwards
+fn print_timings(command: &Command, fetch: &FetchStats, command_time: std::time::Duration) {
+    let secs = |d: std::time::Duration| format!("{:.3}s", d.as_secs_f64());
+    eprintln!(
+        "{:<30} {:>10} {:>10} {:>10} {:>10}",
+        "repository", "download", "decompress", "parse", "total"
+    );
+    for (repo, t) in fetch.repos.iter() {
+        eprintln!(
+            "{:<30} {:>10} {:>10} {:>10} {:>10}",
+            repo,
+            secs(t.download),
+            secs(t.decompress),
+            secs(t.parse),
+            secs(t.total)
+        );
+    }
+    eprintln!(
+        "{:<30} {:>43}",
+        "fetching (concurrently)",
+        secs(fetch.total)
+    );
+    let phase = match command {
+        Command::Lint => "lint",
+        Command::Graph { .. } => "render",
+        _ => "analysis",
+    };
+    eprintln!("{:<30} {:>43}", phase, secs(command_time));
+}
+
+/// Run the command on the fetched crates, and write the summary if requested
+fn analyze(
+    opt: &Opt,
+    cfg: &Config,
+    fetcher: &Fetcher,
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+) -> Result<(), Failure> {
+    check_unique_names(infos).context("Failed to sanity-check the computed information")?;
+
+    if let Some(dir) = &opt.save_snapshot {
+        Snapshot::new(infos)
+            .save(dir)
+            .context("Failed to save the snapshot")?;
+    }
+
+    if let Some(path) = &opt.save_model {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to write the model to {:?}", path))?;
+        serde_json::to_writer(
+            std::io::BufWriter::new(file),
+            &export(infos, &cfg.ownership),
+        )
+        .with_context(|| format!("Failed to write the model to {:?}", path))?;
+    }
+
+    // Before the summary overwrites it, as both can be the same file
+    let baseline = match (&cfg.notify.webhook, &opt.baseline) {
+        (Some(_), Some(path)) => Some(load_baseline(path)?),
+        _ => None,
+    };
+
+    let start = std::time::Instant::now();
+    let mut lint_results = None;
+    let res = run_command(opt, cfg, fetcher, infos, &mut lint_results);
+    if opt.timings {
+        print_timings(&opt.command, &fetcher.stats(), start.elapsed());
+    }
+    if opt.summary.is_some() || cfg.notify.webhook.is_some() {
+        let stats = fetcher.stats();
+        let summary = run_summary(infos, lint_results.as_deref(), &stats, start.elapsed());
+        if let Some(path) = &opt.summary {
+            write_summary(path, &summary)?;
+        }
+        if let Some(webhook) = &cfg.notify.webhook {
+            notify(cfg, webhook, &summary, baseline.as_ref())
+                .context("Failed to notify the webhook")?;
+        }
+    }
+    res?;
+    match fetcher.stats().failed.is_empty() {
+        true => Ok(()),
+        false => Err(Failure::Partial),
+    }
+}
+
+fn run_command(
+    opt: &Opt,
+    cfg: &Config,
+    fetcher: &Fetcher,
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    lint_results: &mut Option<Vec<LintResult>>,
+) -> Result<(), Failure> {
+    match &opt.command {
+        Command::Graph {
+            use_colors,
+            condense,
+            collapse_groups,
+            by_owner,
+            by_prefix,
+            highlight,
+            edges,
+            external_deps,
+            only_dev_deps,
+            only_build_deps,
+            max_nodes,
+            engine,
+            graphviz_arg,
+            output,
+            format,
+            emit,
+        } => {
+            let graph_type = match (*use_colors, *collapse_groups) {
+                (true, _) => GraphType::Colors,
+                (false, true) => GraphType::Groups,
+                (false, false) => GraphType::Cluster,
+            };
+            // Each format along with the file it is written to, if not to the standard output
+            let outputs = match (format, output) {
+                (_, Some(dir)) if !emit.is_empty() => {
+                    emit.iter()
+                        .map(|f| (*f, Some(dir.join(format!("graph.{}", f.extension())))))
+                        .collect()
+                }
+                (Some(format), _) => vec![(*format, output.clone())],
+                (None, Some(output)) => vec![(
+                    Format::from_path(output)
+                        .with_context(|| {
+                            format!(
+                                "Cannot guess the format of {:?} from its extension, please set --format",
+                                output
+                            )
+                        })
+                        .map_err(Failure::Config)?,
+                    Some(output.clone()),
+                )],
+                (None, None) => vec![(Format::Dot, None)],
+            };
+            // The report lists the findings of the lints on the repositories, before they are
+            // regrouped by owner or prefix
+            let lints = match outputs.iter().any(|(f, _)| *f == Format::HtmlReport) {
+                true => lint_report(
+                    infos,
+                    &fetcher.stats().invalid_manifests,
+                    &cfg.lints,
+                    &cfg.policy,
+                    &cfg.ownership,
+                ),
+                false => Vec::new(),
+            };
+            if let Some(prefix) = by_prefix.iter().find(|p| {
+                let p = p.trim_end_matches('*');
+                !infos.values().flatten().any(|c| c.name.starts_with(p))
+            }) {
+                return Err(Failure::Other(anyhow::anyhow!(
+                    "No crate matches --by-prefix {:?}",
+                    prefix
+                )));
+            }
+            // Owners then stand for repositories, which have no group nor owner, while prefixes
+            // stand for repositories along with the crates that have none
+            let (regrouped, no_ownership): (BTreeMap<String, Vec<CrateInfo>>, Ownership);
+            let (infos, ownership) = match (*by_owner, by_prefix.is_empty()) {
+                (true, _) => {
+                    regrouped = crates_by_owner(infos);
+                    no_ownership = Ownership {
+                        deprecated: cfg.ownership.deprecated.clone(),
+                        ..Ownership::default()
+                    };
+                    (&regrouped, &no_ownership)
+                }
+                (false, false) => {
+                    regrouped = crates_by_prefix(infos, by_prefix);
+                    (&regrouped, &cfg.ownership)
+                }
+                (false, true) => (infos, &cfg.ownership),
+            };
+            if let Some(pattern) = highlight
+                .iter()
+                .find(|p| !infos.values().flatten().any(|c| glob_match(p, &c.name)))
+            {
+                return Err(Failure::Other(anyhow::anyhow!(
+                    "No crate matches --highlight {:?}",
+                    pattern
+                )));
+            }
+            let options = RenderOptions {
+                graph_type,
+                condense: *condense,
+                highlight: highlight.clone(),
+                edges: *edges,
+                only_kind: match (*only_dev_deps, *only_build_deps) {
+                    (true, _) => Some(DepKind::Dev),
+                    (false, true) => Some(DepKind::Build),
+                    (false, false) => None,
+                },
+                external: *external_deps,
+                max_nodes: *max_nodes,
+                graphviz: GraphvizConfig {
+                    engine: engine.clone().or_else(|| cfg.graphviz.engine.clone()),
+                    args: cfg
+                        .graphviz
+                        .args
+                        .iter()
+                        .chain(graphviz_arg)
+                        .cloned()
+                        .collect(),
+                },
+                lints,
+            };
+            // Rather than failing after all the downloads, write DOT files in place of the SVG ones
+            // when graphviz is missing, as they can still be rendered elsewhere
+            let outputs = match outputs.iter().any(|(f, _)| *f == Format::Svg)
+                && !options.graphviz.installed()
+            {
+                true => svg_fallback(outputs, options.graphviz.engine()),
+                false => outputs,
+            };
+            // Before writing any file, so that a format not supporting the options does not leave
+            // the others half-written
+            for (format, _) in outputs.iter() {
+                check_render_options(*format, &options)
+                    .context("Failed to render the dependency graph")?;
+            }
+            if let (Some(dir), false) = (output, emit.is_empty()) {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create the directory {:?}", dir))?;
+            }
+            for (format, output) in outputs {
+                // Write the graph as it is rendered, rather than holding it in memory first
+                let mut out: Box<dyn Write> = match &output {
+                    Some(output) => Box::new(std::io::BufWriter::new(
+                        std::fs::File::create(output).with_context(|| {
+                            format!("Failed to write the graph to {:?}", output)
+                        })?,
+                    )),
+                    None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+                };
+                let res = render_to(format, &options, infos, ownership, &mut out)
+                    .context("Failed to render the dependency graph")
+                    .and_then(|()| out.flush().context("Failed to output the dependency graph"));
+                if let (Err(_), Some(output)) = (&res, &output) {
+                    // Rather than leaving a truncated file behind
+                    drop(out);
+                    std::fs::remove_file(output).ok();
+                }
+                res?;
+            }
+        }
+        Command::Lint => {
+            let results = lint_results.insert(
+                sanity_check(
+                    infos,
+                    &fetcher.stats().invalid_manifests,
+                    &cfg.lints,
+                    &cfg.policy,
+                    &cfg.ownership,
+                )
+                .context("Failed to sanity-check the computed information")?,
+            );
+            cli::print_lint_summary(results);
+            if !results.iter().all(LintResult::passed) {
+                return Err(Failure::Lint);
+            }
+        }
+        Command::Tui => tui::run(infos).context("Failed to run the terminal interface")?,
+        Command::Badge { metric, output } => {
+            let svg = badge(*metric, infos);
+            match output {
+                Some(output) => std::fs::write(output, svg)
+                    .with_context(|| format!("Failed to write the badge to {:?}", output))?,
+                None => print!("{}", svg),
+            }
+        }
+        Command::Backstage {
+            per,
+            owner,
+            lifecycle,
+            output,
+        } => {
+            let catalog = backstage_catalog(infos, *per, owner, lifecycle);
+            match output {
+                Some(output) => std::fs::write(output, catalog).with_context(|| {
+                    format!("Failed to write the Backstage entities to {:?}", output)
+                })?,
+                None => print!("{}", catalog),
+            }
+        }
+        Command::Export => {
+            serde_json::to_writer_pretty(std::io::stdout(), &export(infos, &cfg.ownership))
+                .context("Failed to output the crates")?;
+            println!();
+        }
+        Command::Site { output } => {
+            let options = RenderOptions {
+                graphviz: cfg.graphviz.clone(),
+                lints: lint_report(
+                    infos,
+                    &fetcher.stats().invalid_manifests,
+                    &cfg.lints,
+                    &cfg.policy,
+                    &cfg.ownership,
+                ),
+                ..RenderOptions::default()
+            };
+            let pages = site_pages(infos, &cfg.ownership, &options)
+                .context("Failed to generate the site")?;
+            for (page, contents) in pages {
+                let path = output.join(page);
+                path.parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|()| std::fs::write(&path, contents))
+                    .with_context(|| format!("Failed to write the site to {:?}", path))?;
+            }
+        }
+        Command::ReleaseConfig { tool, output } => {
+            let configs =
+                release_configs(infos, *tool).context("Failed to compute the publish order")?;
+            for (file, contents) in configs {
+                let path = output.join(file);
+                path.parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|()| std::fs::write(&path, contents))
+                    .with_context(|| {
+                        format!("Failed to write the release configuration to {:?}", path)
+                    })?;
+            }
+        }
+        Command::PublishOrder => {
+            let groups = publish_order(&DependencyGraph::new(infos))
+                .context("Failed to compute the publish order")?;
+            for (i, group) in groups.iter().enumerate() {
+                println!("# Group {}", i + 1);
+                for (_, c) in group {
+                    println!("{}", c.name);
+                }
+            }
+        }
+        Command::Impact { krate } => {
+            let affected = impact(krate, &DependencyGraph::new(infos))
+                .context("Failed to compute the impact")?;
+            for (repo, crates) in affected {
+                println!("{}:", repo);
+                for c in crates {
+                    println!("  {}", c);
+                }
+            }
+        }
+        Command::Diff { old, new: None } => {
+            let old_infos = opt.load_state(fetcher, old)?;
+            cli::print_diff(&old_infos, infos);
+        }
+        Command::PrComment {
+            github_repo,
+            pr,
+            github_token,
+            against,
+        } => {
+            // Before fetching the old repositories, whose invalid manifests would be linted too
+            let results = lint_results.insert(
+                sanity_check(
+                    infos,
+                    &fetcher.stats().invalid_manifests,
+                    &cfg.lints,
+                    &cfg.policy,
+                    &cfg.ownership,
+                )
+                .context("Failed to sanity-check the computed information")?,
+            );
+            cli::print_lint_summary(results);
+            let old_infos = match against {
+                Some(against) => {
+                    let old_cfg = opt.load_config(against)?;
+                    Some(fetcher.fetch_all(&old_cfg)?)
+                }
+                None => None,
+            };
+            let body = pr_comment_body(results, old_infos.as_ref(), infos);
+            let client = cfg.network.client()?;
+            upsert_pr_comment(&client, github_token, github_repo, *pr, &body)
+                .context("Failed to comment on the pull request")?;
+            if !results.iter().all(LintResult::passed) {
+                return Err(Failure::Lint);
+            }
+        }
+        Command::CriticalPath => {
+            let (path, slack) = critical_path(infos);
+            println!("Critical path ({} crates):", path.len());
+            for c in path {
+                println!("  {}", c);
+            }
+            let mut slack = slack.into_iter().collect::<Vec<_>>();
+            slack.sort_by_key(|(c, s)| (*s, *c));
+            println!();
+            println!("{:<30} {:>5}", "crate", "slack");
+            for (c, s) in slack {
+                println!("{:<30} {:>5}", c, s);
+            }
+        }
+        Command::LongestChains { count } => {
+            for (i, chain) in longest_chains(infos, *count).into_iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("Chain {} ({} crates):", i + 1, chain.len());
+                for (repo, c) in chain {
+                    println!("  {} [{}]", c, repo);
+                }
+            }
+        }
+        Command::Check {
+            against,
+            update_baseline: true,
+        } => {
+            let file = std::fs::File::create(against)
+                .with_context(|| format!("Failed to write the baseline to {:?}", against))?;
+            serde_json::to_writer_pretty(
+                std::io::BufWriter::new(file),
+                &export(infos, &cfg.ownership),
+            )
+            .with_context(|| format!("Failed to write the baseline to {:?}", against))?;
+            tracing::info!(baseline = ?against, "Updated the baseline");
+        }
+        Command::Check {
+            against,
+            update_baseline: false,
+        } => {
+            let json =
+                std::fs::read(against).with_context(|| format!("Failed to read {:?}", against))?;
+            let old = import(&json).with_context(|| format!("Failed to load {:?}", against))?;
+            if report_new_dependencies(&old, infos) {
+                return Err(Failure::Lint);
+            }
+        }
+        Command::Coupling {
+            threshold,
+            by_owner,
+        } => {
+            let by_owner = by_owner.then(|| crates_by_owner(infos));
+            println!(
+                "{:<20} {:<20} {:>6} {:>6} {:>6}  suggestion",
+                match by_owner {
+                    Some(_) => "owner",
+                    None => "repository",
+                },
+                "other",
+                "->",
+                "<-",
+                "score"
+            );
+            for c in coupling(by_owner.as_ref().unwrap_or(infos)) {
+                let suggestion = match (c.forward > 0 && c.backward > 0, c.score >= *threshold) {
+                    (true, true) => "effectively one codebase, merge",
+                    (true, false) => "mutually dependent, consider merging",
+                    (false, _) => "",
+                };
+                let line = format!(
+                    "{:<20} {:<20} {:>6} {:>6} {:>6.2}  {}",
+                    c.repos.0, c.repos.1, c.forward, c.backward, c.score, suggestion
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+        Command::Split { repo } => {
+            let groups = split(repo, infos).context("Failed to split the repository")?;
+            let group_of = |name: &str| groups.iter().position(|g| g.iter().any(|c| c == name));
+            for (i, group) in groups.iter().enumerate() {
+                println!("Group {} ({} crates):", i + 1, group.len());
+                for c in group {
+                    println!("  {}", c);
+                }
+            }
+            let crossing = internal_edges(infos)
+                .into_iter()
+                .filter(|(r, c, dr, d)| *r == repo && *dr == repo && group_of(c) != group_of(d))
+                .collect::<Vec<_>>();
+            println!("Dependencies crossing groups ({}):", crossing.len());
+            for (_, c, _, d) in crossing {
+                println!("  {} -> {}", c, d);
+            }
+        }
+        Command::BumpPlan { changed, against } => {
+            let mut changed = changed.iter().map(|c| c as &str).collect::<Vec<_>>();
+            if let Some(against) = against {
+                let old_cfg = opt.load_config(against)?;
+                let old_infos = fetcher.fetch_all(&old_cfg)?;
+                changed.extend(changed_crates(&old_infos, infos));
+            }
+            let plan = bump_plan(&changed, infos).context("Failed to plan the version bumps")?;
+            println!("Crates to bump and publish, in order:");
+            for (i, (repo, c, reason)) in plan.to_publish.iter().enumerate() {
+                println!(
+                    "  {}. {} {} [{}] ({})",
+                    i + 1,
+                    c.name,
+                    c.version,
+                    repo,
+                    reason
+                );
+            }
+            println!("Requirements to update:");
+            for (repo, c, d) in plan.to_update {
+                println!("  {} [{}]: {} = {:?}", c.name, repo, d.name, d.req);
+            }
+        }
+        Command::OutdatedRequirements { diff } => {
+            let mut outdated = BTreeMap::<&str, Vec<_>>::new();
+            for r in outdated_requirements(infos) {
+                outdated.entry(r.repo).or_default().push(r);
+            }
+            for (repo, requirements) in outdated {
+                if *diff {
+                    // A patch per repository, to apply at its root
+                    println!("# Repository {}", repo);
+                    let mut by_crate = BTreeMap::<&str, (&CrateInfo, Vec<_>)>::new();
+                    for r in requirements.iter() {
+                        by_crate
+                            .entry(&r.krate.name)
+                            .or_insert_with(|| (r.krate, Vec::new()))
+                            .1
+                            .push((r.dep, &r.updated as &str));
+                    }
+                    for (krate, updates) in by_crate.values() {
+                        let manifest = match fetcher.manifest(&krate.name) {
+                            Some(manifest) => manifest,
+                            None => {
+                                tracing::warn!(
+                                    repo,
+                                    krate = %krate.name,
+                                    "Skipping the diff: the manifest was not fetched"
+                                );
+                                continue;
+                            }
+                        };
+                        match requirements_diff(krate, &manifest, updates) {
+                            Ok(diff) => print!("{}", diff),
+                            Err(e) => tracing::warn!(repo, "Skipping the diff: {:#}", e),
+                        }
+                    }
+                    continue;
+                }
+                println!("{}:", repo);
+                for r in requirements {
+                    println!(
+                        "  {} [{}]: {} = {:?} -> {:?} ({} is at {})",
+                        r.krate.name,
+                        r.dep.kind.section(),
+                        r.dep.key(),
+                        r.dep.req,
+                        r.updated,
+                        r.dep.name,
+                        r.version
+                    );
+                }
+            }
+        }
+        Command::UnifyRequirements => {
+            for (i, u) in unify_requirements(infos).into_iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                let classes = u
+                    .classes
+                    .iter()
+                    .map(|(class, count)| format!("{} ({})", class, count))
+                    .collect::<Vec<_>>();
+                println!(
+                    "{} [{}] {} is required as {}, unify on {} with {} changes:",
+                    u.krate.name,
+                    u.repo,
+                    u.krate.version,
+                    classes.join(", "),
+                    u.target,
+                    u.changes.len()
+                );
+                for (repo, c, d, updated) in u.changes {
+                    println!(
+                        "  {} [{}], [{}]: {} = {:?} -> {:?}",
+                        c.name,
+                        repo,
+                        d.kind.section(),
+                        d.name,
+                        d.req,
+                        updated
+                    );
+                }
+            }
+        }
+        Command::ReleasePlan { changed } => {
+            let changed = changed.iter().map(|c| c as &str).collect::<Vec<_>>();
+            let plan = bump_plan(&changed, infos).context("Failed to plan the version bumps")?;
+            print!("{}", release_checklist(&plan));
+        }
+        Command::Query { query: q, json } => {
+            let crates = query(q, infos).context("Failed to evaluate the query")?;
+            if *json {
+                let crates = crates
+                    .iter()
+                    .map(|(repo, c)| serde_json::json!({ "name": c.name, "repo": repo }))
+                    .collect::<Vec<_>>();
+                serde_json::to_writer_pretty(std::io::stdout(), &crates)
+                    .context("Failed to output the query results")?;
+                println!();
+            } else {
+                for (_, c) in crates {
+                    println!("{}", c.name);
+                }
+            }
+        }
+        Command::CheckUnreachable { from, to } => {
+            let from = select(from, infos).map_err(|e| Failure::Config(e.into_inner()))?;
+            let to = select(to, infos).map_err(|e| Failure::Config(e.into_inner()))?;
+            let paths = paths_between(&from, &to, infos);
+            for p in paths.iter() {
+                let (from_repo, from) = p[0];
+                let path = p.iter().map(|(_, c)| &c.name as &str).collect::<Vec<_>>();
+                let styled = p
+                    .iter()
+                    .map(|(repo, c)| styled_crate(repo, &c.name))
+                    .collect::<Vec<_>>();
+                tracing::error!(
+                    repo = from_repo,
+                    krate = %from.name,
+                    path = %path.join(" -> "),
+                    "Forbidden dependency path: {}",
+                    styled.join(" ")
+                );
+            }
+            if !paths.is_empty() {
+                return Err(Failure::Lint);
+            }
+        }
+        Command::Parallelism => {
+            let waves = build_waves(infos);
+            for (i, wave) in waves.iter().enumerate() {
+                println!(
+                    "Wave {} ({} crates): {}",
+                    i + 1,
+                    wave.len(),
+                    wave.join(", ")
+                );
+            }
+            println!("Sequential waves: {}", waves.len());
+            println!(
+                "Maximum parallelism: {} crates",
+                waves.iter().map(|w| w.len()).max().unwrap_or(0)
+            );
+        }
+        Command::Trends { .. }
+        | Command::Completions { .. }
+        | Command::Serve { .. }
+        | Command::Hook { .. }
+        | Command::Diff { new: Some(_), .. }
+        | Command::Init { .. }
+        | Command::CheckConfig => unreachable!("these subcommands are handled separately"),
+        Command::Dominators { from } => {
+            let graph = DependencyGraph::new(infos);
+            let kinds = [DepKind::Normal, DepKind::Dev, DepKind::Build];
+            let roots = match from {
+                Some(from) => select(from, infos)
+                    .map_err(|e| Failure::Config(e.into_inner()))?
+                    .into_iter()
+                    .map(|(_, c)| &c.name as &str)
+                    .collect::<Vec<_>>(),
+                None => {
+                    let rdeps = reverse_adjacency(&adjacency(&graph, &kinds));
+                    rdeps
+                        .iter()
+                        .filter(|(_, r)| r.is_empty())
+                        .map(|(c, _)| *c)
+                        .collect()
+                }
+            };
+            let idom = dominators(&graph, &roots, &kinds);
+            let mut dominated = BTreeMap::<&str, Vec<&str>>::new();
+            for c in idom.keys() {
+                let mut d = idom[c];
+                while let Some(dom) = d {
+                    dominated.entry(dom).or_default().push(c);
+                    d = idom[dom];
+                }
+            }
+            let mut dominated = dominated.into_iter().collect::<Vec<_>>();
+            dominated.sort_by_key(|(c, d)| (std::cmp::Reverse(d.len()), *c));
+            for (c, d) in dominated {
+                println!("{} dominates {} crates: {}", c, d.len(), d.join(", "));
+            }
+        }
+        Command::Metrics { json } => {
+            let metrics = metrics(infos).context("Failed to compute the metrics")?;
+            if *json {
+                serde_json::to_writer_pretty(std::io::stdout(), &metrics)
+                    .context("Failed to output the metrics")?;
+                println!();
+            } else {
+                cli::print_metrics(&metrics);
+            }
+        }
+        Command::TopDependents { count, json } => {
+            let metrics = metrics(infos).context("Failed to compute the metrics")?;
+            let top = top_dependents(&metrics, *count);
+            if *json {
+                serde_json::to_writer_pretty(std::io::stdout(), &top)
+                    .context("Failed to output the most depended-on crates")?;
+                println!();
+            } else {
+                cli::print_top_dependents(&top);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn init_logging(opt: &Opt) {
+    let levels = [
+        tracing_subscriber::filter::LevelFilter::OFF,
+        tracing_subscriber::filter::LevelFilter::ERROR,
+        tracing_subscriber::filter::LevelFilter::WARN,
+        tracing_subscriber::filter::LevelFilter::INFO,
+        tracing_subscriber::filter::LevelFilter::DEBUG,
+        tracing_subscriber::filter::LevelFilter::TRACE,
+    ];
+    let level = (2 + opt.verbose as usize)
+        .saturating_sub(opt.quiet as usize)
+        .min(levels.len() - 1);
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(levels[level])
+        .with_target(false)
+        .with_writer(std::io::stderr);
+    match opt.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+fn log_failure(failure: &Failure) {
+    match failure {
+        Failure::Lint => tracing::error!("Some lints reported issues, see error log above"),
+        Failure::Partial => {
+            tracing::error!("Some repositories were skipped, see error log above")
+        }
+        Failure::Config(e) | Failure::Fetch(e) | Failure::Other(e) => {
+            tracing::error!("{:?}", e)
+        }
+    }
+}
+
+/// Find the cargo workspace containing the current directory, returning its name and root
+fn locate_workspace() -> anyhow::Result<(String, PathBuf)> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let out = std::process::Command::new(cargo)
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .output()
+        .context("Failed to run cargo locate-project")?;
+    ensure!(
+        out.status.success(),
+        "cargo locate-project failed: {}",
+        String::from_utf8_lossy(&out.stderr).trim()
+    );
+    let manifest = PathBuf::from(String::from_utf8_lossy(&out.stdout).trim());
+    let root = manifest
+        .parent()
+        .context("Workspace manifest has no parent directory")?
+        .to_path_buf();
+    let name = root
+        .file_name()
+        .context("Workspace root has no name")?
+        .to_string_lossy()
+        .into_owned();
+    Ok((name, root))
+}
+
+fn main() {
+    // When run as `cargo muregraph`, cargo passes the subcommand name as first argument
+    let mut args = std::env::args_os().collect::<Vec<_>>();
+    let as_cargo_subcommand = args.get(1).is_some_and(|a| a == "muregraph");
+    if as_cargo_subcommand {
+        args.remove(1);
+    }
+    let mut opt = Opt::from_iter(args);
+    opt.read_env_lists();
+    init_logging(&opt);
+    let res = match as_cargo_subcommand {
+        true => locate_workspace()
+            .map_err(Failure::Config)
+            .and_then(|workspace| {
+                opt.workspace = Some(workspace);
+                run(opt)
+            }),
+        false => run(opt),
+    };
+    if let Err(failure) = res {
+        log_failure(&failure);
+        std::process::exit(failure.exit_code());
+    }
+}
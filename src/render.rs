@@ -0,0 +1,1239 @@
+//! Backends rendering the dependency graph, selected by the output [`Format`](crate::Format)
+//!
+//! The crates are first turned into a [`Graph`], which decides of the nodes, of the arrows and of
+//! how they are highlighted, so that each [`Renderer`] only has to write them in its syntax.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Write,
+};
+
+use anyhow::{ensure, Context};
+
+use crate::{
+    all_crates, export, find_cycles, glob_match, graph::DependencyGraph, internal_edges, metrics,
+    render_svg, strongly_connected_components, CrateInfo, CrateMetrics, DepKind, Dependency,
+    EdgeFilter, Error, Finding, GraphType, GraphvizConfig, LintReport, Metrics, Ownership, Publish,
+    RenderOptions, Result, TargetKind,
+};
+
+const COLORS: &[&str] = &[
+    "#e6194b", "#3cb44b", "#ffe119", "#4363d8", "#f58231", "#911eb4", "#46f0f0", "#f032e6",
+    "#bcf60c", "#fabebe", "#008080", "#e6beff", "#9a6324", "#fffac8", "#800000", "#aaffc3",
+    "#808000", "#ffd8b1", "#000075", "#808080", "#ffffff", "#000000",
+];
+
+/// Fill of the highlighted nodes, bright enough to stand out from the clusters
+const HIGHLIGHT: &str = "#ffff00";
+
+/// Backend writing a [`Graph`] in an output format
+pub trait Renderer {
+    /// Write the graph to `out`
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()>;
+}
+
+/// Node of the graph, standing for a crate, for a condensed cycle of crates or for the crates of
+/// a repository left out of a truncated graph
+#[derive(Debug)]
+pub struct Node<'a> {
+    /// Label of the node: the crate name, the names of the crates of the cycle, or the repository
+    /// of the crates left out
+    pub label: String,
+    /// Repositories the crates of the node belong to, sorted
+    pub repos: Vec<&'a str>,
+    /// Whether one of the crates of the node matches the patterns to highlight
+    pub highlighted: bool,
+    /// Number of crates left out that the node stands for, 0 if it is not a placeholder
+    pub hidden: usize,
+}
+
+/// Dependency drawn as an arrow, between crates, merging all the times it is declared
+#[derive(Debug)]
+pub struct Edge<'a> {
+    /// Dependent crate
+    pub from: &'a CrateInfo,
+    /// Name of the dependency, which may not be one of the crates of the graph
+    pub to: &'a str,
+    /// Whether one of the declarations is path-local rather than only from a registry
+    pub path: bool,
+    /// Sections the dependency is declared in, in the order of the manifest
+    pub kinds: Vec<DepKind>,
+    /// Number of times the dependency is declared, eg. as a dependency and a dev-dependency, or
+    /// in several target-specific tables
+    pub count: usize,
+    /// Whether all the declarations are optional, the dependency only being built with some
+    /// features
+    pub optional: bool,
+    /// Features enabling the dependency if it is optional, sorted
+    pub features: Vec<&'a str>,
+}
+
+impl Edge<'_> {
+    /// Label of an optional dependency, telling which features enable it, eg. "feature: postgres"
+    pub fn feature_label(&self) -> Option<String> {
+        match self.features.as_slice() {
+            _ if !self.optional => None,
+            [] => Some(String::from("optional")),
+            [feature] => Some(format!("feature: {}", feature)),
+            features => Some(format!("features: {}", features.join(", "))),
+        }
+    }
+}
+
+/// How a crate is highlighted, depending on where it is published
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Highlight {
+    /// `publish = false`
+    Unpublished,
+    /// Published to crates.io
+    Public,
+    /// Published to other registries only
+    None,
+}
+
+/// Architectural role of a crate, depending on the targets it builds
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    /// Library, including packages that also build binaries, or of unknown targets
+    Library,
+    /// Application entry point, building binaries only
+    Binary,
+    /// Procedural macro library
+    ProcMacro,
+}
+
+/// Crates of the repositories, with what the renderers share: the nodes, possibly condensed, the
+/// dependencies worth drawing and the highlighting of the crates
+pub struct Graph<'a> {
+    /// Crates of each repository
+    pub infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+    /// Whether the cycles are condensed into a single node
+    pub condense: bool,
+    /// Dependencies to draw
+    pub edge_filter: EdgeFilter,
+    /// Kind of the only dependencies drawn, if not all of them are
+    pub only_kind: Option<DepKind>,
+    /// Whether the dependencies on crates outside of the repositories are drawn too
+    pub external: bool,
+    /// Groups and owners of the repositories
+    pub ownership: &'a Ownership,
+    nodes: Vec<Node<'a>>,
+    /// Index in `nodes` of the node of each crate, shared by the crates of a condensed cycle
+    crates: BTreeMap<&'a str, usize>,
+    /// Number of lines of Rust of the largest crate, if they were measured
+    max_lines: u64,
+}
+
+impl<'a> Graph<'a> {
+    /// Graph of `infos` drawn with `options`, where each cycle is a single node if they are
+    /// condensed, repositories belong to their group of `ownership` and the nodes of the crates
+    /// matching the patterns to highlight are highlighted
+    pub fn new(
+        infos: &'a BTreeMap<String, Vec<CrateInfo>>,
+        ownership: &'a Ownership,
+        options: &RenderOptions,
+    ) -> Graph<'a> {
+        let RenderOptions {
+            condense,
+            ref highlight,
+            edges: edge_filter,
+            external,
+            max_nodes,
+            only_kind,
+            ..
+        } = *options;
+        let mut nodes = Vec::new();
+        let mut crates = BTreeMap::new();
+        for (repo, c) in all_crates(infos) {
+            crates.insert(&c.name as &str, nodes.len());
+            nodes.push(Node {
+                label: c.name.clone(),
+                repos: vec![repo],
+                highlighted: highlight.iter().any(|p| glob_match(p, &c.name)),
+                hidden: 0,
+            });
+        }
+        let max_lines = all_crates(infos)
+            .filter_map(|(_, c)| Some(c.size?.lines))
+            .max()
+            .unwrap_or_default();
+        let mut graph = Graph {
+            infos,
+            condense,
+            edge_filter,
+            only_kind,
+            external,
+            ownership,
+            nodes,
+            crates,
+            max_lines,
+        };
+        if condense {
+            graph.condense_cycles();
+        }
+        if let Some(max_nodes) = max_nodes {
+            graph.truncate(max_nodes);
+        }
+        graph
+    }
+
+    /// Replace the crates of each cycle of the drawn dependencies with a single node
+    fn condense_cycles(&mut self) {
+        let graph = DependencyGraph::new(self.infos);
+        for mut scc in strongly_connected_components(&graph, |d| self.drawn(d)) {
+            if scc.len() > 1 {
+                scc.sort_unstable();
+                let label = scc.join("\\n");
+                let mut repos = scc
+                    .iter()
+                    .map(|c| self.crate_node(c).repos[0])
+                    .collect::<Vec<_>>();
+                repos.sort_unstable();
+                repos.dedup();
+                let highlighted = scc.iter().any(|c| self.crate_node(c).highlighted);
+                // A single node for the whole cycle, as its label grows with its size
+                for c in scc {
+                    self.crates.insert(c, self.nodes.len());
+                }
+                self.nodes.push(Node {
+                    label,
+                    repos,
+                    highlighted,
+                    hidden: 0,
+                });
+            }
+        }
+    }
+
+    /// Keep the `max_nodes` crates with the most drawn dependencies and dependents, the others
+    /// being replaced with a placeholder node per repository
+    fn truncate(&mut self, max_nodes: usize) {
+        let mut degrees = all_crates(self.infos)
+            .map(|(_, c)| (&c.name as &str, 0))
+            .collect::<BTreeMap<_, usize>>();
+        let edges = self
+            .edges()
+            .filter(|e| e.from.name != e.to && self.crates.contains_key(e.to))
+            .map(|e| (&e.from.name as &'a str, e.to))
+            .collect::<Vec<_>>();
+        for (from, to) in edges {
+            *degrees.get_mut(from).unwrap() += 1;
+            *degrees.get_mut(to).unwrap() += 1;
+        }
+        let mut ranked = degrees.into_iter().collect::<Vec<_>>();
+        ranked.sort_by_key(|(c, degree)| (std::cmp::Reverse(*degree), *c));
+        let hidden = ranked
+            .into_iter()
+            .skip(max_nodes)
+            .map(|(c, _)| c)
+            .collect::<BTreeSet<_>>();
+        for (repo, c) in all_crates(self.infos) {
+            if !hidden.contains(&c.name as &str) {
+                continue;
+            }
+            let placeholder = format!("\u{2026} {}", repo);
+            let idx = match self.nodes.iter().position(|n| n.label == placeholder) {
+                Some(idx) => idx,
+                None => {
+                    self.nodes.push(Node {
+                        label: placeholder,
+                        repos: vec![repo],
+                        highlighted: false,
+                        hidden: 0,
+                    });
+                    self.nodes.len() - 1
+                }
+            };
+            self.nodes[idx].hidden += 1;
+            self.crates.insert(&c.name, idx);
+        }
+    }
+
+    /// Repositories and their crates by group, those without a group coming first
+    pub fn repos_by_group(&self) -> BTreeMap<Option<&'a str>, Vec<(&'a str, &'a [CrateInfo])>> {
+        let mut res = BTreeMap::<_, Vec<_>>::new();
+        for (repo, infos) in self.infos.iter() {
+            res.entry(self.ownership.groups.get(repo).map(|g| g as &str))
+                .or_default()
+                .push((repo as &str, infos as &[CrateInfo]));
+        }
+        res
+    }
+
+    /// Node of crate `name`, if it is one of the crates of the graph
+    pub fn node(&self, name: &str) -> Option<&Node<'a>> {
+        self.crates.get(name).map(|&i| &self.nodes[i])
+    }
+
+    /// Nodes of all the crates, by crate name
+    pub fn nodes(&self) -> impl Iterator<Item = (&'a str, &Node<'a>)> + '_ {
+        self.crates.iter().map(move |(c, &i)| (*c, &self.nodes[i]))
+    }
+
+    fn crate_node(&self, name: &str) -> &Node<'a> {
+        &self.nodes[self.crates[name]]
+    }
+
+    /// Dependencies to draw, in the order of the crates, with a single edge for all the
+    /// declarations of a dependency by a crate, which is optional if they all are
+    ///
+    /// For now we're interested only in stuff from our own registry or that has path-local
+    /// dependencies, or only one of them depending on the edge filter, along with the ones on
+    /// external crates if they are drawn, and only the ones of a kind if asked to.
+    pub fn edges(&self) -> impl Iterator<Item = Edge<'a>> + '_ {
+        all_crates(self.infos).flat_map(move |(_, c)| {
+            let mut edges = Vec::<Edge>::new();
+            for d in c.deps.iter().filter(|d| self.drawn(d)) {
+                match edges.iter_mut().find(|e| e.to == d.name) {
+                    Some(e) => {
+                        e.path |= d.has_path;
+                        if !e.kinds.contains(&d.kind) {
+                            e.kinds.push(d.kind);
+                        }
+                        e.count += 1;
+                        e.optional &= d.optional;
+                        e.features.extend(d.enabled_by.iter().map(|f| f as &str));
+                    }
+                    None => edges.push(Edge {
+                        from: c,
+                        to: &d.name,
+                        path: d.has_path,
+                        kinds: vec![d.kind],
+                        count: 1,
+                        optional: d.optional,
+                        features: d.enabled_by.iter().map(|f| f as &str).collect(),
+                    }),
+                }
+            }
+            for e in edges.iter_mut() {
+                match e.optional {
+                    true => {
+                        e.features.sort_unstable();
+                        e.features.dedup();
+                    }
+                    false => e.features.clear(),
+                }
+            }
+            edges
+        })
+    }
+
+    /// Whether dependency `d` is drawn, see [`Graph::edges`]
+    fn drawn(&self, d: &Dependency) -> bool {
+        let filtered = match self.edge_filter {
+            EdgeFilter::All => d.from.is_some() || d.has_path,
+            EdgeFilter::Path => d.has_path,
+            EdgeFilter::Registry => d.from.is_some(),
+        };
+        (filtered || (self.external && !self.crates.contains_key(&d.name as &str)))
+            && self.only_kind.is_none_or(|kind| d.kind == kind)
+    }
+
+    /// Crates outside of the repositories that the drawn dependencies point to, with the number
+    /// of crates depending on each of them
+    pub fn externals(&self) -> BTreeMap<&'a str, usize> {
+        let mut res = BTreeMap::new();
+        if self.external {
+            for e in self.edges().filter(|e| self.node(e.to).is_none()) {
+                *res.entry(e.to).or_default() += 1;
+            }
+        }
+        res
+    }
+
+    /// Tooltip of `krate`, telling whether it is deprecated and what it is from its description
+    /// and keywords, if it has any
+    pub fn tooltip(&self, krate: &CrateInfo) -> Option<String> {
+        let deprecation = self.deprecation(krate).map(|d| match d.is_empty() {
+            true => String::from("deprecated"),
+            false => format!("deprecated: {}", d),
+        });
+        let keywords = match krate.keywords.is_empty() {
+            true => None,
+            false => Some(format!("keywords: {}", krate.keywords.join(", "))),
+        };
+        let lines = vec![deprecation, krate.description.clone(), keywords]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        match lines.is_empty() {
+            true => None,
+            false => Some(lines.join("\n")),
+        }
+    }
+
+    /// Size of `krate` relative to the largest crate, between 0 and 1, as the square root of its
+    /// share of lines for its area to be proportional to them, if the sizes were measured
+    pub fn scale(&self, krate: &CrateInfo) -> Option<f64> {
+        match (krate.size, self.max_lines) {
+            (Some(size), max_lines) if max_lines > 0 => {
+                Some((size.lines as f64 / max_lines as f64).sqrt())
+            }
+            _ => None,
+        }
+    }
+
+    /// What to use instead of `krate` if it is deprecated, empty if the configuration does not
+    /// tell
+    pub fn deprecation(&self, krate: &CrateInfo) -> Option<&'a str> {
+        self.ownership
+            .deprecated
+            .get(&krate.name)
+            .map(|d| d as &str)
+    }
+
+    /// Role of `krate`
+    pub fn role(&self, krate: &CrateInfo) -> Role {
+        if krate.targets.contains(&TargetKind::ProcMacro) {
+            Role::ProcMacro
+        } else if krate.targets == [TargetKind::Bin] {
+            Role::Binary
+        } else {
+            Role::Library
+        }
+    }
+
+    /// How `krate` is highlighted
+    pub fn highlight(&self, krate: &CrateInfo) -> Highlight {
+        match &krate.published_to {
+            Publish::Nowhere => Highlight::Unpublished,
+            Publish::Default => Highlight::Public,
+            Publish::At(_) => Highlight::None,
+        }
+    }
+}
+
+/// Graphviz description language
+pub struct Dot {
+    /// How the repository of each crate is shown
+    pub graph_type: GraphType,
+}
+
+impl Dot {
+    fn write(&self, graph: &Graph, out: &mut dyn Write) -> anyhow::Result<()> {
+        if self.graph_type == GraphType::Groups {
+            return self.write_groups(graph, out);
+        }
+
+        let mut printed = BTreeSet::new();
+
+        writeln!(out, "digraph G {{")?;
+        writeln!(out, "    node [shape=rectangle]")?;
+
+        // First, put all the nodes in their repository, itself in its group if it has one
+        if self.graph_type == GraphType::Cluster {
+            for (group, repos) in graph.repos_by_group() {
+                let indent = match group {
+                    Some(group) => {
+                        writeln!(out, "    subgraph \"cluster_group_{}\" {{", group)?;
+                        writeln!(out, "        label = \"{}\";", group)?;
+                        writeln!(out, "        style = \"rounded,bold\";")?;
+                        "    "
+                    }
+                    None => "",
+                };
+                for (repo, infos) in repos {
+                    writeln!(out, "{}    subgraph \"cluster_{}\" {{", indent, repo)?;
+                    match graph.ownership.owners.get(repo) {
+                        Some(owner) => {
+                            writeln!(out, "{}        label = \"{}\\n{}\";", indent, repo, owner)?;
+                            writeln!(
+                                out,
+                                "{}        tooltip = \"{} is owned by {}\";",
+                                indent, repo, owner
+                            )?;
+                        }
+                        None => writeln!(out, "{}        label = \"{}\";", indent, repo)?,
+                    }
+                    writeln!(out, "{}        style = filled;", indent)?;
+                    for i in infos.iter() {
+                        let node = graph.crate_node(&i.name);
+                        if node.repos.len() > 1 || !printed.insert(&node.label) {
+                            continue;
+                        }
+                        // Condensed cycles are not highlighted
+                        let mut attrs = Vec::new();
+                        if node.label == i.name {
+                            match graph.highlight(i) {
+                                Highlight::Unpublished => attrs.push("color=blue".to_string()),
+                                Highlight::Public => attrs.push("color=green".to_string()),
+                                Highlight::None => (),
+                            }
+                            attrs.extend(crate_attrs(graph, i));
+                        }
+                        if node.highlighted {
+                            attrs.push(format!("style=filled, fillcolor=\"{}\"", HIGHLIGHT));
+                            attrs.push("penwidth=3".to_string());
+                        }
+                        if node.hidden > 0 {
+                            attrs.push(format!(
+                                "label=\"{}\", style=dashed",
+                                placeholder_label(node.hidden)
+                            ));
+                        }
+                        writeln!(
+                            out,
+                            "{}        \"{}\" [{}];",
+                            indent,
+                            node.label,
+                            attrs.join(", ")
+                        )?;
+                    }
+                    writeln!(out, "{}    }}", indent)?;
+                }
+                if group.is_some() {
+                    writeln!(out, "    }}")?;
+                }
+            }
+        } else {
+            ensure!(graph.infos.len() <= COLORS.len(), "asked for a color-based output while there are more repositories than colors available");
+            for (idx, (_, infos)) in graph.infos.iter().enumerate() {
+                for i in infos.iter() {
+                    let node = graph.crate_node(&i.name);
+                    if node.repos.len() > 1 || !printed.insert(&node.label) {
+                        continue;
+                    }
+                    let mut attrs = vec![
+                        "style=filled".to_string(),
+                        format!("fillcolor=\"{}\"", COLORS[idx]),
+                    ];
+                    if node.hidden > 0 {
+                        attrs[0] = format!(
+                            "style=\"filled,dashed\", label=\"{}\"",
+                            placeholder_label(node.hidden)
+                        );
+                    }
+                    if node.label == i.name {
+                        attrs.extend(crate_attrs(graph, i));
+                    }
+                    // The fill tells the repository, so only the border stands out
+                    if node.highlighted {
+                        attrs.push("penwidth=4".to_string());
+                    }
+                    writeln!(out, "    \"{}\" [{}];", node.label, attrs.join(", "))?;
+                }
+            }
+        }
+
+        // Components spanning multiple repositories do not belong to any single repository
+        for (_, node) in graph.nodes() {
+            if node.repos.len() > 1 && printed.insert(&node.label) {
+                let style = match node.highlighted {
+                    true => format!("\"dashed,filled\", fillcolor=\"{}\", penwidth=3", HIGHLIGHT),
+                    false => String::from("dashed"),
+                };
+                writeln!(
+                    out,
+                    "    \"{}\" [style={}, xlabel=\"{}\"];",
+                    node.label,
+                    style,
+                    node.repos.join(", ")
+                )?;
+            }
+        }
+
+        // External crates are drawn once, however many of our crates depend on them
+        for (name, dependents) in graph.externals() {
+            writeln!(
+                out,
+                "    \"{}\" [shape=hexagon, style=filled, fillcolor=gray90, label=\"{}\\n({})\", tooltip=\"{} is a dependency of {} of our crates\", id=\"external:{}\", class=\"external\"];",
+                name, name, dependents, name, dependents, name
+            )?;
+        }
+
+        // Then, draw all arrows, labelled with their multiplicity when declared several times,
+        // and with the features enabling them when optional
+        let mut drawn = BTreeSet::new();
+        for e in graph.edges() {
+            let (from, to) = (graph.crate_node(&e.from.name), graph.node(e.to));
+            // Arrows between condensed cycles or placeholders merge several dependencies
+            let merged = graph.condense || from.hidden > 0 || to.is_some_and(|n| n.hidden > 0);
+            let from = &from.label as &str;
+            let to = to.map_or(e.to, |n| &n.label);
+            if merged {
+                let color = if e.path { "[color=blue]" } else { "" };
+                if from != to && drawn.insert((from, to, color)) {
+                    writeln!(out, "    \"{}\" -> \"{}\" {};", from, to, color)?;
+                }
+                continue;
+            }
+            let mut attrs = Vec::new();
+            if e.optional {
+                attrs.push("color=gray50, style=dashed".to_string());
+            } else if e.path {
+                attrs.push("color=blue".to_string());
+            }
+            let mut label = Vec::new();
+            if e.count > 1 {
+                label.push(e.count.to_string());
+            }
+            label.extend(e.feature_label());
+            if !label.is_empty() {
+                attrs.push(format!("label=\"{}\"", label.join("\\n")));
+            }
+            let kinds = e.kinds.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+            if e.count > 1 {
+                attrs.push(format!("tooltip=\"{}\"", kinds.join(", ")));
+            }
+            // Provenance of the dependency, declared in the manifest of the dependent crate
+            let mut classes = kinds.clone();
+            classes.sort_unstable();
+            classes.dedup();
+            attrs.push(format!(
+                "id=\"dep:{}->{}\", class=\"dependency {}\"",
+                e.from.name,
+                e.to,
+                classes.join(" ")
+            ));
+            attrs.push(provenance(&serde_json::json!({
+                "from": e.from.name,
+                "from_repo": graph.crate_node(&e.from.name).repos[0],
+                "to": e.to,
+                "to_repo": graph.node(e.to).map(|n| n.repos[0]),
+                "manifest": e.from.manifest,
+                "kinds": kinds,
+            })));
+            writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [{}];",
+                from,
+                to,
+                attrs.join(", ")
+            )?;
+        }
+
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+
+    /// Write a node per group, listing its repositories, and an arrow between groups labelled
+    /// with the number of dependencies it stands for
+    fn write_groups<'a>(&self, graph: &Graph<'a>, out: &mut dyn Write) -> anyhow::Result<()> {
+        // Groups and repositories may have the same name, hence the prefixes of the node IDs
+        let node_of = |repo: &str| match graph.ownership.groups.get(repo) {
+            Some(group) => format!("group:{}", group),
+            None => format!("repo:{}", repo),
+        };
+        let mut arrows = BTreeMap::<(String, String), usize>::new();
+        for e in graph.edges() {
+            if let Some(to) = graph.node(e.to) {
+                let from = node_of(graph.crate_node(&e.from.name).repos[0]);
+                let to = node_of(to.repos[0]);
+                if from != to {
+                    *arrows.entry((from, to)).or_default() += 1;
+                }
+            }
+        }
+
+        writeln!(out, "digraph G {{")?;
+        writeln!(out, "    node [shape=rectangle]")?;
+        for (group, repos) in graph.repos_by_group() {
+            match group {
+                Some(group) => {
+                    let repos = repos.iter().map(|(r, _)| *r).collect::<Vec<_>>();
+                    writeln!(
+                        out,
+                        "    \"group:{}\" [label=\"{}\\n({})\", style=\"rounded,bold\"];",
+                        group,
+                        group,
+                        repos.join(", ")
+                    )?;
+                }
+                None => {
+                    for (repo, _) in repos {
+                        writeln!(out, "    \"repo:{}\" [label=\"{}\"];", repo, repo)?;
+                    }
+                }
+            }
+        }
+        for ((from, to), count) in arrows {
+            writeln!(out, "    \"{}\" -> \"{}\" [label=\"{}\"];", from, to, count)?;
+        }
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+}
+
+/// Label of a placeholder node standing for `hidden` crates left out
+fn placeholder_label(hidden: usize) -> String {
+    match hidden {
+        1 => String::from("\u{2026} and 1 more crate"),
+        _ => format!("\u{2026} and {} more crates", hidden),
+    }
+}
+
+/// Attributes of the DOT node of crate `krate`, but its color: its shape, after its role, its
+/// size, after its lines of Rust, its label, greyed out and struck through if it is deprecated,
+/// its tooltip and its provenance
+fn crate_attrs(graph: &Graph, krate: &CrateInfo) -> Vec<String> {
+    let mut attrs = Vec::new();
+    match graph.role(krate) {
+        Role::Library => (),
+        Role::Binary => attrs.push("shape=box3d".to_string()),
+        Role::ProcMacro => attrs.push("shape=hexagon".to_string()),
+    }
+    if let Some(scale) = graph.scale(krate) {
+        // From the default size of the nodes, in inches, to 4 times their area
+        attrs.push(format!(
+            "width={:.2}, height={:.2}",
+            0.75 * (1. + scale),
+            0.5 * (1. + scale)
+        ));
+    }
+    if graph.deprecation(krate).is_some() {
+        attrs.push(format!("label=<<S>{}</S>>, fontcolor=gray50", krate.name));
+    }
+    if let Some(tooltip) = graph.tooltip(krate) {
+        // Escape the quotes, backslashes and line breaks of the tooltip
+        let escaped = tooltip
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        attrs.push(format!("tooltip=\"{}\"", escaped));
+    }
+    let repo = graph.crate_node(&krate.name).repos[0];
+    attrs.push(format!(
+        "id=\"crate:{}\", class=\"crate repo-{}\"",
+        krate.name,
+        page_name(repo)
+    ));
+    attrs.push(provenance(&serde_json::json!({
+        "repo": repo,
+        "crate": krate.name,
+        "manifest": krate.manifest,
+    })));
+    attrs
+}
+
+/// `comment` attribute recording where a node or an arrow comes from as a JSON object, which
+/// graphviz keeps as a comment in the SVG output, for post-processors to map it back to the
+/// crates without parsing the labels
+fn provenance(json: &serde_json::Value) -> String {
+    // Only quotes are escaped in DOT strings, other backslashes being kept as they are
+    format!("comment=\"{}\"", json.to_string().replace('"', "\\\""))
+}
+
+impl Renderer for Dot {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        self.write(graph, out).map_err(Error::Render)
+    }
+}
+
+/// SVG, rendered from [`Dot`] with graphviz
+pub struct Svg {
+    /// How the repository of each crate is shown
+    pub graph_type: GraphType,
+    /// Engine and arguments of graphviz
+    pub graphviz: GraphvizConfig,
+}
+
+impl Renderer for Svg {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        let mut dot = Vec::new();
+        Dot {
+            graph_type: self.graph_type,
+        }
+        .render(graph, &mut dot)?;
+        out.write_all(&render_svg(&dot, &self.graphviz)?)
+            .context("Failed to write the SVG")
+            .map_err(Error::Render)
+    }
+}
+
+/// Mermaid flowchart, with a subgraph per repository
+pub struct Mermaid;
+
+impl Mermaid {
+    fn write(&self, graph: &Graph, out: &mut dyn Write) -> anyhow::Result<()> {
+        // Mermaid identifiers cannot contain dashes, so number the crates instead
+        let mut ids = all_crates(graph.infos)
+            .enumerate()
+            .map(|(i, (_, c))| (&c.name as &str, format!("c{}", i)))
+            .collect::<BTreeMap<_, _>>();
+
+        let (mut deprecated, mut highlighted) = (Vec::new(), Vec::new());
+        writeln!(out, "flowchart LR")?;
+        writeln!(out, "    classDef unpublished stroke:blue")?;
+        writeln!(out, "    classDef public stroke:green")?;
+        writeln!(
+            out,
+            "    classDef deprecated color:gray,stroke-dasharray:5 5"
+        )?;
+        writeln!(
+            out,
+            "    classDef highlighted fill:{},stroke-width:4px",
+            HIGHLIGHT
+        )?;
+        writeln!(out, "    classDef external fill:#e5e5e5")?;
+        // External crates are drawn once, with the number of our crates depending on them
+        for (idx, (name, dependents)) in graph.externals().into_iter().enumerate() {
+            let id = format!("x{}", idx);
+            writeln!(
+                out,
+                "    {}{{{{\"{} ({})\"}}}}:::external",
+                id, name, dependents
+            )?;
+            ids.insert(name, id);
+        }
+
+        for (idx, (group, repos)) in graph.repos_by_group().into_iter().enumerate() {
+            let indent = match group {
+                Some(group) => {
+                    writeln!(out, "    subgraph g{}[\"{}\"]", idx, group)?;
+                    "    "
+                }
+                None => "",
+            };
+            for (repo, infos) in repos {
+                match graph.ownership.owners.get(repo) {
+                    Some(owner) => writeln!(
+                        out,
+                        "{}    subgraph {}[\"{}: {}\"]",
+                        indent, repo, repo, owner
+                    )?,
+                    None => writeln!(out, "{}    subgraph {}", indent, repo)?,
+                }
+                for i in infos.iter() {
+                    let class = match graph.highlight(i) {
+                        Highlight::Unpublished => ":::unpublished",
+                        Highlight::Public => ":::public",
+                        Highlight::None => "",
+                    };
+                    let (open, close) = match graph.role(i) {
+                        Role::Library => ("[", "]"),
+                        Role::Binary => ("[[", "]]"),
+                        Role::ProcMacro => ("{{", "}}"),
+                    };
+                    writeln!(
+                        out,
+                        "{}        {}{}\"{}\"{}{}",
+                        indent, ids[&i.name as &str], open, i.name, close, class
+                    )?;
+                    if graph.deprecation(i).is_some() {
+                        deprecated.push(&ids[&i.name as &str] as &str);
+                    }
+                    if graph.crate_node(&i.name).highlighted {
+                        highlighted.push(&ids[&i.name as &str] as &str);
+                    }
+                }
+                writeln!(out, "{}    end", indent)?;
+            }
+            if group.is_some() {
+                writeln!(out, "    end")?;
+            }
+        }
+
+        // Deprecated and highlighted crates get more classes, which `:::` cannot add
+        for (class, ids) in [("deprecated", deprecated), ("highlighted", highlighted)] {
+            if !ids.is_empty() {
+                writeln!(out, "    class {} {}", ids.join(","), class)?;
+            }
+        }
+
+        // Path-local dependencies are drawn with plain arrows, registry ones with dotted arrows,
+        // optional ones being grey and labelled with the features enabling them
+        let (mut links, mut optional) = (0, Vec::new());
+        for e in graph.edges() {
+            if let Some(to) = ids.get(e.to) {
+                let arrow = if e.path { "-->" } else { "-.->" };
+                let label = e
+                    .feature_label()
+                    .map_or(String::new(), |l| format!("|\"{}\"|", l));
+                // Links are styled by their index, in the order they are written
+                if e.optional {
+                    optional.push(links.to_string());
+                }
+                links += 1;
+                writeln!(
+                    out,
+                    "    {} {}{} {}",
+                    ids[&e.from.name as &str], arrow, label, to
+                )?;
+            }
+        }
+        if !optional.is_empty() {
+            writeln!(
+                out,
+                "    linkStyle {} stroke:gray,stroke-dasharray:3 3",
+                optional.join(",")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Renderer for Mermaid {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        self.write(graph, out).map_err(Error::Render)
+    }
+}
+
+/// Markdown document embedding the [`Mermaid`] flowchart, which GitHub and GitLab render
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "```mermaid")
+            .context("Failed to write the Markdown")
+            .map_err(Error::Render)?;
+        Mermaid.render(graph, out)?;
+        writeln!(out, "```")
+            .context("Failed to write the Markdown")
+            .map_err(Error::Render)
+    }
+}
+
+/// Self-contained HTML page to attach to a release: the key metrics, the [`Svg`] graph, or its
+/// DOT description when graphviz is not installed, the lint findings and the crates of each
+/// repository
+pub struct HtmlReport {
+    /// How the repository of each crate is shown
+    pub graph_type: GraphType,
+    /// Engine and arguments of graphviz
+    pub graphviz: GraphvizConfig,
+    /// Findings of the lints
+    pub lints: Vec<LintReport>,
+}
+
+/// Style of the HTML pages, inlined so that each page stands on its own
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+nav a { margin-right: 1em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+td.number { text-align: right; }
+tr.error td:first-child { border-left: 4px solid #e05d44; }
+tr.warning td:first-child { border-left: 4px solid #dfb317; }
+tr.passed td:first-child { border-left: 4px solid #4c1; }
+.graph { overflow: auto; border: 1px solid #ccc; max-height: 80vh; }
+pre { background: #f6f6f6; padding: 1em; overflow: auto; }
+";
+
+/// `text` with the characters special to HTML escaped
+pub(crate) fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write the start of an HTML page titled `title`, up to its `<body>` tag
+pub(crate) fn write_html_head(out: &mut dyn Write, title: &str) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>",
+        html_escape(title),
+        HTML_STYLE
+    )?;
+    Ok(())
+}
+
+/// The graph as inline SVG, or as its DOT description if graphviz cannot be run
+pub(crate) fn html_drawing(
+    graph: &Graph,
+    graph_type: GraphType,
+    graphviz: &GraphvizConfig,
+) -> Result<String> {
+    let mut dot = Vec::new();
+    Dot { graph_type }.render(graph, &mut dot)?;
+    if !graphviz.installed() {
+        return Ok(format!(
+            "<p>`{}` was not found, so here is the graph in the DOT format, which `dot -Tsvg` renders.</p>\n<pre>{}</pre>",
+            graphviz.engine(),
+            html_escape(&String::from_utf8_lossy(&dot))
+        ));
+    }
+    let svg = String::from_utf8_lossy(&render_svg(&dot, graphviz)?).into_owned();
+    // Inline the SVG without its XML declaration and doctype
+    Ok(svg[svg.find("<svg").unwrap_or(0)..].to_string())
+}
+
+/// Write the table of the key metrics of the graph, and of the number of findings of `lints`
+pub(crate) fn write_summary(
+    out: &mut dyn Write,
+    graph: &Graph,
+    metrics: &Metrics,
+    lints: &[LintReport],
+) -> anyhow::Result<()> {
+    let edges = internal_edges(graph.infos);
+    let mut summary = vec![
+        ("Repositories", graph.infos.len().to_string()),
+        ("Crates", metrics.crates.len().to_string()),
+        ("Dependencies between crates", edges.len().to_string()),
+        (
+            "Dependencies across repositories",
+            edges
+                .iter()
+                .filter(|(repo, _, dep_repo, _)| repo != dep_repo)
+                .count()
+                .to_string(),
+        ),
+        (
+            "Cyclic dependencies across repositories",
+            find_cycles(graph.infos).len().to_string(),
+        ),
+        (
+            "Lint findings",
+            lints
+                .iter()
+                .map(|l| l.findings.len())
+                .sum::<usize>()
+                .to_string(),
+        ),
+    ];
+    if let Some(c) = metrics.crates.iter().max_by_key(|c| c.dependents) {
+        summary.push((
+            "Most depended-upon crate",
+            format!("{} ({} dependents)", c.name, c.dependents),
+        ));
+    }
+    if let Some(c) = metrics.crates.iter().max_by_key(|c| c.depth) {
+        summary.push((
+            "Longest dependency chain",
+            format!("{} (depth {})", c.name, c.depth),
+        ));
+    }
+    writeln!(out, "<table>")?;
+    for (label, value) in summary {
+        writeln!(
+            out,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            label,
+            html_escape(&value)
+        )?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+/// Write the table of the findings of `lints`, folded under their title
+pub(crate) fn write_lint_table(out: &mut dyn Write, lints: &[LintReport]) -> anyhow::Result<()> {
+    if lints.is_empty() {
+        writeln!(out, "<p>No lint ran.</p>")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "<table>\n<tr><th>Lint</th><th>Severity</th><th>Findings</th></tr>"
+    )?;
+    for lint in lints {
+        let class = match lint.findings.is_empty() {
+            true => "passed".to_string(),
+            false => lint.severity.to_string(),
+        };
+        write!(
+            out,
+            "<tr class=\"{}\"><td><code>{}</code></td><td>{}</td><td>",
+            class, lint.id, lint.severity
+        )?;
+        match lint.findings.len() {
+            0 => write!(out, "none")?,
+            n => {
+                write!(
+                    out,
+                    "<details><summary>{} ({})</summary>",
+                    html_escape(&lint.title),
+                    n
+                )?;
+                write_findings(out, &lint.findings)?;
+                write!(out, "</details>")?;
+            }
+        }
+        writeln!(out, "</td></tr>")?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+/// Write `findings`, as a table of their fields if they are all about requirements, and as a
+/// list of their lines otherwise
+fn write_findings(out: &mut dyn Write, findings: &[Finding]) -> anyhow::Result<()> {
+    let requirements = findings
+        .iter()
+        .map(|f| f.requirement.as_ref())
+        .collect::<Option<Vec<_>>>();
+    let requirements = match requirements {
+        Some(requirements) => requirements,
+        None => {
+            write!(out, "<ul>")?;
+            for f in findings {
+                write!(out, "<li>{}</li>", html_escape(&f.text))?;
+            }
+            write!(out, "</ul>")?;
+            return Ok(());
+        }
+    };
+    write!(
+        out,
+        "<table><tr><th>Crate</th><th>Dependency</th><th>Requirement</th><th>Version</th><th>Issue</th></tr>"
+    )?;
+    for (f, r) in findings.iter().zip(requirements) {
+        write!(
+            out,
+            "<tr title=\"{}\"><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+            html_escape(&f.text),
+            html_escape(&r.krate),
+            html_escape(&r.dependency),
+            html_escape(&r.requirement),
+            html_escape(&r.version),
+            r.issue
+        )?;
+    }
+    write!(out, "</table>")?;
+    Ok(())
+}
+
+/// Metrics of each crate of `metrics`, by name, for the pages to look them up
+pub(crate) fn crate_metrics<'m, 'a>(
+    metrics: &'m Metrics<'a>,
+) -> HashMap<&'a str, &'m CrateMetrics<'a>> {
+    metrics.crates.iter().map(|m| (m.name, m)).collect()
+}
+
+/// Write the table of `crates`, with their metadata and their metrics from `metrics`, see
+/// [`crate_metrics`], their names linking to the `<crate>.html` pages of `link`, if set, eg.
+/// "crates/"
+pub(crate) fn write_crate_table(
+    out: &mut dyn Write,
+    crates: &[CrateInfo],
+    metrics: &HashMap<&str, &CrateMetrics>,
+    link: Option<&str>,
+) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "<table>\n<tr><th>Crate</th><th>Version</th><th>Edition</th><th>License</th><th>Published to</th><th>Fan-in</th><th>Fan-out</th><th>Dependents</th></tr>"
+    )?;
+    for c in crates {
+        let name = match link {
+            Some(link) => format!(
+                "<a href=\"{}{}.html\">{}</a>",
+                link,
+                page_name(&c.name),
+                html_escape(&c.name)
+            ),
+            None => html_escape(&c.name),
+        };
+        let m = metrics
+            .get(&c.name as &str)
+            .context("Crate missing from the metrics")?;
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+            name,
+            html_escape(&c.version),
+            html_escape(&c.edition),
+            html_escape(c.license.as_deref().unwrap_or("-")),
+            html_escape(&published_to(&c.published_to)),
+            m.fan_in,
+            m.fan_out,
+            m.dependents
+        )?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+/// Where a crate can be published, eg. "crates.io"
+pub(crate) fn published_to(publish: &Publish) -> String {
+    match publish {
+        Publish::Nowhere => "nowhere".to_string(),
+        Publish::Default => "crates.io".to_string(),
+        Publish::At(registries) => registries.join(", "),
+    }
+}
+
+/// Name of the page of a crate or repository named `name`, without its extension, keeping only
+/// the characters that are safe in file names and URLs, and never empty, "." nor ".."
+pub(crate) fn page_name(name: &str) -> String {
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len().max(1));
+    }
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+impl HtmlReport {
+    fn write(
+        &self,
+        graph: &Graph,
+        metrics: &Metrics,
+        drawing: &str,
+        out: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        write_html_head(out, "Dependency report")?;
+        writeln!(out, "<h1>Dependency report</h1>")?;
+        writeln!(
+            out,
+            "<p>Generated on {}.</p>",
+            chrono::Utc::now().format("%Y-%m-%d at %H:%M UTC")
+        )?;
+        writeln!(out, "<h2>Summary</h2>")?;
+        write_summary(out, graph, metrics, &self.lints)?;
+        writeln!(
+            out,
+            "<h2>Graph</h2>\n<div class=\"graph\">\n{}\n</div>",
+            drawing
+        )?;
+        writeln!(out, "<h2>Lints</h2>")?;
+        write_lint_table(out, &self.lints)?;
+        writeln!(out, "<h2>Crates</h2>")?;
+        let crate_metrics = crate_metrics(metrics);
+        for (repo, crates) in graph.infos {
+            writeln!(out, "<h3>{}</h3>", html_escape(repo))?;
+            write_ownership(out, graph.ownership, repo)?;
+            write_crate_table(out, crates, &crate_metrics, None)?;
+        }
+        writeln!(out, "</body>\n</html>")?;
+        Ok(())
+    }
+}
+
+/// Write the group and the owner of `repo` in `ownership`, if it has any
+pub(crate) fn write_ownership(
+    out: &mut dyn Write,
+    ownership: &Ownership,
+    repo: &str,
+) -> anyhow::Result<()> {
+    let fields = [
+        ("Group", ownership.groups.get(repo)),
+        ("Owner", ownership.owners.get(repo)),
+    ];
+    for (label, value) in fields.iter() {
+        if let Some(value) = value {
+            writeln!(out, "<p>{}: {}</p>", label, html_escape(value))?;
+        }
+    }
+    Ok(())
+}
+
+impl Renderer for HtmlReport {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        let metrics = metrics(graph.infos)?;
+        let drawing = html_drawing(graph, self.graph_type, &self.graphviz)?;
+        self.write(graph, &metrics, &drawing, out)
+            .context("Failed to write the HTML report")
+            .map_err(Error::Render)
+    }
+}
+
+/// Output of [`export`], as pretty-printed JSON
+pub struct Json;
+
+impl Renderer for Json {
+    fn render(&self, graph: &Graph, out: &mut dyn Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &export(graph.infos, graph.ownership))
+            .context("Failed to write the JSON")
+            .map_err(Error::Render)?;
+        writeln!(out)
+            .context("Failed to write the JSON")
+            .map_err(Error::Render)
+    }
+}
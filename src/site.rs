@@ -0,0 +1,312 @@
+//! Static site of the dependency graph, written by `muregraph site`
+//!
+//! The site has an overview page with the key metrics and the graph, a page per repository, a
+//! page per crate with its metadata, dependencies and dependents, and the dashboard of the lints.
+//! Pages link to each other with relative links, so that the site can be served from any
+//! directory, eg. by GitHub Pages.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Write,
+};
+
+use anyhow::Context;
+
+use crate::{
+    all_crates, internal_edges, metrics,
+    render::{
+        crate_metrics, html_drawing, html_escape, page_name, published_to, write_crate_table,
+        write_html_head, write_lint_table, write_ownership, write_summary, Graph,
+    },
+    reverse_deps, CrateIndex, CrateInfo, CrateMetrics, Error, LintReport, Metrics, Ownership,
+    RenderOptions, Result, Severity,
+};
+
+/// Pages of the site of `infos`, by path relative to the root of the site, eg.
+/// "crates/core-net.html", the overview graph being drawn with `options`
+pub fn pages(
+    infos: &BTreeMap<String, Vec<CrateInfo>>,
+    ownership: &Ownership,
+    options: &RenderOptions,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let graph = Graph::new(infos, ownership, options);
+    let metrics = metrics(infos)?;
+    let drawing = html_drawing(&graph, options.graph_type, &options.graphviz)?;
+    write_pages(&graph, &metrics, &drawing, &options.lints)
+        .context("Failed to write the pages")
+        .map_err(Error::Render)
+}
+
+fn write_pages(
+    graph: &Graph,
+    metrics: &Metrics,
+    drawing: &str,
+    lints: &[LintReport],
+) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    let mut pages = BTreeMap::new();
+
+    let mut out = Vec::new();
+    write_overview(&mut out, graph, metrics, drawing, lints)?;
+    pages.insert("index.html".to_string(), out);
+
+    let mut out = Vec::new();
+    write_lints(&mut out, lints)?;
+    pages.insert("lints.html".to_string(), out);
+
+    // Repositories each repository depends on, and is depended on by
+    let mut repo_edges = BTreeMap::<&str, (BTreeSet<&str>, BTreeSet<&str>)>::new();
+    for (from, _, to, _) in internal_edges(graph.infos) {
+        if from != to {
+            repo_edges.entry(from).or_default().0.insert(to);
+            repo_edges.entry(to).or_default().1.insert(from);
+        }
+    }
+    let crate_metrics = crate_metrics(metrics);
+    for (repo, crates) in graph.infos {
+        let mut out = Vec::new();
+        let edges = repo_edges.remove(repo as &str).unwrap_or_default();
+        write_repo(&mut out, graph, &crate_metrics, repo, crates, edges)?;
+        pages.insert(format!("repos/{}.html", page_name(repo)), out);
+    }
+
+    let index = CrateIndex::new(graph.infos);
+    let rdeps = reverse_deps(graph.infos);
+    for (repo, c) in all_crates(graph.infos) {
+        let mut out = Vec::new();
+        let dependents = rdeps.get(&c.name as &str).map_or(&[][..], |r| r);
+        write_crate(&mut out, &crate_metrics, &index, repo, c, dependents)?;
+        pages.insert(format!("crates/{}.html", page_name(&c.name)), out);
+    }
+    Ok(pages)
+}
+
+/// Write the links to the overview and to the lints, from a page `root` is the path to the
+/// root of the site from, eg. "../"
+fn write_nav(out: &mut dyn Write, root: &str) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "<nav><a href=\"{0}index.html\">Overview</a><a href=\"{0}lints.html\">Lints</a></nav>",
+        root
+    )?;
+    Ok(())
+}
+
+/// Link to the page of `repo`, from a page `root` is the path to the root of the site from
+fn repo_link(root: &str, repo: &str) -> String {
+    format!(
+        "<a href=\"{}repos/{}.html\">{}</a>",
+        root,
+        page_name(repo),
+        html_escape(repo)
+    )
+}
+
+/// Link to the page of the crate `name`, from a page `root` is the path to the root of the site
+/// from
+fn crate_link(root: &str, name: &str) -> String {
+    format!(
+        "<a href=\"{}crates/{}.html\">{}</a>",
+        root,
+        page_name(name),
+        html_escape(name)
+    )
+}
+
+fn write_overview(
+    out: &mut dyn Write,
+    graph: &Graph,
+    metrics: &Metrics,
+    drawing: &str,
+    lints: &[LintReport],
+) -> anyhow::Result<()> {
+    write_html_head(out, "Dependency graph")?;
+    write_nav(out, "")?;
+    writeln!(out, "<h1>Dependency graph</h1>")?;
+    writeln!(
+        out,
+        "<p>Generated on {}.</p>",
+        chrono::Utc::now().format("%Y-%m-%d at %H:%M UTC")
+    )?;
+    writeln!(out, "<h2>Summary</h2>")?;
+    write_summary(out, graph, metrics, lints)?;
+    writeln!(
+        out,
+        "<h2>Graph</h2>\n<div class=\"graph\">\n{}\n</div>",
+        drawing
+    )?;
+    writeln!(out, "<h2>Repositories</h2>")?;
+    writeln!(
+        out,
+        "<table>\n<tr><th>Repository</th><th>Group</th><th>Owner</th><th>Crates</th></tr>"
+    )?;
+    for (repo, crates) in graph.infos {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"number\">{}</td></tr>",
+            repo_link("", repo),
+            html_escape(graph.ownership.groups.get(repo).map_or("-", |g| g)),
+            html_escape(graph.ownership.owners.get(repo).map_or("-", |o| o)),
+            crates.len()
+        )?;
+    }
+    writeln!(out, "</table>\n</body>\n</html>")?;
+    Ok(())
+}
+
+fn write_lints(out: &mut dyn Write, lints: &[LintReport]) -> anyhow::Result<()> {
+    write_html_head(out, "Lints")?;
+    write_nav(out, "")?;
+    writeln!(out, "<h1>Lints</h1>")?;
+    let failing = lints
+        .iter()
+        .filter(|l| l.severity == Severity::Error && !l.findings.is_empty())
+        .count();
+    writeln!(
+        out,
+        "<p>{} lints ran, {} of which fail.</p>",
+        lints.len(),
+        failing
+    )?;
+    write_lint_table(out, lints)?;
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
+
+/// Write the page of `repo`, which depends on and is depended on by the repositories of
+/// `(dependencies, dependents)`
+fn write_repo(
+    out: &mut dyn Write,
+    graph: &Graph,
+    metrics: &HashMap<&str, &CrateMetrics>,
+    repo: &str,
+    crates: &[CrateInfo],
+    (dependencies, dependents): (BTreeSet<&str>, BTreeSet<&str>),
+) -> anyhow::Result<()> {
+    write_html_head(out, repo)?;
+    write_nav(out, "../")?;
+    writeln!(out, "<h1>{}</h1>", html_escape(repo))?;
+    write_ownership(out, graph.ownership, repo)?;
+
+    for (label, repos) in [("Depends on", dependencies), ("Depended on by", dependents)].iter() {
+        let links = repos
+            .iter()
+            .map(|r| repo_link("../", r))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "<p>{}: {}</p>",
+            label,
+            match links.is_empty() {
+                true => "no other repository".to_string(),
+                false => links.join(", "),
+            }
+        )?;
+    }
+
+    writeln!(out, "<h2>Crates</h2>")?;
+    write_crate_table(out, crates, metrics, Some("../crates/"))?;
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
+
+fn write_crate(
+    out: &mut dyn Write,
+    metrics: &HashMap<&str, &CrateMetrics>,
+    index: &CrateIndex,
+    repo: &str,
+    c: &CrateInfo,
+    dependents: &[(&str, &CrateInfo)],
+) -> anyhow::Result<()> {
+    write_html_head(out, &c.name)?;
+    write_nav(out, "../")?;
+    writeln!(out, "<h1>{}</h1>", html_escape(&c.name))?;
+    if let Some(description) = &c.description {
+        writeln!(out, "<p>{}</p>", html_escape(description))?;
+    }
+
+    let m = metrics
+        .get(&c.name as &str)
+        .context("Crate missing from the metrics")?;
+    let mut fields = vec![
+        ("Repository", repo_link("../", repo)),
+        ("Version", html_escape(&c.version)),
+        ("Edition", html_escape(&c.edition)),
+        ("License", html_escape(c.license.as_deref().unwrap_or("-"))),
+        ("Published to", html_escape(&published_to(&c.published_to))),
+    ];
+    if !c.keywords.is_empty() {
+        fields.push(("Keywords", html_escape(&c.keywords.join(", "))));
+    }
+    if !c.owners.is_empty() {
+        fields.push(("Owners", html_escape(&c.owners.join(", "))));
+    }
+    if let Some(revision) = &c.revision {
+        fields.push(("Revision", html_escape(revision)));
+    }
+    if let Some(size) = c.size {
+        fields.push(("Lines of Rust", size.lines.to_string()));
+    }
+    fields.extend(vec![
+        ("Fan-in", m.fan_in.to_string()),
+        ("Fan-out", m.fan_out.to_string()),
+        ("Dependents", m.dependents.to_string()),
+        ("Depth", m.depth.to_string()),
+        ("Instability", format!("{:.2}", m.instability)),
+    ]);
+    writeln!(out, "<table>")?;
+    for (label, value) in fields {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", label, value)?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<h2>Dependencies</h2>")?;
+    if c.deps.is_empty() {
+        writeln!(out, "<p>None.</p>")?;
+    } else {
+        writeln!(
+            out,
+            "<table>\n<tr><th>Crate</th><th>Requirement</th><th>Section</th><th>Optional</th></tr>"
+        )?;
+        for d in c.deps.iter() {
+            // Only the crates of the repositories have a page
+            let name = match index.get(&d.name) {
+                Some((dep_repo, _)) => format!(
+                    "{} [{}]",
+                    crate_link("../", &d.name),
+                    repo_link("../", dep_repo)
+                ),
+                None => html_escape(&d.name),
+            };
+            writeln!(
+                out,
+                "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                name,
+                html_escape(&d.req),
+                d.kind.section(),
+                match d.optional {
+                    true => "yes",
+                    false => "no",
+                }
+            )?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    writeln!(out, "<h2>Dependents</h2>")?;
+    if dependents.is_empty() {
+        writeln!(out, "<p>None.</p>")?;
+    } else {
+        writeln!(out, "<ul>")?;
+        for (dep_repo, dependent) in dependents {
+            writeln!(
+                out,
+                "<li>{} [{}]</li>",
+                crate_link("../", &dependent.name),
+                repo_link("../", dep_repo)
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
@@ -0,0 +1,734 @@
+//! Backends fetching the crates of a repository, selected by the section of the configuration
+//! the repository is listed in
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context as _;
+
+use crate::{
+    codeowners::{self, CodeOwners},
+    enabling_features, parse_manifest, tarball_files, CrateInfo, DepKind, Dependency, Error,
+    Fetcher, Publish, RepoTimings, Result, SourceSize, TargetKind,
+};
+
+/// Backend fetching the crates of a repository
+pub trait Source: Send + Sync {
+    /// Fetch and parse the crates of the repository
+    fn fetch(&self, cx: &mut Context) -> Result<Vec<CrateInfo>>;
+}
+
+/// What a [`Source`] fetches a repository with
+pub struct Context<'a> {
+    /// Fetcher of all the repositories, to download files and honor its options
+    pub fetcher: &'a Fetcher,
+    /// Name of the repository
+    pub repo: &'a str,
+    /// Progress bar of the repository
+    pub bar: &'a indicatif::ProgressBar,
+    /// Time spent in each phase, to fill in
+    pub timings: &'a mut RepoTimings,
+}
+
+/// Tarball of the repository downloaded over HTTP, from `[tarballs]`
+pub struct Tarball {
+    /// URL of the tarball, possibly gzipped
+    pub url: String,
+    /// Ref the URL is pinned to, from `[pins]`
+    pub rev: Option<String>,
+}
+
+impl Source for Tarball {
+    fn fetch(&self, cx: &mut Context) -> Result<Vec<CrateInfo>> {
+        // A monorepo can be listed under several names, to be downloaded and parsed only once
+        let mut res = cx.fetcher.fetch_once(cx.repo, &self.url, || {
+            handle_tarball(cx.fetcher, cx.repo, &self.url, cx.bar, cx.timings)
+        })?;
+        for info in res.iter_mut() {
+            info.revision = self.rev.clone();
+        }
+        Ok(res)
+    }
+}
+
+/// Repository checked out locally, from `[directories]`
+pub struct Directory {
+    /// Root of the checkout
+    pub path: PathBuf,
+}
+
+impl Source for Directory {
+    fn fetch(&self, cx: &mut Context) -> Result<Vec<CrateInfo>> {
+        cx.bar.set_prefix(&format!("{}: scanning", cx.repo));
+        scan_directory(cx.fetcher, cx.repo, &self.path, cx.timings)
+    }
+}
+
+/// Git repository cloned with the `git` command, from `[git]`
+pub struct Git {
+    /// URL to clone
+    pub url: String,
+    /// Tag, branch or commit to check out, from `[pins]`, instead of the default branch
+    pub rev: Option<String>,
+}
+
+impl Source for Git {
+    fn fetch(&self, cx: &mut Context) -> Result<Vec<CrateInfo>> {
+        let start = std::time::Instant::now();
+        tracing::info!(repo = cx.repo, url = %self.url, "Cloning repository");
+        cx.bar.set_prefix(&format!("{}: cloning", cx.repo));
+        let dest = cx.fetcher.temp_dir().join(cx.repo);
+        if dest.exists() {
+            std::fs::remove_dir_all(&dest)
+                .with_context(|| format!("Failed to remove the previous clone {:?}", dest))
+                .map_err(Error::Fetch)?;
+        }
+        // git has no connection timeout, but aborts transfers that stall for longer than
+        // `http.lowSpeedTime`, and the whole clone is bounded by the total timeout
+        let (connect_timeout, timeout) = cx.fetcher.timeouts(cx.repo);
+        let deadline = timeout.map(|t| start + t);
+        let low_speed_time = format!(
+            "http.lowSpeedTime={}",
+            connect_timeout.map_or(30, |t| t.as_secs().max(1))
+        );
+        let git = |args: &[&str]| {
+            let mut command = std::process::Command::new("git");
+            command
+                .args(["-c", "http.lowSpeedLimit=1", "-c", &low_speed_time])
+                .args(args);
+            let out = output_until(&mut command, deadline)
+                .context("Failed to run git")
+                .map_err(Error::Fetch)?;
+            if !out.status.success() {
+                return Err(Error::Fetch(anyhow::anyhow!(
+                    "Failed to clone {:?}: {}",
+                    self.url,
+                    String::from_utf8_lossy(&out.stderr).trim()
+                )));
+            }
+            Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+        };
+        let dir = &dest.to_string_lossy() as &str;
+        match &self.rev {
+            // Fetching the ref alone works for commits too, unlike `git clone --branch`
+            Some(rev) => {
+                git(&["init", "--quiet", dir])?;
+                git(&[
+                    "-C", dir, "fetch", "--quiet", "--depth", "1", &self.url, rev,
+                ])?;
+                git(&["-C", dir, "checkout", "--quiet", "FETCH_HEAD"])?;
+            }
+            None => git(&["clone", "--quiet", "--depth", "1", &self.url, dir]).map(drop)?,
+        }
+        let commit = git(&["-C", dir, "rev-parse", "HEAD"])?;
+        tracing::debug!(repo = cx.repo, commit = %commit, "Cloned repository");
+        cx.timings.download = start.elapsed();
+        cx.bar.set_prefix(&format!("{}: parsing", cx.repo));
+        let mut res = scan_directory(cx.fetcher, cx.repo, &dest, cx.timings)?;
+        for info in res.iter_mut() {
+            info.revision = Some(commit.clone());
+        }
+        Ok(res)
+    }
+}
+
+/// Workspace whose crates are listed by `cargo metadata`, from `[cargo-metadata]`
+///
+/// Unlike the other sources, this resolves the fields inherited from the workspace, at the cost
+/// of requiring cargo and a valid workspace.
+pub struct CargoMetadata {
+    /// Root of the workspace
+    pub path: PathBuf,
+}
+
+impl Source for CargoMetadata {
+    fn fetch(&self, cx: &mut Context) -> Result<Vec<CrateInfo>> {
+        cx.bar
+            .set_prefix(&format!("{}: running cargo metadata", cx.repo));
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let (connect_timeout, timeout) = cx.fetcher.timeouts(cx.repo);
+        let mut command = std::process::Command::new(cargo);
+        command
+            .args([
+                "metadata",
+                "--format-version",
+                "1",
+                "--no-deps",
+                "--manifest-path",
+            ])
+            .arg(self.path.join("Cargo.toml"));
+        if let Some(connect_timeout) = connect_timeout {
+            command.env(
+                "CARGO_HTTP_TIMEOUT",
+                connect_timeout.as_secs().max(1).to_string(),
+            );
+        }
+        let out = output_until(&mut command, timeout.map(|t| std::time::Instant::now() + t))
+            .context("Failed to run cargo metadata")
+            .map_err(Error::Fetch)?;
+        if !out.status.success() {
+            return Err(Error::Fetch(anyhow::anyhow!(
+                "cargo metadata failed for {:?}: {}",
+                self.path,
+                String::from_utf8_lossy(&out.stderr).trim()
+            )));
+        }
+        let parse_start = std::time::Instant::now();
+        let metadata: serde_json::Value = serde_json::from_slice(&out.stdout)
+            .context("Failed to parse cargo metadata")
+            .map_err(Error::Parse)?;
+        let packages = metadata["packages"]
+            .as_array()
+            .context("cargo metadata did not list packages")
+            .map_err(Error::Parse)?;
+        // Manifest paths are absolute, and relative to the workspace in `CODEOWNERS`
+        let codeowners = read_codeowners(&self.path)?;
+        let registries = registry_names(&self.path);
+        let root = self
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| self.path.clone());
+        let res = packages
+            .iter()
+            .map(|package| {
+                let mut info = package_info(package, &registries)?;
+                if let Some(manifest) = package["manifest_path"].as_str() {
+                    let manifest = Path::new(manifest);
+                    if cx.fetcher.keep_manifests {
+                        if let Ok(contents) = std::fs::read(manifest) {
+                            cx.fetcher.keep_manifest(&info.name, &contents);
+                        }
+                    }
+                    let manifest = manifest.strip_prefix(&root).unwrap_or(manifest);
+                    if let Some(codeowners) = &codeowners {
+                        info.owners = codeowners.owners(manifest);
+                    }
+                    info.manifest = Some(manifest.to_path_buf());
+                }
+                Ok(info)
+            })
+            .collect::<anyhow::Result<_>>()
+            .map_err(Error::Parse);
+        cx.timings.parse = parse_start.elapsed();
+        res
+    }
+}
+
+/// Run `command` to completion like [`std::process::Command::output`], killing it if it is still
+/// running at `deadline`
+fn output_until(
+    command: &mut std::process::Command,
+    deadline: Option<std::time::Instant>,
+) -> anyhow::Result<std::process::Output> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return Ok(command.output()?),
+    };
+    let mut child = command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    // Read the pipes while waiting, so that the command does not block on a full pipe
+    let read = |pipe: Option<Box<dyn std::io::Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                pipe.read_to_end(&mut buf).ok();
+            }
+            buf
+        })
+    };
+    let stdout = read(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = read(child.stderr.take().map(|p| Box::new(p) as _));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if std::time::Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+            // The readers are left behind, as children of the command may keep the pipes open
+            anyhow::bail!("{:?} did not finish within network.timeout", command);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Names of the registries cargo knows of when run from `dir`, by index URL, from the
+/// `[registries]` tables of its configuration files and the `CARGO_REGISTRIES_<NAME>_INDEX`
+/// environment variables
+fn registry_names(dir: &Path) -> BTreeMap<String, String> {
+    let mut files = dir
+        .ancestors()
+        .flat_map(|d| [d.join(".cargo/config.toml"), d.join(".cargo/config")])
+        .collect::<Vec<_>>();
+    let home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    if let Some(home) = home {
+        files.extend([home.join("config.toml"), home.join("config")]);
+    }
+    let mut names = BTreeMap::new();
+    // The files closest to the directory take precedence, so they are read last
+    for file in files.iter().rev() {
+        let config = match std::fs::read_to_string(file).map(|c| c.parse::<toml::Value>()) {
+            Ok(Ok(config)) => config,
+            _ => continue,
+        };
+        let registries = config.get("registries").and_then(|r| r.as_table());
+        for (name, registry) in registries.into_iter().flatten() {
+            if let Some(index) = registry.get("index").and_then(|i| i.as_str()) {
+                names.insert(index_key(index), name.clone());
+            }
+        }
+    }
+    for (key, index) in std::env::vars() {
+        let name = key
+            .strip_prefix("CARGO_REGISTRIES_")
+            .and_then(|k| k.strip_suffix("_INDEX"));
+        if let Some(name) = name {
+            names.insert(
+                index_key(&index),
+                name.to_ascii_lowercase().replace('_', "-"),
+            );
+        }
+    }
+    names
+}
+
+/// Index URL of a registry without what cargo may add to or remove from it, to compare the URLs
+/// of the configuration with those of `cargo metadata`
+fn index_key(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_string()
+}
+
+/// Convert a package as listed by `cargo metadata`, the registries of its dependencies being
+/// named after `registries`, the names of the registries by index URL, unless they are unknown
+fn package_info(
+    package: &serde_json::Value,
+    registries: &BTreeMap<String, String>,
+) -> anyhow::Result<CrateInfo> {
+    let string = |v: &serde_json::Value| v.as_str().map(String::from);
+    let name = string(&package["name"]).context("Package without a name")?;
+    let set = |field: &str| match &package[field] {
+        serde_json::Value::Null => false,
+        serde_json::Value::Array(a) => !a.is_empty(),
+        _ => true,
+    };
+    let features =
+        serde_json::from_value::<BTreeMap<String, Vec<String>>>(package["features"].clone())
+            .unwrap_or_default();
+    let deps = package["dependencies"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|d| {
+            let optional = d["optional"].as_bool().unwrap_or_default();
+            // Features refer to the dependency by its key in the manifest, which is its rename
+            let key = string(&d["rename"]).or_else(|| string(&d["name"]));
+            Ok(Dependency {
+                name: string(&d["name"]).context("Dependency without a name")?,
+                key: string(&d["rename"]),
+                has_path: d["path"].is_string(),
+                // `cargo metadata` gives the index URL of the registry, while manifests name it
+                from: string(&d["registry"])
+                    .map(|index| registries.get(&index_key(&index)).cloned().unwrap_or(index)),
+                git: d["source"]
+                    .as_str()
+                    .and_then(|s| s.strip_prefix("git+"))
+                    .map(String::from),
+                req: string(&d["req"]).unwrap_or_else(|| String::from("*")),
+                kind: match d["kind"].as_str() {
+                    Some("dev") => DepKind::Dev,
+                    Some("build") => DepKind::Build,
+                    _ => DepKind::Normal,
+                },
+                optional,
+                enabled_by: match (optional, key) {
+                    (true, Some(key)) => enabling_features(&features, &key),
+                    _ => Vec::new(),
+                },
+            })
+        })
+        .collect::<anyhow::Result<_>>()
+        .with_context(|| format!("Invalid dependencies for package {}", name))?;
+    // Tests, examples, benches and build scripts are not among the target kinds
+    let mut targets = package["targets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|t| t["kind"].as_array().into_iter().flatten())
+        .filter_map(|k| match k.as_str()? {
+            "bin" => Some(TargetKind::Bin),
+            "proc-macro" => Some(TargetKind::ProcMacro),
+            "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" => Some(TargetKind::Lib),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    targets.sort_unstable();
+    targets.dedup();
+    Ok(CrateInfo {
+        version: string(&package["version"]).unwrap_or_default(),
+        edition: string(&package["edition"]).unwrap_or_else(|| String::from("2015")),
+        license: string(&package["license"]),
+        description: string(&package["description"]),
+        keywords: package["keywords"]
+            .as_array()
+            .map(|k| k.iter().filter_map(string).collect())
+            .unwrap_or_default(),
+        metadata_fields: crate::METADATA_FIELDS
+            .iter()
+            .filter(|f| set(f))
+            .map(|f| f.to_string())
+            .collect(),
+        published_to: match package["publish"].as_array() {
+            None => Publish::Default,
+            Some(r) if r.is_empty() => Publish::Nowhere,
+            Some(r) => Publish::At(r.iter().filter_map(string).collect()),
+        },
+        deps,
+        name,
+        manifest: None,
+        owners: Vec::new(),
+        targets,
+        revision: None,
+        size: None,
+    })
+}
+
+/// Whether the manifests at `paths` in an archive are all in the same top-level directory
+fn archive_top_level<'p>(paths: impl Iterator<Item = &'p Path>) -> bool {
+    let mut top_level = None;
+    for path in paths {
+        let mut components = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)));
+        let first = components.next();
+        if components.next().is_none() || top_level.is_some_and(|t| Some(t) != first) {
+            return false;
+        }
+        top_level = first;
+    }
+    top_level.is_some()
+}
+
+/// Sizes of the packages of the manifests at `manifests`, from the size of the Rust sources of
+/// each directory, which belong to the closest package containing them
+fn package_sizes(
+    manifests: &[PathBuf],
+    sources: &BTreeMap<PathBuf, SourceSize>,
+) -> Vec<SourceSize> {
+    let packages = manifests
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| Some((m.parent()?, i)))
+        .collect::<BTreeMap<_, _>>();
+    let mut res = vec![SourceSize::default(); manifests.len()];
+    for (dir, size) in sources {
+        if let Some(i) = dir.ancestors().find_map(|d| packages.get(d)) {
+            res[*i] += *size;
+        }
+    }
+    res
+}
+
+/// Rules of the `CODEOWNERS` file of the repository checked out in `dir`, if it has one
+fn read_codeowners(dir: &Path) -> Result<Option<CodeOwners>> {
+    for location in codeowners::LOCATIONS {
+        let path = dir.join(location);
+        if path.is_file() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {:?}", path))
+                .map_err(Error::Fetch)?;
+            return Ok(Some(CodeOwners::parse(&contents)));
+        }
+    }
+    Ok(None)
+}
+
+/// Files of the package in `dir` that Cargo discovers targets from, relative to `dir`
+fn target_files(dir: &Path) -> Vec<PathBuf> {
+    let bins = Path::new("src/bin");
+    let mut files = vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/main.rs")];
+    for entry in std::fs::read_dir(dir.join(bins))
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let file = bins.join(entry.file_name());
+        files.push(file.join("main.rs"));
+        files.push(file);
+    }
+    files.retain(|file| dir.join(file).is_file());
+    files
+}
+
+/// Parse all the crates of a repository checked out in `dir`
+fn scan_directory(
+    fetcher: &Fetcher,
+    name: &str,
+    dir: &Path,
+    timings: &mut RepoTimings,
+) -> Result<Vec<CrateInfo>> {
+    tracing::info!(repo = name, dir = ?dir, "Scanning repository");
+    let codeowners = read_codeowners(dir)?;
+    let root = dir;
+    let mut res = Vec::new();
+    // Manifests of the crates of `res`, and size of the Rust sources of each directory
+    let mut manifests = Vec::new();
+    let mut sources = BTreeMap::<PathBuf, SourceSize>::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to list {:?}", dir))
+            .map_err(Error::Fetch)?;
+        for entry in entries {
+            let entry = entry
+                .with_context(|| format!("Failed to list {:?}", dir))
+                .map_err(Error::Fetch)?;
+            let path = entry.path();
+            let file_name = entry.file_name();
+            if path.is_dir() {
+                // Skip build outputs and hidden directories like .git
+                if file_name != "target" && !file_name.to_string_lossy().starts_with('.') {
+                    pending.push(path);
+                }
+            } else if file_name == "Cargo.toml" {
+                let manifest = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {:?}", path))
+                    .map_err(Error::Fetch)?;
+                let parse_start = std::time::Instant::now();
+                let info = parse_manifest(&manifest)
+                    .map_err(|e| e.context(format!("Failed to parse manifest {:?}", path)));
+                let mut info = fetcher.skip_invalid(name, &path, info)?;
+                if let Some(info) = &mut info {
+                    fetcher.keep_manifest(&info.name, &manifest);
+                    info.discover_targets(target_files(&dir).iter().map(PathBuf::as_path));
+                    let manifest = path.strip_prefix(root).unwrap_or(&path);
+                    if let Some(codeowners) = &codeowners {
+                        info.owners = codeowners.owners(manifest);
+                    }
+                    info.manifest = Some(manifest.to_path_buf());
+                }
+                timings.parse += parse_start.elapsed();
+                if let Some(info) = info {
+                    res.push(info);
+                    manifests.push(path);
+                }
+            } else if fetcher.measure_size && path.extension() == Some(OsStr::new("rs")) {
+                let contents = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {:?}", path))
+                    .map_err(Error::Fetch)?;
+                *sources.entry(dir.clone()).or_default() += SourceSize::of(&contents);
+            }
+        }
+    }
+    if fetcher.measure_size {
+        for (info, size) in res.iter_mut().zip(package_sizes(&manifests, &sources)) {
+            info.size = Some(size);
+        }
+    }
+    tracing::debug!(repo = name, crates = res.len(), "Scanned repository");
+    Ok(res)
+}
+
+fn handle_tarball(
+    fetcher: &Fetcher,
+    name: &str,
+    url: &str,
+    bar: &indicatif::ProgressBar,
+    timings: &mut RepoTimings,
+) -> Result<Vec<CrateInfo>> {
+    let start = std::time::Instant::now();
+    tracing::info!(repo = name, url, "Fetching repository");
+    bar.reset_elapsed();
+
+    // Figure out the size of the download
+    // TODO: It looks like this significantly slows down the process. Also, trying to use HEAD
+    // instead of GET is even slower. Let's not have a pretty progress bar for now, it's probably
+    // not a big deal anyway.
+    /*
+    bar.set_prefix(&format!("{}: figuring out the size", name));
+    let r = client
+        .get(url)
+        .send()
+        .with_context(|| format!("Failed to send HEAD request to URL {:?}", url))?;
+    anyhow::ensure!(
+        r.status().is_success(),
+        "HEAD request to {:?} was unsuccessful",
+        url
+    );
+    if let Some(l) = r.content_length() {
+        bar.inc_length(l);
+        bar.set_style(
+            indicatif::ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes:<8}/{total_bytes:8} ({eta}) {prefix}")
+                .progress_chars("=>-"),
+        );
+    }
+    std::mem::drop(r);
+    */
+
+    // Download the (compressed) archive file
+    bar.set_prefix(&format!("{}: downloading", name));
+    let archive_path = fetcher.download(name, url, bar)?;
+    timings.download = start.elapsed();
+    tracing::debug!(
+        repo = name,
+        elapsed = ?start.elapsed(),
+        "Downloaded repository"
+    );
+
+    // Parse the manifests across threads as they are extracted from the tarball, uncompressing
+    // it if necessary, as parsing dominates once the downloads are cached: each manifest is
+    // dropped once parsed, rather than all of them being held in memory first
+    bar.set_prefix(&format!("{}: parsing", name));
+    let archive = std::fs::File::open(&archive_path)
+        .with_context(|| format!("Failed to open the file at {:?} for reading", archive_path))
+        .map_err(Error::Decode)?;
+    let parsed = std::sync::Mutex::new(Vec::new());
+    let mut extracted = std::time::Duration::default();
+    // Best `CODEOWNERS` file so far, with its priority and the root of the repository
+    let mut codeowners = None::<(usize, PathBuf, Vec<u8>)>;
+    // Paths of the files Cargo discovers targets from, and size of the Rust sources of each
+    // directory if measured
+    let mut targets = BTreeSet::new();
+    let mut sources = BTreeMap::<PathBuf, SourceSize>::new();
+    let wanted = |path: &Path| match path.file_name().and_then(OsStr::to_str) {
+        Some("Cargo.toml" | "CODEOWNERS" | "lib.rs" | "main.rs") => true,
+        _ => {
+            path.extension() == Some(OsStr::new("rs"))
+                && (fetcher.measure_size || path.ancestors().any(|a| a.ends_with("src/bin")))
+        }
+    };
+    rayon::scope(|s| {
+        let mut index = 0;
+        let res = tarball_files(archive, wanted, |path, manifest| {
+            if path.ends_with("CODEOWNERS") {
+                if let Some((priority, root)) = codeowners::tarball_location(&path) {
+                    if codeowners.as_ref().is_none_or(|(p, _, _)| priority < *p) {
+                        codeowners = Some((priority, root, manifest));
+                    }
+                }
+                return Ok(());
+            } else if !path.ends_with("Cargo.toml") {
+                if fetcher.measure_size {
+                    let dir = path.parent().unwrap_or(&path).to_path_buf();
+                    *sources.entry(dir).or_default() += SourceSize::of(&manifest);
+                }
+                targets.insert(path);
+                return Ok(());
+            }
+            let (parsed, archive_path) = (&parsed, &archive_path);
+            s.spawn(move |_| {
+                let info = parse_manifest(&manifest).map_err(|e| {
+                    e.context(format!(
+                        "Failed to parse file {:?} from downloaded tarball {:?} as a Cargo.toml file",
+                        path, archive_path
+                    ))
+                });
+                if let Ok(Some(info)) = &info {
+                    fetcher.keep_manifest(&info.name, &manifest);
+                }
+                parsed.lock().unwrap().push((index, path, info));
+            });
+            index += 1;
+            Ok(())
+        });
+        extracted = start.elapsed();
+        res
+    })?;
+    // Parsing overlaps with decompressing, so only the parsing left once the whole archive was
+    // read counts as parsing
+    timings.decompress = extracted - timings.download;
+    timings.parse = start.elapsed() - extracted;
+
+    // Handle the manifests in the order of the tarball, for the first invalid one to be reported
+    let mut parsed = parsed.into_inner().unwrap();
+    parsed.sort_unstable_by_key(|(index, _, _)| *index);
+    let codeowners = codeowners
+        .map(|(_, root, contents)| (root, CodeOwners::parse(&String::from_utf8_lossy(&contents))));
+    // Archives of GitHub and GitLab have all their files in a top-level directory, which is not
+    // part of the paths of the manifests in the repository
+    let top_level = archive_top_level(parsed.iter().map(|(_, path, _)| path as &Path));
+    let mut res = Vec::new();
+    let mut manifests = Vec::new();
+    for (_, path, info) in parsed {
+        if let Some(mut info) = fetcher.skip_invalid(name, &path, info)? {
+            let dir = path.parent().unwrap_or(&path);
+            info.discover_targets(targets.iter().filter_map(|t| t.strip_prefix(dir).ok()));
+            if let Some((root, codeowners)) = &codeowners {
+                info.owners = codeowners.owners(path.strip_prefix(root).unwrap_or(&path));
+            }
+            info.manifest = Some(
+                path.components()
+                    .filter(|c| matches!(c, Component::Normal(_)))
+                    .skip(top_level as usize)
+                    .collect(),
+            );
+            tracing::debug!(repo = name, manifest = ?path, krate = %info.name, "Found crate");
+            res.push(info);
+            manifests.push(path);
+        }
+    }
+    if fetcher.measure_size {
+        for (info, size) in res.iter_mut().zip(package_sizes(&manifests, &sources)) {
+            info.size = Some(size);
+        }
+    }
+
+    tracing::info!(
+        repo = name,
+        crates = res.len(),
+        elapsed = ?start.elapsed(),
+        "Fetched repository"
+    );
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registries_of_cargo_metadata_are_named() {
+        let package = serde_json::json!({
+            "name": "app",
+            "version": "0.1.0",
+            "dependencies": [
+                { "name": "core", "req": "^1", "registry": "sparse+https://cargo.example.com/index/" },
+                { "name": "other", "req": "^1", "registry": "https://elsewhere.example.com/index" },
+                { "name": "serde", "req": "^1" },
+            ],
+        });
+        let registries = BTreeMap::from([(
+            index_key("sparse+https://cargo.example.com/index"),
+            String::from("internal"),
+        )]);
+        let info = package_info(&package, &registries).unwrap();
+        let from = info
+            .deps
+            .iter()
+            .map(|d| d.from.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            from,
+            vec![
+                Some("internal"),
+                Some("https://elsewhere.example.com/index"),
+                None
+            ]
+        );
+    }
+}
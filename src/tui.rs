@@ -0,0 +1,308 @@
+//! Interactive terminal interface to browse the crates, their dependencies and the cycles
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListState, Paragraph},
+    Frame,
+};
+
+use muregraph::{
+    adjacency, all_crates, find_cycles, graph::DependencyGraph, reverse_adjacency, CrateInfo,
+    DepKind,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Pane {
+    Crates,
+    Deps,
+    Rdeps,
+    Cycles,
+}
+
+const PANES: [Pane; 4] = [Pane::Crates, Pane::Deps, Pane::Rdeps, Pane::Cycles];
+
+struct App<'a> {
+    /// All the crates, as `(repository, crate)`
+    crates: Vec<(&'a str, &'a str)>,
+    repo_of: BTreeMap<&'a str, &'a str>,
+    deps: BTreeMap<&'a str, Vec<&'a str>>,
+    rdeps: BTreeMap<&'a str, Vec<&'a str>>,
+    cycles: Vec<Vec<(String, String)>>,
+    search: String,
+    searching: bool,
+    focus: Pane,
+    lists: [ListState; 4],
+}
+
+impl<'a> App<'a> {
+    fn new(infos: &'a BTreeMap<String, Vec<CrateInfo>>) -> App<'a> {
+        let crates = all_crates(infos)
+            .map(|(repo, c)| (repo, &c.name as &str))
+            .collect::<Vec<_>>();
+        let deps = adjacency(
+            &DependencyGraph::new(infos),
+            &[DepKind::Normal, DepKind::Dev, DepKind::Build],
+        );
+        let rdeps = reverse_adjacency(&deps);
+        let mut lists = <[ListState; 4]>::default();
+        lists[0].select(Some(0));
+        App {
+            repo_of: crates.iter().map(|(r, c)| (*c, *r)).collect(),
+            crates,
+            deps,
+            rdeps,
+            cycles: find_cycles(infos),
+            search: String::new(),
+            searching: false,
+            focus: Pane::Crates,
+            lists,
+        }
+    }
+
+    fn list(&mut self, pane: Pane) -> &mut ListState {
+        &mut self.lists[PANES.iter().position(|p| *p == pane).unwrap()]
+    }
+
+    /// Crates matching the search, by crate or repository name
+    fn matching(&self) -> Vec<(&'a str, &'a str)> {
+        self.crates
+            .iter()
+            .filter(|(r, c)| c.contains(&self.search) || r.contains(&self.search))
+            .copied()
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&'a str> {
+        let i = self.lists[0].selected()?;
+        self.matching().get(i).map(|(_, c)| *c)
+    }
+
+    /// Items of the dependency, reverse dependency and cycle panes
+    fn items(&self, pane: Pane) -> Vec<String> {
+        let styled = |c: &str| format!("{} [{}]", c, self.repo_of.get(c).unwrap_or(&"?"));
+        match (pane, self.selected()) {
+            (Pane::Deps, Some(c)) => self.deps[c].iter().map(|d| styled(d)).collect(),
+            (Pane::Rdeps, Some(c)) => self.rdeps[c].iter().map(|d| styled(d)).collect(),
+            (Pane::Cycles, _) => self
+                .cycles
+                .iter()
+                .map(|cycle| {
+                    let mut names = cycle.iter().map(|(_, c)| c as &str).collect::<Vec<_>>();
+                    // The paths come back to the repository of their first crate, maybe through
+                    // another of its crates, so they only need closing if they do not
+                    if cycle.first().map(|(r, _)| r) != cycle.last().map(|(r, _)| r) {
+                        names.push(names[0]);
+                    }
+                    names.join(" -> ")
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn len(&self, pane: Pane) -> usize {
+        match pane {
+            Pane::Crates => self.matching().len(),
+            pane => self.items(pane).len(),
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.len(self.focus);
+        let list = self.list(self.focus);
+        if len == 0 {
+            list.select(None);
+            return;
+        }
+        let current = list.selected().unwrap_or(0) as isize;
+        list.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    /// Select crate `name` in the crate list, clearing the search if it hides the crate
+    fn jump(&mut self, name: &str) {
+        if !self.matching().iter().any(|(_, c)| *c == name) {
+            self.search.clear();
+        }
+        let i = self.matching().iter().position(|(_, c)| *c == name);
+        self.lists[0].select(i);
+        self.focus = Pane::Crates;
+    }
+
+    fn activate(&mut self) {
+        let i = match self.list(self.focus).selected() {
+            Some(i) => i,
+            None => return,
+        };
+        let target = match (self.focus, self.selected()) {
+            (Pane::Deps, Some(c)) => self.deps[c].get(i).copied(),
+            (Pane::Rdeps, Some(c)) => self.rdeps[c].get(i).copied(),
+            (Pane::Cycles, _) => self
+                .cycles
+                .get(i)
+                .and_then(|cycle| cycle.first())
+                .and_then(|(_, c)| self.repo_of.get_key_value(c as &str))
+                .map(|(c, _)| *c),
+            _ => None,
+        };
+        if let Some(target) = target {
+            self.jump(target);
+        }
+    }
+
+    /// Handle a key press, returning whether to quit
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.searching {
+            match key {
+                KeyCode::Char(c) => self.search.push(c),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                }
+                KeyCode::Enter => self.searching = false,
+                _ => (),
+            }
+            self.lists[0].select(Some(0));
+            return false;
+        }
+        match key {
+            KeyCode::Char('q') => return true,
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.focus = Pane::Crates;
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                let i = PANES.iter().position(|p| *p == self.focus).unwrap();
+                let next = match key {
+                    KeyCode::Tab => (i + 1) % PANES.len(),
+                    _ => (i + PANES.len() - 1) % PANES.len(),
+                };
+                self.focus = PANES[next];
+                if self.list(self.focus).selected().is_none() {
+                    self.move_selection(0);
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::Enter => self.activate(),
+            _ => (),
+        }
+        false
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search, main, cycles, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [crates, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [deps, rdeps] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        let search_style = match self.searching {
+            true => Style::default().fg(Color::Yellow),
+            false => Style::default(),
+        };
+        frame.render_widget(
+            Paragraph::new(self.search.as_str())
+                .style(search_style)
+                .block(Block::default().borders(Borders::ALL).title("Search")),
+            search,
+        );
+
+        let selected = self.selected();
+        let items = self
+            .matching()
+            .iter()
+            .map(|(r, c)| format!("{} [{}]", c, r))
+            .collect::<Vec<_>>();
+        self.draw_list(frame, Pane::Crates, "Crates", items, crates);
+        let title = |t: &str| match selected {
+            Some(c) => format!("{} of {}", t, c),
+            None => t.to_string(),
+        };
+        self.draw_list(
+            frame,
+            Pane::Deps,
+            &title("Dependencies"),
+            self.items(Pane::Deps),
+            deps,
+        );
+        self.draw_list(
+            frame,
+            Pane::Rdeps,
+            &title("Dependents"),
+            self.items(Pane::Rdeps),
+            rdeps,
+        );
+        let title = format!("Cycles across repositories ({})", self.cycles.len());
+        self.draw_list(
+            frame,
+            Pane::Cycles,
+            &title,
+            self.items(Pane::Cycles),
+            cycles,
+        );
+
+        frame.render_widget(
+            Paragraph::new("/ search  tab switch pane  ↑↓ move  enter go to crate  q quit"),
+            help,
+        );
+    }
+
+    fn draw_list(
+        &mut self,
+        frame: &mut Frame,
+        pane: Pane,
+        title: &str,
+        items: Vec<String>,
+        area: Rect,
+    ) {
+        let border = match self.focus == pane {
+            true => Style::default().fg(Color::Cyan),
+            false => Style::default(),
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title(title.to_string()),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, self.list(pane));
+    }
+}
+
+pub fn run(infos: &BTreeMap<String, Vec<CrateInfo>>) -> anyhow::Result<()> {
+    let mut app = App::new(infos);
+    let mut terminal = ratatui::try_init().context("Failed to initialize the terminal")?;
+    let res = (|| -> anyhow::Result<()> {
+        loop {
+            terminal
+                .draw(|frame| app.draw(frame))
+                .context("Failed to draw the interface")?;
+            if let Event::Key(key) = event::read().context("Failed to read the terminal events")? {
+                if key.kind == KeyEventKind::Press && app.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::try_restore().context("Failed to restore the terminal")?;
+    res
+}
@@ -0,0 +1,148 @@
+//! Browser-friendly API, for the crate compiled to wasm32 with wasm-bindgen
+//!
+//! There is no `Fetcher` in the browser: repositories are added to a [`Workspace`] from
+//! the bytes of their tarballs, eg. of files dropped by the user or downloaded with
+//! [`fetch_tarball`], and the graph is then rendered client-side.
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    check_unique_names, lint, parse_manifest, render, tarball_manifests, CrateInfo, Format,
+    GraphType, InvalidManifest, LintConfig, Ownership, PolicyConfig, RenderOptions,
+};
+
+fn js_error(e: impl std::fmt::Display) -> JsValue {
+    JsValue::from_str(&format!("{:#}", e))
+}
+
+/// Crates of the repositories added so far
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Workspace {
+    infos: BTreeMap<String, Vec<CrateInfo>>,
+    invalid_manifests: Vec<InvalidManifest>,
+}
+
+#[wasm_bindgen]
+impl Workspace {
+    /// Start with no repository
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    /// Add repository `repo` from its tarball, possibly gzipped, replacing it if it was already
+    /// added
+    ///
+    /// With `lenient`, the manifests that fail to parse are skipped instead, for the
+    /// `invalid-manifests` lint to report them.
+    #[wasm_bindgen(js_name = addTarball)]
+    pub fn add_tarball(
+        &mut self,
+        repo: &str,
+        tarball: &[u8],
+        lenient: bool,
+    ) -> Result<(), JsValue> {
+        let mut crates = Vec::new();
+        let mut invalid = Vec::new();
+        tarball_manifests(tarball, |path, manifest| {
+            match parse_manifest(&manifest) {
+                Ok(info) => crates.extend(info),
+                Err(e) if lenient => invalid.push(InvalidManifest {
+                    repo: repo.to_string(),
+                    path,
+                    error: format!("{:#}", e),
+                }),
+                Err(e) => return Err(e.context(format!("Failed to parse manifest {:?}", path))),
+            }
+            Ok(())
+        })
+        .map_err(js_error)?;
+        self.remove_repository(repo);
+        self.infos.insert(repo.to_string(), crates);
+        self.invalid_manifests.extend(invalid);
+        Ok(())
+    }
+
+    /// Remove repository `repo`, if it was added
+    #[wasm_bindgen(js_name = removeRepository)]
+    pub fn remove_repository(&mut self, repo: &str) {
+        self.infos.remove(repo);
+        self.invalid_manifests.retain(|m| m.repo != repo);
+    }
+
+    /// Render the graph in `format`, among `dot`, `mermaid` and `json`
+    ///
+    /// `svg` needs graphviz's `dot` command, so the DOT output is to be rendered in the browser
+    /// instead, eg. with viz.js.
+    pub fn render(
+        &self,
+        format: &str,
+        use_colors: bool,
+        condense: bool,
+    ) -> Result<String, JsValue> {
+        let format = format.parse::<Format>().map_err(js_error)?;
+        let graph_type = match use_colors {
+            true => GraphType::Colors,
+            false => GraphType::Cluster,
+        };
+        check_unique_names(&self.infos).map_err(js_error)?;
+        let options = RenderOptions {
+            graph_type,
+            condense,
+            ..RenderOptions::default()
+        };
+        let out = render(format, &options, &self.infos, &Ownership::default()).map_err(js_error)?;
+        String::from_utf8(out).map_err(js_error)
+    }
+
+    /// Run the lints enabled by `config`, in the TOML format of the `[lints]` section, returning
+    /// them as a JSON array of objects with their `id`, `severity` and `findings`
+    pub fn lint(&self, config: &str) -> Result<String, JsValue> {
+        let cfg: LintConfig = toml::from_str(config)
+            .map_err(|e| js_error(format!("Failed to parse the lint configuration: {}", e)))?;
+        cfg.validate().map_err(js_error)?;
+        check_unique_names(&self.infos).map_err(js_error)?;
+        let cx = lint::Context {
+            infos: &self.infos,
+            invalid_manifests: &self.invalid_manifests,
+            ownership: &Ownership::default(),
+            exceptions: &[],
+            today: chrono::Utc::now().date_naive(),
+        };
+        let results = lint::registry(&cfg, &PolicyConfig::default(), &Ownership::default())
+            .iter()
+            .map(|l| {
+                serde_json::json!({
+                    "id": l.id(),
+                    "severity": l.severity().to_string(),
+                    "findings": l.check(&cx),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::Value::Array(results).to_string())
+    }
+}
+
+/// Download a tarball with the browser's `fetch`, for [`Workspace::add_tarball`]
+///
+/// The server must allow cross-origin requests, unless it serves the page too.
+#[wasm_bindgen(js_name = fetchTarball)]
+pub async fn fetch_tarball(url: String) -> Result<js_sys::Uint8Array, JsValue> {
+    let window = web_sys::window().ok_or_else(|| js_error("There is no window to fetch from"))?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(js_error(format!(
+            "GET request to {:?} was unsuccessful: {}",
+            url,
+            response.status()
+        )));
+    }
+    let body = JsFuture::from(response.array_buffer()?).await?;
+    Ok(js_sys::Uint8Array::new(&body))
+}